@@ -1,13 +1,16 @@
-use crate::allocate::{STACK_ALLOCATOR, SYSTEM_ALLOCATOR};
+use crate::allocate::{SAllocatorRef, STACK_ALLOCATOR, SYSTEM_ALLOCATOR};
 use crate::collections::{SPoolHandle, SPool, SVec, SQueue};
 use crate::safewindows;
-use crate::utils::{SAABB, SRay, ray_intersects_aabb};
+use crate::math::{Vec3};
+use crate::utils::{SAABB, SBoundingSphere, SOBB, SFrustum, SRay, ray_intersects_aabb};
 
 pub type SNodeHandle = SPoolHandle<u16, u16>;
 
 #[derive(Clone)]
 struct SLeafNode<TOwner: Clone + PartialEq> {
+    // -- the enlarged bounds the tree is built around, tight_bounds is what the owner actually takes up
     bounds: SAABB,
+    tight_bounds: SAABB,
     parent: SNodeHandle,
     owner: TOwner,
 }
@@ -30,6 +33,43 @@ enum ENode<TOwner: Clone + PartialEq> {
 pub struct STree<TOwner: Clone + PartialEq> {
     nodes: SPool<ENode<TOwner>, u16, u16>,
     root: SNodeHandle,
+    fat_bounds_settings: SFatBoundsSettings,
+    update_stats: SUpdateStats,
+}
+
+// -- leaves are stored with bounds bigger than their owner, so small moves don't touch the tree at all.
+// -- The defaults keep leaves tight
+#[derive(Copy, Clone, Debug)]
+pub struct SFatBoundsSettings {
+    // -- added on every side
+    pub margin: f32,
+    // -- how many updates worth of displacement to stretch the bounds by, in the direction of travel
+    pub displacement_multiplier: f32,
+}
+
+// -- what update_entry ended up doing, since the stats were last reset
+#[derive(Copy, Clone, Default, Debug)]
+pub struct SUpdateStats {
+    pub updates: u64,
+    // -- still inside the fat bounds, nothing to do
+    pub contained: u64,
+    // -- new fat bounds fit in the parent, only ancestors' bounds changed
+    pub refits: u64,
+    pub reinserts: u64,
+}
+
+// -- anything the tree can be searched with, as long as it can tell whether it touches a node's bounds
+pub trait TBVHQueryVolume {
+    fn overlaps_bounds(&self, bounds: &SAABB) -> bool;
+}
+
+// -- keeps the overlapping pairs from the last update, so narrowphase can find out which pairs
+// -- began or ended overlapping without diffing everything itself
+pub struct SPairTracker<TOwner: Clone + PartialEq + PartialOrd> {
+    pairs: SVec<(TOwner, TOwner)>,
+    prev_pairs: SVec<(TOwner, TOwner)>,
+    began: SVec<(TOwner, TOwner)>,
+    ended: SVec<(TOwner, TOwner)>,
 }
 
 impl<TOwner: Clone + PartialEq> ENode<TOwner> {
@@ -93,6 +133,15 @@ impl<TOwner: Clone + PartialEq> ENode<TOwner> {
     }
 }
 
+impl Default for SFatBoundsSettings {
+    fn default() -> Self {
+        Self {
+            margin: 0.0,
+            displacement_multiplier: 0.0,
+        }
+    }
+}
+
 impl<TOwner: Clone + PartialEq> Default for ENode<TOwner> {
     fn default() -> Self {
         Self::Free
@@ -107,8 +156,6 @@ impl<TOwner: Clone + PartialEq> STree<TOwner> {
     }
 
     fn find_best_sibling(&self, query_node: SNodeHandle) -> SNodeHandle {
-        self.tree_valid();
-
         struct SSearch {
             node_handle: SNodeHandle,
             inherited_cost: f32,
@@ -169,9 +216,54 @@ impl<TOwner: Clone + PartialEq> STree<TOwner> {
         Self {
             nodes: SPool::create_default(&SYSTEM_ALLOCATOR(), 1024),
             root: SNodeHandle::default(),
+            fat_bounds_settings: SFatBoundsSettings::default(),
+            update_stats: SUpdateStats::default(),
         }
     }
 
+    pub fn new_with_capacity(max_nodes: u16) -> Self {
+        Self {
+            nodes: SPool::create_default(&SYSTEM_ALLOCATOR(), max_nodes),
+            root: SNodeHandle::default(),
+            fat_bounds_settings: SFatBoundsSettings::default(),
+            update_stats: SUpdateStats::default(),
+        }
+    }
+
+    // -- only affects leaves inserted or updated afterwards
+    pub fn set_fat_bounds_settings(&mut self, settings: SFatBoundsSettings) {
+        self.fat_bounds_settings = settings;
+    }
+
+    pub fn update_stats(&self) -> SUpdateStats {
+        self.update_stats
+    }
+
+    pub fn reset_update_stats(&mut self) {
+        self.update_stats = SUpdateStats::default();
+    }
+
+    fn fatten(&self, tight_bounds: &SAABB, displacement: &Vec3) -> SAABB {
+        let margin = Vec3::new(self.fat_bounds_settings.margin, self.fat_bounds_settings.margin, self.fat_bounds_settings.margin);
+        let mut result = SAABB {
+            min: tight_bounds.min - margin,
+            max: tight_bounds.max + margin,
+        };
+
+        // -- only stretch towards where the owner is heading
+        let predicted = self.fat_bounds_settings.displacement_multiplier * *displacement;
+        result.min = Vec3::min(&result.min, &(result.min + predicted));
+        result.max = Vec3::max(&result.max, &(result.max + predicted));
+
+        result
+    }
+
+    // -- what the node pool of a tree created with max_nodes takes up, for budgeting
+    pub fn bytes_for_capacity(max_nodes: u16) -> usize {
+        // -- each slot has a node, a generation and a freelist entry
+        max_nodes as usize * (std::mem::size_of::<ENode<TOwner>>() + 2 * std::mem::size_of::<u16>())
+    }
+
     pub fn owner(&self, node_handle: SNodeHandle) -> TOwner {
         self.nodes.get(node_handle).expect("invalid entry").owner().expect("asked for owner of non-leaf!")
     }
@@ -245,61 +337,80 @@ impl<TOwner: Clone + PartialEq> STree<TOwner> {
         self.replace_child_without_updating_bounds(node_a_original_parent, node_a, node_b);
     }
 
+    // -- tree rotation as in Box2D's dynamic tree: swap a child with one of the other child's
+    // -- children, or swap a grandchild under each child, whichever shrinks the children the most.
+    // -- Done on the way up after every insert and remove, so the tree doesn't degrade as
+    // -- things move. node's own bounds don't change since it still holds the same leaves
     fn rotate_children_grandchildren(&mut self, node_handle: SNodeHandle) {
         #[derive(Clone, Copy)]
         struct SSwap {
-            child: SNodeHandle,
-            other_child: SNodeHandle,
-            grandchild: SNodeHandle,
+            node_a: SNodeHandle,
+            node_b: SNodeHandle,
             sa_diff: f32,
         }
-        let mut best_swap : Option<SSwap> = None;
 
-        if let ENode::Internal(internal) = self.nodes.get(node_handle).expect("pass valid handle") {
-            let mut test_grandchild = |
-                swap_child : SNodeHandle,
-                other_child : SNodeHandle,
-                swap_child_cur_sa : f32,
-                swap_grandchild : SNodeHandle,
-                other_grandchild : SNodeHandle,
-            | {
-                let possible_bounds = SAABB::union(
-                    &self.nodes.get(swap_child).expect("somehow bad handle").bounds(),
-                    &self.nodes.get(other_grandchild).expect("somehow bad handle").bounds(),
-                );
-                let possible_sa = possible_bounds.surface_area();
-                let sa_diff = swap_child_cur_sa - possible_sa;
-                if (sa_diff > 0.0) && (best_swap.is_none() || best_swap.expect("checked here").sa_diff < sa_diff) {
-                    best_swap = Some(SSwap{
-                        child: swap_child,
-                        other_child: other_child,
-                        grandchild: swap_grandchild,
-                        sa_diff: sa_diff,
-                    });
-                }
-            };
+        let (child1, child2) = match self.nodes.get(node_handle).expect("pass valid handle") {
+            ENode::Internal(internal) => (internal.child1, internal.child2),
+            _ => {
+                break_assert!(false);
+                return;
+            },
+        };
 
-            let mut test_child = |swap_child : SNodeHandle, other_child: SNodeHandle| {
-                let cur_bounds = self.nodes.get(other_child).expect("pass valid handle").bounds();
-                let cur_sa = cur_bounds.surface_area();
-                if let ENode::Internal(other_child_internal) = self.nodes.get(other_child).expect("pass valid handle") {
-                    test_grandchild(swap_child, other_child, cur_sa, other_child_internal.child1, other_child_internal.child2);
-                    test_grandchild(swap_child, other_child, cur_sa, other_child_internal.child2, other_child_internal.child1);
-                }
-            };
+        let children_of = |handle: SNodeHandle| -> Option<(SNodeHandle, SNodeHandle)> {
+            match self.nodes.get(handle).expect("somehow bad handle") {
+                ENode::Internal(internal) => Some((internal.child1, internal.child2)),
+                _ => None,
+            }
+        };
+        let sa = |handle: SNodeHandle| self.nodes.get(handle).expect("somehow bad handle").bounds().surface_area();
+        let union_sa = |a: SNodeHandle, b: SNodeHandle| self.union(a, b).surface_area();
 
-            // -- looking at grandchildren under child2
-            test_child(internal.child1, internal.child2);
-            test_child(internal.child2, internal.child1);
+        let mut best_swap : Option<SSwap> = None;
+        let mut test_swap = |node_a: SNodeHandle, node_b: SNodeHandle, sa_diff: f32| {
+            if sa_diff > 0.0 && best_swap.map_or(true, |best| best.sa_diff < sa_diff) {
+                best_swap = Some(SSwap{
+                    node_a,
+                    node_b,
+                    sa_diff,
+                });
+            }
+        };
+
+        // -- a child with a grandchild under the other child only changes the other child's bounds
+        for (swap_child, other_child) in &[(child1, child2), (child2, child1)] {
+            if let Some((grandchild1, grandchild2)) = children_of(*other_child) {
+                let cur_sa = sa(*other_child);
+                test_swap(*swap_child, grandchild1, cur_sa - union_sa(*swap_child, grandchild2));
+                test_swap(*swap_child, grandchild2, cur_sa - union_sa(*swap_child, grandchild1));
+            }
+        }
+
+        // -- swapping a grandchild from each side changes both children's bounds
+        if let (Some((d, e)), Some((f, g))) = (children_of(child1), children_of(child2)) {
+            let cur_sa = sa(child1) + sa(child2);
+            test_swap(d, f, cur_sa - (union_sa(f, e) + union_sa(d, g)));
+            test_swap(d, g, cur_sa - (union_sa(g, e) + union_sa(f, d)));
         }
 
-        if let Some(best_swap_int) = &best_swap {
-            self.swap_nodes_without_updating_bounds(best_swap_int.child, best_swap_int.grandchild);
-            self.update_bounds_from_children(best_swap_int.other_child);
+        if let Some(swap) = best_swap {
+            self.swap_nodes_without_updating_bounds(swap.node_a, swap.node_b);
+
+            // -- whatever moved kept its own bounds, only the children that gained or lost a node change
+            for child in &[child1, child2] {
+                if let ENode::Internal(_) = self.nodes.get(*child).expect("somehow bad handle") {
+                    self.update_bounds_from_children(*child);
+                }
+            }
         }
     }
 
     pub fn insert(&mut self, owner: TOwner, bounds: &SAABB, fixed_handle: Option<SNodeHandle>) -> Result<SNodeHandle, &'static str> {
+        let fat_bounds = self.fatten(bounds, &Vec3::zero());
+        self.insert_with_fat_bounds(owner, bounds, &fat_bounds, fixed_handle)
+    }
+
+    fn insert_with_fat_bounds(&mut self, owner: TOwner, bounds: &SAABB, fat_bounds: &SAABB, fixed_handle: Option<SNodeHandle>) -> Result<SNodeHandle, &'static str> {
         let first : bool = self.nodes.used() == 0;
         let leaf_handle = match fixed_handle {
             Some(h) => h,
@@ -310,7 +421,8 @@ impl<TOwner: Clone + PartialEq> STree<TOwner> {
         {
             let node = self.nodes.get_mut(leaf_handle).unwrap();
             *node = ENode::Leaf(SLeafNode{
-                bounds: bounds.clone(),
+                bounds: fat_bounds.clone(),
+                tight_bounds: bounds.clone(),
                 parent: Default::default(),
                 owner: owner,
             });
@@ -321,29 +433,35 @@ impl<TOwner: Clone + PartialEq> STree<TOwner> {
             return Ok(leaf_handle);
         }
 
-        // -- Step 1: find the best sibling for the new leaf
-        let sibling_handle = self.find_best_sibling(leaf_handle);
+        self.link_node(leaf_handle)?;
+        #[cfg(debug_assertions)]
+        self.tree_valid();
+
+        Ok(leaf_handle)
+    }
+
+    // -- puts a node that isn't in the tree yet, a leaf or the root of a subtree, next to its best sibling
+    fn link_node(&mut self, node_handle: SNodeHandle) -> Result<(), &'static str> {
+        // -- Step 1: find the best sibling for the new node
+        let sibling_handle = self.find_best_sibling(node_handle);
 
         // -- Step 2: create a new parent
         let old_parent_handle = self.nodes.get(sibling_handle).unwrap().parent();
 
-        let new_parent_handle = self.nodes.alloc().unwrap();
+        let new_parent_handle = self.nodes.alloc()?;
         {
-            let new_bounds = SAABB::union(
-                self.nodes.get(sibling_handle).unwrap().bounds(),
-                bounds,
-            );
+            let new_bounds = self.union(sibling_handle, node_handle);
             let new_parent = self.nodes.get_mut(new_parent_handle).unwrap();
             *new_parent = ENode::Internal(SInternalNode{
                 bounds: new_bounds,
                 parent: old_parent_handle,
                 child1: sibling_handle,
-                child2: leaf_handle,
+                child2: node_handle,
             });
         }
 
         self.nodes.get_mut(sibling_handle).unwrap().set_parent(new_parent_handle);
-        self.nodes.get_mut(leaf_handle).unwrap().set_parent(new_parent_handle);
+        self.nodes.get_mut(node_handle).unwrap().set_parent(new_parent_handle);
 
         if old_parent_handle.valid() {
             // -- sibling was not the root
@@ -367,7 +485,7 @@ impl<TOwner: Clone + PartialEq> STree<TOwner> {
         }
 
         // -- Step 3: walk up, refitting AABBs
-        let mut cur_handle = self.nodes.get(leaf_handle).unwrap().parent();
+        let mut cur_handle = self.nodes.get(node_handle).unwrap().parent();
         while cur_handle.valid() {
             self.update_bounds_from_children(cur_handle);
             self.rotate_children_grandchildren(cur_handle);
@@ -375,9 +493,189 @@ impl<TOwner: Clone + PartialEq> STree<TOwner> {
             cur_handle = self.nodes.get(cur_handle).unwrap().parent();
         }
 
+        Ok(())
+    }
+
+    // -- adds every (owner, bounds) in entries at once, which is much faster than inserting them one
+    // -- at a time and gives a better tree. The batch is built into a subtree top down, splitting
+    // -- wherever the binned surface area heuristic says is cheapest, and the subtree is then linked in
+    // -- like a single leaf. out gets the handle for each entry, in order
+    pub fn insert_batch(&mut self, entries: &[(TOwner, SAABB)], out: &mut SVec<SNodeHandle>) -> Result<(), &'static str> {
+        if entries.len() == 0 {
+            return Ok(());
+        }
+        if self.nodes.max() as usize - (self.nodes.used() as usize) < 2 * entries.len() {
+            return Err("not enough room in BVH pool for batch");
+        }
+
+        // -- a range of leaves that still needs a subtree built over it, to hang off parent
+        struct SBuildTask {
+            start: usize,
+            end: usize,
+            parent: SNodeHandle,
+        }
+
+        STACK_ALLOCATOR.with(|sa| -> Result<(), &'static str> {
+            let mut leaves = SVec::<SNodeHandle>::new(&sa.as_ref(), entries.len(), 0)?;
+            for (owner, bounds) in entries {
+                let leaf_handle = self.nodes.alloc()?;
+                *self.nodes.get_mut(leaf_handle).unwrap() = ENode::Leaf(SLeafNode{
+                    bounds: self.fatten(bounds, &Vec3::zero()),
+                    tight_bounds: bounds.clone(),
+                    parent: Default::default(),
+                    owner: owner.clone(),
+                });
+                leaves.push(leaf_handle);
+                out.push(leaf_handle);
+            }
+
+            let mut subtree_root = SNodeHandle::default();
+            let mut tasks = SVec::<SBuildTask>::new(&sa.as_ref(), entries.len(), 0)?;
+            tasks.push(SBuildTask{ start: 0, end: leaves.len(), parent: SNodeHandle::default() });
+
+            while let Some(task) = tasks.pop() {
+                let node_handle = if task.end - task.start == 1 {
+                    leaves[task.start]
+                }
+                else {
+                    let mid = self.split_by_binned_sah(&mut leaves.as_mut_slice()[task.start..task.end]) + task.start;
+
+                    let mut bounds = self.nodes.get(leaves[task.start]).unwrap().bounds().clone();
+                    for leaf_handle in &leaves.as_slice()[task.start + 1..task.end] {
+                        bounds = SAABB::union(&bounds, self.nodes.get(*leaf_handle).unwrap().bounds());
+                    }
+
+                    let internal_handle = self.nodes.alloc()?;
+                    *self.nodes.get_mut(internal_handle).unwrap() = ENode::Internal(SInternalNode{
+                        bounds,
+                        parent: SNodeHandle::default(),
+                        child1: SNodeHandle::default(),
+                        child2: SNodeHandle::default(),
+                    });
+
+                    tasks.push(SBuildTask{ start: task.start, end: mid, parent: internal_handle });
+                    tasks.push(SBuildTask{ start: mid, end: task.end, parent: internal_handle });
+
+                    internal_handle
+                };
+
+                if task.parent.valid() {
+                    self.nodes.get_mut(node_handle).unwrap().set_parent(task.parent);
+                    if let ENode::Internal(internal) = self.nodes.get_mut(task.parent).unwrap() {
+                        if !internal.child1.valid() {
+                            internal.child1 = node_handle;
+                        }
+                        else {
+                            internal.child2 = node_handle;
+                        }
+                    }
+                }
+                else {
+                    subtree_root = node_handle;
+                }
+            }
+
+            if self.root.valid() {
+                self.link_node(subtree_root)?;
+            }
+            else {
+                self.root = subtree_root;
+            }
+
+            Ok(())
+        })?;
+
+        #[cfg(debug_assertions)]
         self.tree_valid();
 
-        Ok(leaf_handle)
+        Ok(())
+    }
+
+    // -- reorders leaves so the ones before the returned index go in one child and the rest in the other.
+    // -- Centroids are dropped into bins along each axis, and the split between bins with the lowest
+    // -- count * surface area on both sides wins
+    fn split_by_binned_sah(&self, leaves: &mut [SNodeHandle]) -> usize {
+        const NUM_BINS : usize = 16;
+
+        let centroid = |leaf_handle: &SNodeHandle| self.nodes.get(*leaf_handle).unwrap().bounds().center();
+
+        let mut centroid_bounds = SAABB::new(&centroid(&leaves[0]));
+        for leaf_handle in &leaves[1..] {
+            centroid_bounds.expand(&centroid(leaf_handle));
+        }
+
+        let bin_for = |leaf_handle: &SNodeHandle, axis: usize| -> usize {
+            let extent = centroid_bounds.max[axis] - centroid_bounds.min[axis];
+            let bin = ((centroid(leaf_handle)[axis] - centroid_bounds.min[axis]) / extent * NUM_BINS as f32) as usize;
+            std::cmp::min(bin, NUM_BINS - 1)
+        };
+
+        // -- (axis, last bin on the left side, cost)
+        let mut best_split : Option<(usize, usize, f32)> = None;
+
+        for axis in 0..3 {
+            if centroid_bounds.max[axis] - centroid_bounds.min[axis] <= 0.000_01 {
+                continue;
+            }
+
+            let mut bin_counts = [0usize; NUM_BINS];
+            let mut bin_bounds : [Option<SAABB>; NUM_BINS] = [None; NUM_BINS];
+            for leaf_handle in leaves.iter() {
+                let bin = bin_for(leaf_handle, axis);
+                let leaf_bounds = self.nodes.get(*leaf_handle).unwrap().bounds();
+                bin_counts[bin] += 1;
+                bin_bounds[bin] = Some(match &bin_bounds[bin] {
+                    Some(bounds) => SAABB::union(bounds, leaf_bounds),
+                    None => leaf_bounds.clone(),
+                });
+            }
+
+            let union_bins = |bins: &[Option<SAABB>]| -> f32 {
+                let mut result : Option<SAABB> = None;
+                for bounds in bins.iter().flatten() {
+                    result = Some(match &result {
+                        Some(r) => SAABB::union(r, bounds),
+                        None => bounds.clone(),
+                    });
+                }
+                result.map_or(0.0, |r| r.surface_area())
+            };
+
+            for last_left_bin in 0..NUM_BINS - 1 {
+                let left_count : usize = bin_counts[..=last_left_bin].iter().sum();
+                let right_count = leaves.len() - left_count;
+                if left_count == 0 || right_count == 0 {
+                    continue;
+                }
+
+                let cost = left_count as f32 * union_bins(&bin_bounds[..=last_left_bin])
+                    + right_count as f32 * union_bins(&bin_bounds[last_left_bin + 1..]);
+                if best_split.map_or(true, |(_, _, best_cost)| cost < best_cost) {
+                    best_split = Some((axis, last_left_bin, cost));
+                }
+            }
+        }
+
+        // -- every centroid in the same spot, any split is as good as another
+        let (axis, last_left_bin) = match best_split {
+            Some((axis, last_left_bin, _)) => (axis, last_left_bin),
+            None => return leaves.len() / 2,
+        };
+
+        let mut mid = 0;
+        let mut end = leaves.len();
+        while mid < end {
+            if bin_for(&leaves[mid], axis) <= last_left_bin {
+                mid += 1;
+            }
+            else {
+                end -= 1;
+                leaves.swap(mid, end);
+            }
+        }
+
+        break_assert!(mid > 0 && mid < leaves.len());
+        mid
     }
 
     pub fn get_bvh_heirarchy_for_entry(&self, entry: SNodeHandle, output: &mut SVec<SAABB>) {
@@ -388,6 +686,8 @@ impl<TOwner: Clone + PartialEq> STree<TOwner> {
         }
     }
 
+    // -- walks the whole tree, so only for debug builds and tests
+    #[cfg(any(test, debug_assertions))]
     fn tree_valid(&self) -> bool {
         STACK_ALLOCATOR.with(|sa| -> bool {
             let mut search_queue = SQueue::<SNodeHandle>::new(&sa.as_ref(), self.nodes.used()).unwrap();
@@ -504,10 +804,60 @@ impl<TOwner: Clone + PartialEq> STree<TOwner> {
         })
     }
 
+    // -- guesses the displacement from how far the bounds moved since the last update
     pub fn update_entry(&mut self, entry: SNodeHandle, bounds: &SAABB) {
+        let displacement = match self.nodes.get(entry).expect("invalid entry") {
+            ENode::Leaf(leaf) => bounds.center() - leaf.tight_bounds.center(),
+            _ => panic!("updating a non-leaf node"),
+        };
+        self.update_entry_with_displacement(entry, bounds, &displacement);
+    }
+
+    // -- displacement is how far the entry is expected to move before its next update
+    pub fn update_entry_with_displacement(&mut self, entry: SNodeHandle, bounds: &SAABB, displacement: &Vec3) {
+        self.update_stats.updates += 1;
+
+        let parent = match self.nodes.get_mut(entry).expect("invalid entry") {
+            ENode::Leaf(leaf) => {
+                if SAABB::contains(&leaf.bounds, bounds) {
+                    leaf.tight_bounds = bounds.clone();
+                    self.update_stats.contained += 1;
+                    return;
+                }
+                leaf.parent
+            },
+            _ => panic!("updating a non-leaf node"),
+        };
+
+        let fat_bounds = self.fatten(bounds, displacement);
+
+        // -- if the parent still holds the leaf, the structure is fine and only the bounds above it change
+        let fits_parent = !parent.valid() || SAABB::contains(self.nodes.get(parent).expect("bad parent").bounds(), &fat_bounds);
+        if fits_parent {
+            if let ENode::Leaf(leaf) = self.nodes.get_mut(entry).expect("invalid entry") {
+                leaf.bounds = fat_bounds;
+                leaf.tight_bounds = bounds.clone();
+            }
+
+            // -- bounds can only shrink on the way up, stop once they don't
+            let mut cur_handle = parent;
+            while cur_handle.valid() {
+                let old_bounds = self.nodes.get(cur_handle).unwrap().bounds().clone();
+                self.update_bounds_from_children(cur_handle);
+                if *self.nodes.get(cur_handle).unwrap().bounds() == old_bounds {
+                    break;
+                }
+                cur_handle = self.nodes.get(cur_handle).unwrap().parent();
+            }
+
+            self.update_stats.refits += 1;
+            return;
+        }
+
         let owner = self.owner(entry);
         self.remove(entry.clone(), false);
-        self.insert(owner, bounds, Some(entry)).expect("allocation should never fail since we kept our handle");
+        self.insert_with_fat_bounds(owner, bounds, &fat_bounds, Some(entry)).expect("allocation should never fail since we kept our handle");
+        self.update_stats.reinserts += 1;
     }
 
     pub fn remove(&mut self, target_entry: SNodeHandle, free_entry: bool) {
@@ -570,10 +920,11 @@ impl<TOwner: Clone + PartialEq> STree<TOwner> {
 
                     self.nodes.get_mut(other_child_handle).expect("produced bad handle").set_parent(parent_parent_handle);
 
-                    // -- recompute AABBs up the tree
+                    // -- recompute AABBs up the tree, rebalancing as we go
                     let mut recompute_handle = parent_parent_handle;
                     while recompute_handle.valid() {
                         self.update_bounds_from_children(recompute_handle);
+                        self.rotate_children_grandchildren(recompute_handle);
                         recompute_handle = self.nodes.get(recompute_handle).expect("produced bad handle").parent();
                     }
                 }
@@ -602,6 +953,7 @@ impl<TOwner: Clone + PartialEq> STree<TOwner> {
             }
         }
 
+        #[cfg(debug_assertions)]
         self.tree_valid();
     }
 
@@ -687,6 +1039,28 @@ impl<TOwner: Clone + PartialEq> STree<TOwner> {
         }
     }
 
+    // -- surface area of every internal node relative to the root's, which is proportional to the
+    // -- expected number of nodes a random ray visits. Lower is better
+    pub fn compute_sah_cost(&self) -> f32 {
+        if !self.root.valid() {
+            return 0.0;
+        }
+
+        let root_sa = self.nodes.get(self.root).unwrap().bounds().surface_area();
+        if root_sa <= 0.0 {
+            return 0.0;
+        }
+
+        let mut internal_sa = 0.0;
+        for i in 0..self.nodes.max() {
+            if let ENode::Internal(internal) = self.nodes.get_by_index(i).expect("bounded loop") {
+                internal_sa += internal.bounds.surface_area();
+            }
+        }
+
+        internal_sa / root_sa
+    }
+
     // -- returns all leaf nodes, and the t to their start
     pub fn cast_ray(&self, ray: &SRay, out: &mut SVec<(f32, TOwner)>) {
         if self.nodes.used() == 0 {
@@ -714,7 +1088,276 @@ impl<TOwner: Clone + PartialEq> STree<TOwner> {
         });
     }
 
-    pub fn imgui_menu(&self, imgui_ui: &imgui::Ui, draw_selected_bvh: &mut bool) {
+    // -- calls on_leaf with the owner of every leaf whose fat bounds overlap volume, until it returns false
+    pub fn query_with<V: TBVHQueryVolume, F: FnMut(&TOwner) -> bool>(&self, volume: &V, mut on_leaf: F) {
+        if !self.root.valid() {
+            return;
+        }
+
+        STACK_ALLOCATOR.with(|sa| {
+            let mut to_search = SVec::<SNodeHandle>::new(&sa.as_ref(), self.nodes.used() as usize, 0).unwrap();
+            to_search.push(self.root);
+
+            while let Some(cur_handle) = to_search.pop() {
+                let node = self.nodes.get(cur_handle).unwrap();
+                if !volume.overlaps_bounds(node.bounds()) {
+                    continue;
+                }
+
+                if let ENode::Internal(internal) = node {
+                    to_search.push(internal.child1);
+                    to_search.push(internal.child2);
+                }
+                else if let ENode::Leaf(leaf) = node {
+                    if !on_leaf(&leaf.owner) {
+                        return;
+                    }
+                }
+            }
+        });
+    }
+
+    // -- appends the owner of every leaf whose fat bounds overlap volume. Returns false if out
+    // -- filled up before every leaf was found
+    pub fn query<V: TBVHQueryVolume>(&self, volume: &V, out: &mut SVec<TOwner>) -> bool {
+        let mut complete = true;
+        self.query_with(volume, |owner| {
+            if out.remaining_capacity() == 0 {
+                complete = false;
+                return false;
+            }
+            out.push(owner.clone());
+            true
+        });
+        complete
+    }
+
+    pub fn query_aabb(&self, aabb: &SAABB, out: &mut SVec<TOwner>) -> bool {
+        self.query(aabb, out)
+    }
+
+    // -- appends up to k owners whose fat bounds are within max_distance of point, nearest first, along
+    // -- with the distance to their bounds. Leaves with point inside them are at distance 0.
+    // -- Returns false if out filled up before k were found
+    pub fn query_nearest(&self, point: &Vec3, k: usize, max_distance: f32, out: &mut SVec<(f32, TOwner)>) -> bool {
+        if !self.root.valid() || k == 0 {
+            return true;
+        }
+
+        let max_sqdistance = max_distance * max_distance;
+
+        STACK_ALLOCATOR.with(|sa| {
+            let mut frontier = SVec::<(f32, SNodeHandle)>::new(&sa.as_ref(), self.nodes.used() as usize, 0).unwrap();
+            let root_sqdistance = self.nodes.get(self.root).unwrap().bounds().sqdistance_to_point(point);
+            if root_sqdistance <= max_sqdistance {
+                push_by_distance(&mut frontier, root_sqdistance, self.root);
+            }
+
+            let mut found = 0;
+            while let Some((sqdistance, cur_handle)) = frontier.pop() {
+                match self.nodes.get(cur_handle).unwrap() {
+                    ENode::Internal(internal) => {
+                        for child in &[internal.child1, internal.child2] {
+                            let child_sqdistance = self.nodes.get(*child).unwrap().bounds().sqdistance_to_point(point);
+                            if child_sqdistance <= max_sqdistance {
+                                push_by_distance(&mut frontier, child_sqdistance, *child);
+                            }
+                        }
+                    },
+                    ENode::Leaf(leaf) => {
+                        // -- nodes come off the frontier nearest first, so the first k leaves are the nearest k
+                        if out.remaining_capacity() == 0 {
+                            return false;
+                        }
+                        out.push((sqdistance.sqrt(), leaf.owner.clone()));
+
+                        found += 1;
+                        if found == k {
+                            break;
+                        }
+                    },
+                    ENode::Free => {
+                        break_assert!(false);
+                    },
+                }
+            }
+
+            true
+        })
+    }
+
+    // -- visits leaves in the order the ray enters their fat bounds, up to max_t. on_leaf gets the t
+    // -- the ray enters the leaf at and returns the max_t to keep searching to. Returning the t of a
+    // -- confirmed hit stops the search once nothing left can be closer, returning max_t keeps going
+    pub fn cast_ray_front_to_back<F: FnMut(f32, &TOwner) -> f32>(&self, ray: &SRay, max_t: f32, mut on_leaf: F) {
+        if !self.root.valid() {
+            return;
+        }
+
+        STACK_ALLOCATOR.with(|sa| {
+            let mut max_t = max_t;
+            let mut frontier = SVec::<(f32, SNodeHandle)>::new(&sa.as_ref(), self.nodes.used() as usize, 0).unwrap();
+            if let Some(t) = ray_intersects_aabb(ray, self.nodes.get(self.root).unwrap().bounds()) {
+                push_by_distance(&mut frontier, t, self.root);
+            }
+
+            while let Some((t, cur_handle)) = frontier.pop() {
+                if t > max_t {
+                    break;
+                }
+
+                match self.nodes.get(cur_handle).unwrap() {
+                    ENode::Internal(internal) => {
+                        for child in &[internal.child1, internal.child2] {
+                            if let Some(child_t) = ray_intersects_aabb(ray, self.nodes.get(*child).unwrap().bounds()) {
+                                if child_t <= max_t {
+                                    push_by_distance(&mut frontier, child_t, *child);
+                                }
+                            }
+                        }
+                    },
+                    ENode::Leaf(leaf) => {
+                        max_t = on_leaf(t, &leaf.owner);
+                    },
+                    ENode::Free => {
+                        break_assert!(false);
+                    },
+                }
+            }
+        });
+    }
+
+    // -- same as cast_ray, but sorted by t. Returns false if out filled up, what it holds is then
+    // -- still the nearest leaves
+    pub fn cast_ray_sorted(&self, ray: &SRay, out: &mut SVec<(f32, TOwner)>) -> bool {
+        let mut complete = true;
+        self.cast_ray_front_to_back(ray, std::f32::MAX, |t, owner| {
+            if out.remaining_capacity() == 0 {
+                complete = false;
+                return -1.0;
+            }
+            out.push((t, owner.clone()));
+            std::f32::MAX
+        });
+        complete
+    }
+
+    // -- pushes every pair of leaves under node_a in self and node_b in other whose bounds overlap
+    fn collect_overlapping_leaves<TOtherOwner: Clone + PartialEq>(
+        &self,
+        node_a: SNodeHandle,
+        other: &STree<TOtherOwner>,
+        node_b: SNodeHandle,
+        to_test: &mut SVec<(SNodeHandle, SNodeHandle)>,
+        out: &mut SVec<(TOwner, TOtherOwner)>,
+    ) -> bool {
+        to_test.push((node_a, node_b));
+
+        while let Some((cur_a, cur_b)) = to_test.pop() {
+            let a = self.nodes.get(cur_a).unwrap();
+            let b = other.nodes.get(cur_b).unwrap();
+
+            if !SAABB::overlaps(a.bounds(), b.bounds()) {
+                continue;
+            }
+
+            match (a, b) {
+                (ENode::Leaf(leaf_a), ENode::Leaf(leaf_b)) => {
+                    if out.remaining_capacity() == 0 {
+                        to_test.clear();
+                        return false;
+                    }
+                    out.push((leaf_a.owner.clone(), leaf_b.owner.clone()));
+                },
+                (ENode::Internal(internal_a), ENode::Leaf(_)) => {
+                    to_test.push((internal_a.child1, cur_b));
+                    to_test.push((internal_a.child2, cur_b));
+                },
+                (ENode::Leaf(_), ENode::Internal(internal_b)) => {
+                    to_test.push((cur_a, internal_b.child1));
+                    to_test.push((cur_a, internal_b.child2));
+                },
+                (ENode::Internal(internal_a), ENode::Internal(internal_b)) => {
+                    // -- descend the bigger node, so both sides shrink at about the same rate
+                    if internal_a.bounds.surface_area() >= internal_b.bounds.surface_area() {
+                        to_test.push((internal_a.child1, cur_b));
+                        to_test.push((internal_a.child2, cur_b));
+                    }
+                    else {
+                        to_test.push((cur_a, internal_b.child1));
+                        to_test.push((cur_a, internal_b.child2));
+                    }
+                },
+                _ => {
+                    break_assert!(false);
+                },
+            }
+        }
+
+        true
+    }
+
+    // -- every pair of owners whose leaves overlap, sorted, with the lower owner first in each pair
+    // -- returns false if out filled up before every pair was found
+    pub fn overlapping_pairs(&self, out: &mut SVec<(TOwner, TOwner)>) -> bool where TOwner: PartialOrd {
+        if !self.root.valid() {
+            return true;
+        }
+
+        let complete = STACK_ALLOCATOR.with(|sa| {
+            // -- the pair search only descends one side at a time, so its stack stays under 2x the tree height
+            let stack_size = 4 * self.nodes.used() as usize + 4;
+            let mut to_search = SVec::<SNodeHandle>::new(&sa.as_ref(), self.nodes.used() as usize, 0).unwrap();
+            let mut to_test = SVec::<(SNodeHandle, SNodeHandle)>::new(&sa.as_ref(), stack_size, 0).unwrap();
+            to_search.push(self.root);
+
+            // -- every overlapping pair of leaves is split between the two children of exactly one node
+            while let Some(cur_handle) = to_search.pop() {
+                if let ENode::Internal(internal) = self.nodes.get(cur_handle).unwrap() {
+                    if !self.collect_overlapping_leaves(internal.child1, self, internal.child2, &mut to_test, out) {
+                        return false;
+                    }
+                    to_search.push(internal.child1);
+                    to_search.push(internal.child2);
+                }
+            }
+
+            true
+        });
+
+        for pair in out.as_mut_slice() {
+            if pair.1 < pair.0 {
+                std::mem::swap(&mut pair.0, &mut pair.1);
+            }
+        }
+        sort_and_dedup_pairs(out);
+
+        complete
+    }
+
+    // -- every pair of owners from self and other whose leaves overlap, sorted
+    // -- returns false if out filled up before every pair was found
+    pub fn overlapping_pairs_with<TOtherOwner: Clone + PartialEq + PartialOrd>(
+        &self,
+        other: &STree<TOtherOwner>,
+        out: &mut SVec<(TOwner, TOtherOwner)>,
+    ) -> bool where TOwner: PartialOrd {
+        if !self.root.valid() || !other.root.valid() {
+            return true;
+        }
+
+        let complete = STACK_ALLOCATOR.with(|sa| {
+            let stack_size = 2 * (self.nodes.used() as usize + other.nodes.used() as usize) + 4;
+            let mut to_test = SVec::<(SNodeHandle, SNodeHandle)>::new(&sa.as_ref(), stack_size, 0).unwrap();
+            self.collect_overlapping_leaves(self.root, other, other.root, &mut to_test, out)
+        });
+
+        sort_and_dedup_pairs(out);
+
+        complete
+    }
+
+    pub fn imgui_menu(&mut self, imgui_ui: &imgui::Ui, draw_selected_bvh: &mut bool) {
         use imgui::*;
 
         if self.nodes.used() == 0 {
@@ -728,6 +1371,17 @@ impl<TOwner: Clone + PartialEq> STree<TOwner> {
             imgui_ui.menu(imgui::im_str!("BVH"), true, || {
                 imgui_ui.text(&im_str!("Tree height: {}", self.compute_height()));
                 imgui_ui.text(&im_str!("Average leaf height: {}", self.compute_average_leaf_height()));
+                imgui_ui.text(&im_str!("SAH cost: {}", self.compute_sah_cost()));
+
+                let stats = self.update_stats;
+                imgui_ui.text(&im_str!("Updates: {}", stats.updates));
+                imgui_ui.text(&im_str!("  Contained: {}", stats.contained));
+                imgui_ui.text(&im_str!("  Refit: {}", stats.refits));
+                imgui_ui.text(&im_str!("  Reinserted: {}", stats.reinserts));
+                if imgui_ui.small_button(im_str!("Reset stats")) {
+                    self.reset_update_stats();
+                }
+
                 imgui_ui.checkbox(&im_str!("Draw selected entity's BVH"), draw_selected_bvh);
 
                 while let Some(cur_handle) = to_show.pop() {
@@ -750,3 +1404,575 @@ impl<TOwner: Clone + PartialEq> STree<TOwner> {
     }
 }
 
+// -- frontier for best-first searches, kept sorted with the nearest node last so pop() returns it
+fn push_by_distance(frontier: &mut SVec<(f32, SNodeHandle)>, distance: f32, node: SNodeHandle) {
+    frontier.push((distance, node));
+
+    let entries = frontier.as_mut_slice();
+    let mut i = entries.len() - 1;
+    while i > 0 && entries[i - 1].0 < entries[i].0 {
+        entries.swap(i - 1, i);
+        i -= 1;
+    }
+}
+
+impl TBVHQueryVolume for SAABB {
+    fn overlaps_bounds(&self, bounds: &SAABB) -> bool {
+        SAABB::overlaps(self, bounds)
+    }
+}
+
+impl TBVHQueryVolume for SBoundingSphere {
+    fn overlaps_bounds(&self, bounds: &SAABB) -> bool {
+        self.overlaps_aabb(bounds)
+    }
+}
+
+impl TBVHQueryVolume for SOBB {
+    fn overlaps_bounds(&self, bounds: &SAABB) -> bool {
+        self.overlaps_aabb(bounds)
+    }
+}
+
+impl TBVHQueryVolume for SFrustum {
+    fn overlaps_bounds(&self, bounds: &SAABB) -> bool {
+        self.overlaps_aabb(bounds)
+    }
+}
+
+// -- an owner can have more than one leaf, so the same pair of owners can be found more than once
+fn sort_and_dedup_pairs<TA: PartialOrd, TB: PartialOrd>(pairs: &mut SVec<(TA, TB)>) {
+    pairs.as_mut_slice().sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+    let mut unique_count = 0;
+    for i in 0..pairs.len() {
+        if unique_count == 0 || pairs[i] != pairs[unique_count - 1] {
+            pairs.as_mut_slice().swap(unique_count, i);
+            unique_count += 1;
+        }
+    }
+
+    while pairs.len() > unique_count {
+        pairs.pop();
+    }
+}
+
+impl<TOwner: Clone + PartialEq + PartialOrd> SPairTracker<TOwner> {
+    pub fn new(allocator: &SAllocatorRef, max_pairs: usize) -> Result<Self, &'static str> {
+        Ok(Self {
+            pairs: SVec::new(allocator, max_pairs, 0)?,
+            prev_pairs: SVec::new(allocator, max_pairs, 0)?,
+            began: SVec::new(allocator, max_pairs, 0)?,
+            ended: SVec::new(allocator, max_pairs, 0)?,
+        })
+    }
+
+    // -- a truncated pair list can't tell which pairs ended, so on overflow the tracker keeps the
+    // -- pairs from the last complete update and reports nothing as began or ended
+    pub fn update(&mut self, tree: &STree<TOwner>) -> Result<(), &'static str> {
+        std::mem::swap(&mut self.pairs, &mut self.prev_pairs);
+        self.pairs.clear();
+
+        if !tree.overlapping_pairs(&mut self.pairs) {
+            std::mem::swap(&mut self.pairs, &mut self.prev_pairs);
+            self.began.clear();
+            self.ended.clear();
+            return Err("more overlapping pairs than the tracker has room for");
+        }
+
+        self.diff_with_prev_pairs();
+        Ok(())
+    }
+
+    // -- for pairs found some other way than overlapping leaves. Pairs are taken as given, so
+    // -- (a, b) and (b, a) are tracked separately
+    pub fn set_pairs(&mut self, pairs: &[(TOwner, TOwner)]) -> Result<(), &'static str> {
+        if pairs.len() > self.pairs.capacity() {
+            self.began.clear();
+            self.ended.clear();
+            return Err("more pairs than the tracker has room for");
+        }
+
+        std::mem::swap(&mut self.pairs, &mut self.prev_pairs);
+        self.pairs.clear();
+
+        for pair in pairs {
+            self.pairs.push(pair.clone());
+        }
+        sort_and_dedup_pairs(&mut self.pairs);
+        self.diff_with_prev_pairs();
+        Ok(())
+    }
+
+    fn diff_with_prev_pairs(&mut self) {
+        self.began.clear();
+        self.ended.clear();
+
+        // -- both lists are sorted, so walk them together
+        let prev = self.prev_pairs.as_slice();
+        let cur = self.pairs.as_slice();
+        let mut prev_idx = 0;
+        let mut cur_idx = 0;
+        while prev_idx < prev.len() || cur_idx < cur.len() {
+            if cur_idx >= cur.len() || (prev_idx < prev.len() && prev[prev_idx] < cur[cur_idx]) {
+                self.ended.push(prev[prev_idx].clone());
+                prev_idx += 1;
+            }
+            else if prev_idx >= prev.len() || cur[cur_idx] < prev[prev_idx] {
+                self.began.push(cur[cur_idx].clone());
+                cur_idx += 1;
+            }
+            else {
+                prev_idx += 1;
+                cur_idx += 1;
+            }
+        }
+    }
+
+    // -- every pair overlapping as of the last update
+    pub fn pairs(&self) -> &[(TOwner, TOwner)] {
+        self.pairs.as_slice()
+    }
+
+    // -- pairs that are overlapping this update but weren't last update
+    pub fn began(&self) -> &[(TOwner, TOwner)] {
+        self.began.as_slice()
+    }
+
+    // -- pairs that were overlapping last update but aren't anymore
+    pub fn ended(&self) -> &[(TOwner, TOwner)] {
+        self.ended.as_slice()
+    }
+}
+
+#[test]
+fn test_overlapping_pairs_and_tracking() {
+    let unit_box = |x: f32, z: f32| SAABB {
+        min: Vec3::new(x, 0.0, z),
+        max: Vec3::new(x + 1.0, 1.0, z + 1.0),
+    };
+
+    // -- a row of boxes where each one overlaps its neighbours, plus one off on its own
+    let mut tree = STree::<u32>::new();
+    let mut handles = Vec::new();
+    for i in 0..8 {
+        handles.push(tree.insert(i, &unit_box(0.75 * i as f32, 0.0), None).unwrap());
+    }
+    tree.insert(100, &unit_box(0.0, 10.0), None).unwrap();
+
+    let mut pairs = SVec::new(&SYSTEM_ALLOCATOR(), 64, 0).unwrap();
+    assert!(tree.overlapping_pairs(&mut pairs));
+    let expected : Vec<(u32, u32)> = (0..7).map(|i| (i, i + 1)).collect();
+    assert_eq!(pairs.as_slice(), expected.as_slice());
+
+    // -- against a second tree holding one box over the middle of the row
+    let mut other = STree::<u32>::new();
+    other.insert(50, &unit_box(2.6, 0.0), None).unwrap();
+    let mut cross_pairs = SVec::new(&SYSTEM_ALLOCATOR(), 64, 0).unwrap();
+    assert!(tree.overlapping_pairs_with(&other, &mut cross_pairs));
+    assert_eq!(cross_pairs.as_slice(), &[(3, 50), (4, 50)]);
+
+    let mut query_results = SVec::new(&SYSTEM_ALLOCATOR(), 64, 0).unwrap();
+    tree.query_aabb(&unit_box(2.6, 0.0), &mut query_results);
+    query_results.as_mut_slice().sort();
+    assert_eq!(query_results.as_slice(), &[3, 4]);
+
+    let mut tracker = SPairTracker::new(&SYSTEM_ALLOCATOR(), 64).unwrap();
+    tracker.update(&tree).unwrap();
+    assert_eq!(tracker.pairs().len(), 7);
+    assert_eq!(tracker.began().len(), 7);
+    assert!(tracker.ended().is_empty());
+
+    tracker.update(&tree).unwrap();
+    assert!(tracker.began().is_empty());
+    assert!(tracker.ended().is_empty());
+
+    // -- move the last box in the row over to the loner
+    tree.update_entry(handles[7], &unit_box(0.5, 10.0));
+    tracker.update(&tree).unwrap();
+    assert_eq!(tracker.began(), &[(7, 100)]);
+    assert_eq!(tracker.ended(), &[(6, 7)]);
+
+    let mut set_tracker = SPairTracker::new(&SYSTEM_ALLOCATOR(), 64).unwrap();
+    set_tracker.set_pairs(&[(5, 1), (2, 3), (5, 1)]).unwrap();
+    assert_eq!(set_tracker.pairs(), &[(2, 3), (5, 1)]);
+    set_tracker.set_pairs(&[(5, 1), (1, 5)]).unwrap();
+    assert_eq!(set_tracker.began(), &[(1, 5)]);
+    assert_eq!(set_tracker.ended(), &[(2, 3)]);
+
+    // -- a tracker too small for every pair keeps what it had instead of reporting false ends
+    let mut small_pairs = SVec::new(&SYSTEM_ALLOCATOR(), 4, 0).unwrap();
+    assert!(!tree.overlapping_pairs(&mut small_pairs));
+    let mut small_tracker = SPairTracker::new(&SYSTEM_ALLOCATOR(), 4).unwrap();
+    small_tracker.set_pairs(&[(1, 2)]).unwrap();
+    assert!(small_tracker.update(&tree).is_err());
+    assert_eq!(small_tracker.pairs(), &[(1, 2)]);
+    assert!(small_tracker.began().is_empty());
+    assert!(small_tracker.ended().is_empty());
+    assert!(small_tracker.set_pairs(&[(1, 2), (2, 3), (3, 4), (4, 5), (5, 6)]).is_err());
+    assert_eq!(small_tracker.pairs(), &[(1, 2)]);
+}
+
+#[test]
+fn test_rotations_keep_tree_balanced() {
+    let unit_box = |p: Vec3| SAABB {
+        min: p,
+        max: p + Vec3::new(0.5, 0.5, 0.5),
+    };
+
+    let count = 512;
+    // -- a perfectly balanced tree of 512 leaves has height 10
+    let max_height = 14;
+
+    // -- sorted along a line, which without rotations degenerates into a list of height ~count / 2
+    let mut line = STree::<u32>::new_with_capacity(2 * count as u16);
+    for i in 0..count {
+        line.insert(i, &unit_box(Vec3::new(i as f32, 0.0, 0.0)), None).unwrap();
+    }
+    assert!(line.compute_height() <= max_height);
+    // -- a perfectly balanced tree over the line costs about 8.75, the degenerate one over 100
+    assert!(line.compute_sah_cost() < 10.0);
+
+    // -- sorted rows of a grid
+    let mut grid = STree::<u32>::new_with_capacity(2 * count as u16);
+    for i in 0..count {
+        grid.insert(i, &unit_box(Vec3::new((i % 32) as f32, 0.0, (i / 32) as f32)), None).unwrap();
+    }
+    assert!(grid.compute_height() <= max_height);
+    assert!(grid.compute_sah_cost() < 10.0);
+
+    // -- scattered randomly, then moved around, then mostly removed
+    let mut seed : u32 = 12345;
+    let mut random_box = || {
+        let mut coord = || {
+            seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+            100.0 * (seed >> 8) as f32 / (1 << 24) as f32
+        };
+        unit_box(Vec3::new(coord(), coord(), coord()))
+    };
+
+    let mut scattered = STree::<u32>::new_with_capacity(2 * count as u16);
+    let mut handles = Vec::new();
+    for i in 0..count {
+        handles.push(scattered.insert(i, &random_box(), None).unwrap());
+    }
+    assert!(scattered.compute_height() <= max_height + 4);
+    assert!(scattered.compute_sah_cost() < 20.0);
+
+    for _ in 0..4 {
+        for handle in &handles {
+            scattered.update_entry(*handle, &random_box());
+        }
+    }
+    assert!(scattered.compute_height() <= max_height + 4);
+    assert!(scattered.compute_sah_cost() < 20.0);
+
+    for handle in &handles[..count as usize - 64] {
+        scattered.remove(*handle, true);
+    }
+    assert!(scattered.compute_height() <= 10);
+    assert!(scattered.compute_sah_cost() < 8.0);
+    assert!(scattered.tree_valid());
+}
+
+#[test]
+fn test_fat_bounds_updates() {
+    let unit_box = |x: f32| SAABB {
+        min: Vec3::new(x, 0.0, 0.0),
+        max: Vec3::new(x + 1.0, 1.0, 1.0),
+    };
+
+    let mut tree = STree::<u32>::new();
+    tree.set_fat_bounds_settings(SFatBoundsSettings{ margin: 0.25, displacement_multiplier: 2.0 });
+    let mut handles = Vec::new();
+    for i in 0..8 {
+        handles.push(tree.insert(i, &unit_box(2.0 * i as f32), None).unwrap());
+    }
+
+    // -- inside the margin, the tree doesn't change
+    tree.update_entry(handles[0], &unit_box(0.1));
+    let stats = tree.update_stats();
+    assert_eq!((stats.updates, stats.contained, stats.refits, stats.reinserts), (1, 1, 0, 0));
+
+    // -- just past the margin, the leaf grows towards where it's going
+    tree.update_entry(handles[0], &unit_box(0.3));
+    let stats = tree.update_stats();
+    assert_eq!(stats.updates, 2);
+    assert_eq!(stats.refits + stats.reinserts, 1);
+    let mut query_results = SVec::new(&SYSTEM_ALLOCATOR(), 64, 0).unwrap();
+    let ahead = SAABB{ min: Vec3::new(1.6, 0.0, 0.0), max: Vec3::new(1.7, 1.0, 1.0) };
+    tree.query_aabb(&ahead, &mut query_results);
+    assert_eq!(query_results.as_slice(), &[0]);
+
+    // -- so continuing the same way stays contained
+    tree.update_entry(handles[0], &unit_box(0.5));
+    assert_eq!(tree.update_stats().contained, 2);
+
+    // -- and a teleport has to reinsert
+    tree.update_entry_with_displacement(handles[0], &unit_box(50.0), &Vec3::zero());
+    let stats = tree.update_stats();
+    assert_eq!(stats.updates, 4);
+    assert_eq!(stats.reinserts, stats.updates - stats.contained - stats.refits);
+    assert!(stats.reinserts >= 1);
+    assert!(tree.tree_valid());
+
+    query_results.clear();
+    tree.query_aabb(&unit_box(50.0), &mut query_results);
+    assert_eq!(query_results.as_slice(), &[0]);
+    query_results.clear();
+    tree.query_aabb(&unit_box(0.5), &mut query_results);
+    assert!(query_results.is_empty());
+
+    tree.reset_update_stats();
+    assert_eq!(tree.update_stats().updates, 0);
+
+    // -- tight trees still skip updates that don't move anything
+    let mut tight = STree::<u32>::new();
+    let handle = tight.insert(0, &unit_box(0.0), None).unwrap();
+    tight.insert(1, &unit_box(2.0), None).unwrap();
+    tight.update_entry(handle, &unit_box(0.0));
+    assert_eq!(tight.update_stats().contained, 1);
+    tight.update_entry(handle, &unit_box(0.1));
+    assert_eq!(tight.update_stats().contained, 1);
+}
+
+#[test]
+fn test_spatial_queries() {
+    use crate::math::{Quat};
+
+    let mut seed : u32 = 777;
+    let mut coord = || {
+        seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+        40.0 * (seed >> 8) as f32 / (1 << 24) as f32 - 20.0
+    };
+
+    let mut tree = STree::<u32>::new();
+    let mut boxes = Vec::new();
+    for i in 0..200 {
+        let min = Vec3::new(coord(), coord(), coord());
+        let aabb = SAABB{ min, max: min + Vec3::new(1.0, 1.0, 1.0) };
+        tree.insert(i, &aabb, None).unwrap();
+        boxes.push(aabb);
+    }
+
+    // -- every query finds exactly what testing each box would
+    fn check<V: TBVHQueryVolume>(tree: &STree<u32>, boxes: &[SAABB], volume: &V) -> usize {
+        let mut results = SVec::new(&SYSTEM_ALLOCATOR(), 256, 0).unwrap();
+        assert!(tree.query(volume, &mut results));
+        results.as_mut_slice().sort();
+        let expected : Vec<u32> = (0..boxes.len() as u32).filter(|i| volume.overlaps_bounds(&boxes[*i as usize])).collect();
+        assert_eq!(results.as_slice(), expected.as_slice());
+        expected.len()
+    }
+
+    let sphere = SBoundingSphere{ center: Vec3::new(2.0, -3.0, 1.0), radius: 8.0 };
+    assert!(check(&tree, &boxes, &sphere) > 0);
+    let obb = SOBB{
+        center: Vec3::new(-4.0, 0.0, 3.0),
+        half_extents: Vec3::new(10.0, 2.0, 4.0),
+        rotation: Quat::new_from_euler(&Vec3::new(0.4, 0.8, 0.0)),
+    };
+    assert!(check(&tree, &boxes, &obb) > 0);
+    let frustum = SFrustum::new_perspective(&Vec3::new(0.0, 0.0, -25.0), &Vec3::new(0.2, 0.0, 1.0), &Vec3::new(0.0, 1.0, 0.0), 0.8, 1.5, 0.1, 30.0);
+    assert!(check(&tree, &boxes, &frustum) > 0);
+    assert!(check(&tree, &boxes, &frustum) < boxes.len());
+
+    // -- stopping from the callback
+    let mut visited = 0;
+    tree.query_with(&sphere, |_| {
+        visited += 1;
+        false
+    });
+    assert_eq!(visited, 1);
+
+    // -- a box off the diagonal of a box rotated 45 degrees only overlaps its bounds
+    let diamond = SOBB{
+        center: Vec3::zero(),
+        half_extents: Vec3::new(1.0, 1.0, 1.0),
+        rotation: Quat::new_angle_axis(0.25 * std::f32::consts::PI, &Vec3::new(0.0, 0.0, 1.0)),
+    };
+    let off_diagonal = SAABB{ min: Vec3::new(1.2, 1.2, -0.5), max: Vec3::new(2.0, 2.0, 0.5) };
+    assert!(SAABB::overlaps(&diamond.aabb(), &off_diagonal));
+    assert!(!diamond.overlaps_aabb(&off_diagonal));
+    assert!(diamond.overlaps_aabb(&SAABB{ min: Vec3::new(1.2, -0.2, -0.5), max: Vec3::new(2.0, 0.2, 0.5) }));
+
+    let view = SFrustum::new_perspective(&Vec3::zero(), &Vec3::new(0.0, 0.0, 1.0), &Vec3::new(0.0, 1.0, 0.0), 0.5 * std::f32::consts::PI, 1.0, 0.1, 100.0);
+    let cube_at = |p: Vec3| SAABB{ min: p - Vec3::new(0.5, 0.5, 0.5), max: p + Vec3::new(0.5, 0.5, 0.5) };
+    assert!(view.overlaps_aabb(&cube_at(Vec3::new(0.0, 0.0, 10.0))));
+    assert!(view.overlaps_aabb(&cube_at(Vec3::new(9.0, -9.0, 10.0))));
+    assert!(!view.overlaps_aabb(&cube_at(Vec3::new(0.0, 0.0, -10.0))));
+    assert!(!view.overlaps_aabb(&cube_at(Vec3::new(20.0, 0.0, 10.0))));
+    assert!(!view.overlaps_aabb(&cube_at(Vec3::new(0.0, -20.0, 10.0))));
+    assert!(!view.overlaps_aabb(&cube_at(Vec3::new(0.0, 0.0, 120.0))));
+
+    // -- nearest neighbours match sorting every box by distance
+    let point = Vec3::new(1.0, 2.0, -3.0);
+    let mut nearest = SVec::new(&SYSTEM_ALLOCATOR(), 16, 0).unwrap();
+    assert!(tree.query_nearest(&point, 8, std::f32::MAX, &mut nearest));
+    let mut distances : Vec<f32> = boxes.iter().map(|b| b.sqdistance_to_point(&point).sqrt()).collect();
+    distances.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    assert_eq!(nearest.len(), 8);
+    for i in 0..8 {
+        assert!((nearest[i].0 - distances[i]).abs() < 0.0001);
+        assert!((boxes[nearest[i].1 as usize].sqdistance_to_point(&point).sqrt() - nearest[i].0).abs() < 0.0001);
+    }
+
+    nearest.clear();
+    tree.query_nearest(&point, 8, distances[2] + 0.0001, &mut nearest);
+    assert_eq!(nearest.len(), 3);
+
+    // -- sorted ray casts find what the unsorted one does, front to back
+    let ray = SRay{ origin: Vec3::new(-25.0, 0.3, 0.2), dir: Vec3::new(1.0, 0.05, -0.02) };
+    let wide_ray = |origin: Vec3| SRay{ origin, dir: ray.dir };
+    let mut hits = 0;
+    for offset in 0..10 {
+        let ray = wide_ray(ray.origin + Vec3::new(0.0, 4.0 * offset as f32 - 20.0, 0.0));
+        let mut unsorted = SVec::new(&SYSTEM_ALLOCATOR(), 256, 0).unwrap();
+        tree.cast_ray(&ray, &mut unsorted);
+        let mut sorted = SVec::new(&SYSTEM_ALLOCATOR(), 256, 0).unwrap();
+        assert!(tree.cast_ray_sorted(&ray, &mut sorted));
+
+        assert_eq!(sorted.len(), unsorted.len());
+        for i in 1..sorted.len() {
+            assert!(sorted[i - 1].0 <= sorted[i].0);
+        }
+        unsorted.as_mut_slice().sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let mut resorted : Vec<(f32, u32)> = sorted.as_slice().to_vec();
+        resorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(resorted.as_slice(), unsorted.as_slice());
+
+        // -- confirming the first leaf as a hit stops the search there
+        if sorted.len() > 0 {
+            hits += 1;
+            let mut visited = Vec::new();
+            tree.cast_ray_front_to_back(&ray, std::f32::MAX, |t, owner| {
+                visited.push((t, *owner));
+                t
+            });
+            assert!(visited.iter().all(|(t, _)| *t == sorted[0].0));
+            assert!(visited.contains(&sorted[0]));
+        }
+
+        // -- a full out says so, and keeps the nearest
+        if sorted.len() > 1 {
+            let mut truncated = SVec::new(&SYSTEM_ALLOCATOR(), 1, 0).unwrap();
+            assert!(!tree.cast_ray_sorted(&ray, &mut truncated));
+            assert_eq!(truncated[0].0, sorted[0].0);
+        }
+    }
+    assert!(hits > 0);
+
+    let mut truncated = SVec::new(&SYSTEM_ALLOCATOR(), 1, 0).unwrap();
+    assert!(!tree.query(&sphere, &mut truncated));
+    assert_eq!(truncated.len(), 1);
+    let mut truncated = SVec::new(&SYSTEM_ALLOCATOR(), 2, 0).unwrap();
+    assert!(!tree.query_nearest(&point, 8, std::f32::MAX, &mut truncated));
+    assert_eq!(truncated.len(), 2);
+}
+
+#[test]
+fn test_insert_batch() {
+    let mut seed : u32 = 4242;
+    let mut random_box = || {
+        let mut coord = || {
+            seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+            100.0 * (seed >> 8) as f32 / (1 << 24) as f32
+        };
+        let min = Vec3::new(coord(), coord(), coord());
+        SAABB{ min, max: min + Vec3::new(0.5, 0.5, 0.5) }
+    };
+
+    let count = 512;
+    let entries : Vec<(u32, SAABB)> = (0..count).map(|i| (i, random_box())).collect();
+
+    let mut tree = STree::<u32>::new_with_capacity(2 * count as u16 + 64);
+    let mut handles = SVec::new(&SYSTEM_ALLOCATOR(), count as usize, 0).unwrap();
+    tree.insert_batch(entries.as_slice(), &mut handles).unwrap();
+    assert!(tree.tree_valid());
+    assert_eq!(handles.len(), count as usize);
+    for i in 0..count as usize {
+        assert_eq!(tree.owner(handles[i]), entries[i].0);
+    }
+
+    // -- a balanced tree of 512 leaves has height 10, and the bulk build should beat inserting one by one
+    let mut incremental = STree::<u32>::new_with_capacity(2 * count as u16);
+    for (owner, bounds) in &entries {
+        incremental.insert(*owner, bounds, None).unwrap();
+    }
+    assert!(tree.compute_height() <= 14);
+    assert!(tree.compute_sah_cost() < incremental.compute_sah_cost());
+
+    let query = SAABB{ min: Vec3::new(20.0, 20.0, 20.0), max: Vec3::new(60.0, 60.0, 60.0) };
+    let mut results = SVec::new(&SYSTEM_ALLOCATOR(), count as usize, 0).unwrap();
+    tree.query_aabb(&query, &mut results);
+    results.as_mut_slice().sort();
+    let expected : Vec<u32> = entries.iter().filter(|(_, b)| SAABB::overlaps(b, &query)).map(|(o, _)| *o).collect();
+    assert_eq!(results.as_slice(), expected.as_slice());
+
+    // -- still works as a dynamic tree afterwards
+    for i in 0..count as usize / 2 {
+        tree.update_entry(handles[i], &random_box());
+    }
+    for i in count as usize / 2..count as usize - 32 {
+        tree.remove(handles[i], true);
+    }
+    tree.insert(1000, &random_box(), None).unwrap();
+    assert!(tree.tree_valid());
+
+    // -- batches into a tree that already has leaves, including batches of one
+    let mut more_handles = SVec::new(&SYSTEM_ALLOCATOR(), 32, 0).unwrap();
+    let more : Vec<(u32, SAABB)> = (0..16).map(|i| (2000 + i, random_box())).collect();
+    tree.insert_batch(more.as_slice(), &mut more_handles).unwrap();
+    tree.insert_batch(&[(3000, random_box())], &mut more_handles).unwrap();
+    assert!(tree.tree_valid());
+    assert_eq!(more_handles.len(), 17);
+    assert_eq!(tree.owner(more_handles[16]), 3000);
+
+    // -- everything in the same spot can't be split by SAH, but still has to build
+    let mut stacked = STree::<u32>::new();
+    let mut stacked_handles = SVec::new(&SYSTEM_ALLOCATOR(), 64, 0).unwrap();
+    let same_spot : Vec<(u32, SAABB)> = (0..64).map(|i| (i, entries[0].1)).collect();
+    stacked.insert_batch(same_spot.as_slice(), &mut stacked_handles).unwrap();
+    assert!(stacked.tree_valid());
+    assert!(stacked.compute_height() <= 7);
+
+    let mut too_small = STree::<u32>::new_with_capacity(16);
+    assert!(too_small.insert_batch(same_spot.as_slice(), &mut stacked_handles).is_err());
+}
+
+// -- cargo test --release bench_insert_batch -- --ignored --nocapture
+#[test]
+#[ignore]
+fn bench_insert_batch() {
+    let mut seed : u32 = 99;
+    let mut random_box = || {
+        let mut coord = || {
+            seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+            200.0 * (seed >> 8) as f32 / (1 << 24) as f32
+        };
+        let min = Vec3::new(coord(), coord(), coord());
+        SAABB{ min, max: min + Vec3::new(1.0, 1.0, 1.0) }
+    };
+
+    for count in &[256u32, 1024, 4096] {
+        let entries : Vec<(u32, SAABB)> = (0..*count).map(|i| (i, random_box())).collect();
+
+        let start = std::time::Instant::now();
+        let mut incremental = STree::<u32>::new_with_capacity(2 * *count as u16);
+        for (owner, bounds) in &entries {
+            incremental.insert(*owner, bounds, None).unwrap();
+        }
+        let incremental_time = start.elapsed();
+
+        let start = std::time::Instant::now();
+        let mut batch = STree::<u32>::new_with_capacity(2 * *count as u16);
+        let mut handles = SVec::new(&SYSTEM_ALLOCATOR(), *count as usize, 0).unwrap();
+        batch.insert_batch(entries.as_slice(), &mut handles).unwrap();
+        let batch_time = start.elapsed();
+
+        println!("{} leaves: incremental {:?} (height {}, SAH cost {}), batch {:?} (height {}, SAH cost {})",
+            count,
+            incremental_time, incremental.compute_height(), incremental.compute_sah_cost(),
+            batch_time, batch.compute_height(), batch.compute_sah_cost());
+    }
+}
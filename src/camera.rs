@@ -1,18 +1,79 @@
+use serde::{Serialize, Deserialize};
+
 use crate::math::{Vec3, Mat4, Quat};
 
+use crate::editmode;
+use crate::entity::{SEntityBucket};
+use crate::entity_model;
 use crate::game_context::{SGameContext, SFrameContext};
 use crate::game_mode;
 use crate::input;
+use crate::render;
+use crate::utils;
+
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum EProjection {
+    Perspective,
+    OrthoTop, // looking down -y
+    OrthoFront, // looking down +z
+    OrthoSide, // looking down -x
+}
 
 pub struct SDebugFPCamera {
     pub pos_world: Vec3,
     x_angle: f32,
     y_angle: f32,
+
+    // -- orbit pivot is this far in front of the camera
+    orbit_distance: f32,
+
+    projection: EProjection,
+    // -- world space height of the view when orthographic
+    ortho_height: f32,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SCameraBookmark {
+    pub name: String,
+    pos_world: Vec3,
+    x_angle: f32,
+    y_angle: f32,
+    orbit_distance: f32,
+    projection: EProjection,
+    ortho_height: f32,
+}
+
+impl EProjection {
+    pub const ALL : [EProjection; 4] = [
+        EProjection::Perspective,
+        EProjection::OrthoTop,
+        EProjection::OrthoFront,
+        EProjection::OrthoSide,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Perspective => "Perspective",
+            Self::OrthoTop => "Top",
+            Self::OrthoFront => "Front",
+            Self::OrthoSide => "Side",
+        }
+    }
+
+    pub fn is_ortho(&self) -> bool {
+        *self != Self::Perspective
+    }
 }
 
 impl SDebugFPCamera {
     const MAX_X_DELTA : f32 = std::f32::consts::PI / 2.5;
     const TWOPI : f32 = std::f32::consts::PI * 2.0;
+    const MIN_ORBIT_DISTANCE : f32 = 0.1;
+    const DOLLY_FRACTION_PER_NOTCH : f32 = 0.15;
+    const MIN_ORTHO_HEIGHT : f32 = 0.1;
+    pub const ZFAR : f32 = 100.0;
+    // -- ortho views clip at +/- this distance along forward, so nothing near the camera position is lost
+    pub const ORTHO_DEPTH : f32 = 1000.0;
 
     fn forward_local() -> Vec3 {
         Vec3::new(0.0, 0.0, 1.0)
@@ -27,6 +88,13 @@ impl SDebugFPCamera {
     }
 
     pub fn forward_world(&self) -> Vec3 {
+        match self.projection {
+            EProjection::Perspective => {},
+            EProjection::OrthoTop => return Vec3::new(0.0, -1.0, 0.0),
+            EProjection::OrthoFront => return Vec3::new(0.0, 0.0, 1.0),
+            EProjection::OrthoSide => return Vec3::new(-1.0, 0.0, 0.0),
+        }
+
         let rotate_x = Quat::new_angle_axis(self.x_angle, &Self::right_local());
         let rotate_y = Quat::new_angle_axis(self.y_angle, &Self::up_world());
 
@@ -36,17 +104,184 @@ impl SDebugFPCamera {
         return forward_world;
     }
 
+    fn right_world(&self) -> Vec3 {
+        match self.projection {
+            EProjection::Perspective => Self::right_local().rotate_y(self.y_angle),
+            _ => Vec3::cross(&self.up_view(), &self.forward_world()),
+        }
+    }
+
+    // -- up vector of the view, the top view can't use world up since it looks straight down
+    fn up_view(&self) -> Vec3 {
+        match self.projection {
+            EProjection::OrthoTop => Vec3::new(0.0, 0.0, 1.0),
+            _ => Self::up_world(),
+        }
+    }
+
     pub fn new(pos: Vec3) -> Self {
         Self {
             pos_world: pos,
             x_angle: 0.0,
             y_angle: 0.0,
+            orbit_distance: 10.0,
+            projection: EProjection::Perspective,
+            ortho_height: 10.0,
+        }
+    }
+
+    pub fn projection(&self) -> EProjection {
+        self.projection
+    }
+
+    pub fn ortho_height(&self) -> Option<f32> {
+        if self.projection.is_ortho() {
+            Some(self.ortho_height)
+        }
+        else {
+            None
+        }
+    }
+
+    pub fn set_projection(&mut self, projection: EProjection) {
+        if projection == self.projection {
+            return;
+        }
+
+        // -- keep looking at the same pivot: ortho views sit on the pivot (they clip on both sides),
+        // -- perspective backs off from it again
+        let pivot = self.orbit_pivot();
+        if !self.projection.is_ortho() {
+            self.ortho_height = self.orbit_distance.max(Self::MIN_ORTHO_HEIGHT);
+        }
+
+        self.projection = projection;
+        self.pos_world = self.orbit_pivot_to_pos(&pivot);
+    }
+
+    fn orbit_pivot_to_pos(&self, pivot: &Vec3) -> Vec3 {
+        if self.projection.is_ortho() {
+            *pivot
+        }
+        else {
+            *pivot - self.orbit_distance * self.forward_world()
+        }
+    }
+
+    pub fn projection_matrix(&self, aspect: f32, fovy: f32, znear: f32) -> Mat4 {
+        match self.projection {
+            EProjection::Perspective => Mat4::new_perspective(aspect, fovy, znear, Self::ZFAR),
+            _ => {
+                let half_height = self.ortho_height / 2.0;
+                let half_width = aspect * half_height;
+                Mat4::new_orthographic(-half_width, half_width, -half_height, half_height, -Self::ORTHO_DEPTH, Self::ORTHO_DEPTH)
+            },
+        }
+    }
+
+    pub fn orbit_pivot(&self) -> Vec3 {
+        if self.projection.is_ortho() {
+            self.pos_world
+        }
+        else {
+            self.pos_world + self.orbit_distance * self.forward_world()
         }
     }
 
-    pub fn update_from_input(&mut self, input: &input::SInput, dts: f32, can_rotate_camera: bool) {
-        let forward_world = Self::forward_local().rotate_y(self.y_angle);
-        let right_world = Self::right_local().rotate_y(self.y_angle);
+    pub fn look_along(&mut self, dir: &Vec3) {
+        // -- inverse of forward_world: rotating forward_local around x gives y = -sin(x_angle),
+        // -- then rotating around y gives atan2(x, z) = y_angle
+        let dir = dir.normalized();
+        self.x_angle = utils::clamp((-dir.y).asin(), -Self::MAX_X_DELTA, Self::MAX_X_DELTA);
+        self.y_angle = dir.x.atan2(dir.z);
+    }
+
+    // -- back off along the current view direction until the AABB's bounding sphere fits in the view
+    pub fn focus_on_aabb(&mut self, aabb: &utils::SAABB, fovy: f32) {
+        let center = 0.5 * (aabb.min + aabb.max);
+        let radius = (0.5 * (aabb.max - aabb.min).mag()).max(Self::MIN_ORBIT_DISTANCE);
+
+        self.orbit_distance = radius / (fovy / 2.0).sin();
+        self.ortho_height = 2.0 * radius;
+        self.pos_world = self.orbit_pivot_to_pos(&center);
+    }
+
+    pub fn orbit(&mut self, dx: f32, dy: f32) {
+        // -- ortho views have a fixed orientation
+        if self.projection.is_ortho() {
+            return;
+        }
+
+        let pivot = self.orbit_pivot();
+
+        self.x_angle = utils::clamp(self.x_angle + dy / 100.0, -Self::MAX_X_DELTA, Self::MAX_X_DELTA);
+        self.y_angle = (self.y_angle + dx / 100.0) % Self::TWOPI;
+
+        self.pos_world = pivot - self.orbit_distance * self.forward_world();
+    }
+
+    pub fn dolly(&mut self, notches: f32) {
+        // -- moving along forward does nothing in ortho, so zoom instead
+        if self.projection.is_ortho() {
+            let scale = (1.0 - notches * Self::DOLLY_FRACTION_PER_NOTCH).max(Self::DOLLY_FRACTION_PER_NOTCH);
+            self.ortho_height = (self.ortho_height * scale).max(Self::MIN_ORTHO_HEIGHT);
+            return;
+        }
+
+        // -- step proportionally to the pivot distance, pushing the pivot ahead once we reach it
+        let step = notches * Self::DOLLY_FRACTION_PER_NOTCH * self.orbit_distance.max(1.0);
+        self.pos_world = self.pos_world + step * self.forward_world();
+        self.orbit_distance = (self.orbit_distance - step).max(Self::MIN_ORBIT_DISTANCE);
+    }
+
+    pub fn pan(&mut self, dx: f32, dy: f32) {
+        let right = self.right_world();
+        let up = Vec3::cross(&self.forward_world(), &right);
+
+        // -- scale with pivot distance (or view size) so the pivot roughly follows the cursor
+        let scale = match self.projection {
+            EProjection::Perspective => self.orbit_distance / 500.0,
+            _ => self.ortho_height / 500.0,
+        };
+        self.pos_world = self.pos_world + (-dx * scale) * right + (dy * scale) * up;
+    }
+
+    pub fn bookmark(&self, name: &str) -> SCameraBookmark {
+        SCameraBookmark {
+            name: String::from(name),
+            pos_world: self.pos_world,
+            x_angle: self.x_angle,
+            y_angle: self.y_angle,
+            orbit_distance: self.orbit_distance,
+            projection: self.projection,
+            ortho_height: self.ortho_height,
+        }
+    }
+
+    pub fn apply_bookmark(&mut self, bookmark: &SCameraBookmark) {
+        self.pos_world = bookmark.pos_world;
+        self.x_angle = bookmark.x_angle;
+        self.y_angle = bookmark.y_angle;
+        self.orbit_distance = bookmark.orbit_distance;
+        self.projection = bookmark.projection;
+        self.ortho_height = bookmark.ortho_height;
+    }
+
+    // -- forward and right along the ground, for walking in the direction we're looking
+    pub fn walk_axes(&self) -> (Vec3, Vec3) {
+        (Self::forward_local().rotate_y(self.y_angle), Self::right_local().rotate_y(self.y_angle))
+    }
+
+    pub fn update_move_from_input(&mut self, input: &input::SInput, dts: f32) {
+        // -- in ortho views W/S move up and down the screen rather than into it
+        let (forward_world, right_world, up_world) = match self.projection {
+            EProjection::Perspective => (
+                Self::forward_local().rotate_y(self.y_angle),
+                Self::right_local().rotate_y(self.y_angle),
+                Self::up_world(),
+            ),
+            _ => (self.up_view(), self.right_world(), -self.forward_world()),
+        };
 
         const SPEED: f32 = 5.0;
 
@@ -66,13 +301,18 @@ impl SDebugFPCamera {
             self.pos_world = self.pos_world + speed_dt * right_world;
         }
         if input.space_down {
-            self.pos_world = self.pos_world + speed_dt * Self::up_world();
+            self.pos_world = self.pos_world + speed_dt * up_world;
         }
         if input.c_down {
-            self.pos_world = self.pos_world + neg_speed_dt * Self::up_world();
+            self.pos_world = self.pos_world + neg_speed_dt * up_world;
         }
+    }
 
-        if can_rotate_camera {
+    pub fn update_look_from_input(&mut self, input: &input::SInput, can_rotate_camera: bool) {
+        if can_rotate_camera && self.projection.is_ortho() {
+            self.pan(input.mouse_dx as f32, input.mouse_dy as f32);
+        }
+        else if can_rotate_camera {
             if input.mouse_dy != 0 {
                 self.x_angle = super::utils::clamp(
                     self.x_angle + ((input.mouse_dy as f32) / 100.0),
@@ -88,7 +328,7 @@ impl SDebugFPCamera {
     }
 
     pub fn world_to_view_matrix(&self) -> Mat4 {
-        Mat4::new_look_at(&self.pos_world, &(self.pos_world + self.forward_world()), &Self::up_world())
+        Mat4::new_look_at(&self.pos_world, &(self.pos_world + self.forward_world()), &self.up_view())
     }
 }
 
@@ -97,13 +337,84 @@ pub fn update_debug_camera(game_context: &SGameContext, frame_context: &SFrameCo
     game_context.data_bucket.get::<SDebugFPCamera>()
         .and::<game_mode::SGameMode>()
         .and::<input::SInput>()
-        .with_mcc(|camera, game_mode, input| {
+        .with_mmc(|camera, game_mode, input| {
+            // -- in play the character controller moves the camera
             if let game_mode::EMode::Play = game_mode.mode {
                 can_rotate_camera = true;
             }
-            else if input.middle_mouse_down {
-                can_rotate_camera = true;
+            else {
+                if input.middle_mouse_down && !input.alt_down {
+                    can_rotate_camera = true;
+                }
+                // -- keys typed into an imgui text field shouldn't fly the camera
+                if !frame_context.imgui_want_capture_keyboard {
+                    camera.update_move_from_input(&input, frame_context.dt_s);
+                }
+            }
+            camera.update_look_from_input(&input, can_rotate_camera);
+
+            if let game_mode::EMode::Edit = game_mode.mode {
+                update_editor_camera_controls(game_context, camera, game_mode, input, frame_context.imgui_want_capture_mouse, frame_context.imgui_want_capture_keyboard);
             }
-            camera.update_from_input(&input, frame_context.dt_s, can_rotate_camera);
         });
+}
+
+fn update_editor_camera_controls(
+    game_context: &SGameContext,
+    camera: &mut SDebugFPCamera,
+    game_mode: &mut game_mode::SGameMode,
+    input: &input::SInput,
+    imgui_want_capture_mouse: bool,
+    imgui_want_capture_keyboard: bool,
+) {
+    // -- alt + left drag orbits, alt + middle drag pans
+    if input.alt_down && !imgui_want_capture_mouse {
+        let dx = input.mouse_dx as f32;
+        let dy = input.mouse_dy as f32;
+        if input.left_mouse_down {
+            camera.orbit(dx, dy);
+        }
+        else if input.middle_mouse_down {
+            camera.pan(dx, dy);
+        }
+    }
+
+    if input.mouse_wheel != 0 && !imgui_want_capture_mouse {
+        camera.dolly(input.mouse_wheel as f32);
+    }
+
+    // -- the hotkeys below are letters and digits, which also go into imgui text fields
+    if imgui_want_capture_keyboard {
+        return;
+    }
+
+    if input.f_edge.down() {
+        let mut focus_aabb = None;
+        game_context.data_bucket.get::<SEntityBucket>()
+            .and::<entity_model::SBucket>()
+            .and::<render::SRender>()
+            .with_ccc(|entities, em, render| {
+                focus_aabb = editmode::entities_aabb(game_mode.edit_mode_ctxt.selected_entities(), entities, em, render);
+                if let Some(aabb) = &focus_aabb {
+                    camera.focus_on_aabb(aabb, render.fovy());
+                }
+            });
+    }
+
+    // -- number keys recall bookmarks, ctrl + number keys store them
+    let ctxt = &mut game_mode.edit_mode_ctxt;
+    for key in 0..10 {
+        if !input.numbers_edge[key].down() {
+            continue;
+        }
+
+        // -- 1 is the first slot, 0 is the last
+        let slot = (key + 9) % 10;
+        if input.control_down {
+            ctxt.set_camera_bookmark(slot, camera.bookmark(format!("Bookmark {}", key).as_str()));
+        }
+        else if let Some(bookmark) = ctxt.camera_bookmark(slot) {
+            camera.apply_bookmark(bookmark);
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,658 @@
+use crate::camera;
+use crate::collision::{SCollisionFilter, ECollisionLayer, COLLISION_MASK_ALL};
+use crate::databucket::{SEntityBVH};
+use crate::entity::{SEntityBucket};
+use crate::entity_model;
+use crate::game_context::{SGameContext, SFrameContext};
+use crate::gjk::{SGJKSettings, EGJKDistanceResult, gjk_distance, SShapeCastSettings, EShapeCastResult, shape_cast};
+use crate::input;
+use crate::math::{Vec3};
+use crate::render;
+use crate::shapes::{SCapsule, STransformedShape, shape_aabb};
+use crate::utils::{SAABB, STransform};
+
+// -- kinematic capsule that walks around the level. It isn't simulated, each update sweeps it
+// -- through the world and slides it along whatever it hits. Floors keep our horizontal speed and
+// -- walls and steep slopes only stop horizontal motion, so walking up a steep one doesn't climb.
+// -- The rounded bottom resting on the edge of a walkable face counts as floor too, which is how
+// -- we get onto ledges
+
+#[derive(Copy, Clone, Debug)]
+pub struct SCharacterControllerSettings {
+    pub radius: f32,
+    // -- from the feet to the top of the capsule
+    pub height: f32,
+    pub eye_height: f32,
+    // -- ledges up to this high are stepped onto, and we stay stuck to the ground over drops this small
+    pub step_height: f32,
+    // -- steepest walkable slope, in radians
+    pub max_slope: f32,
+    pub walk_speed: f32,
+    pub jump_speed: f32,
+    pub gravity: f32,
+    // -- the level geometry we walk on has to collide with this
+    pub collision: SCollisionFilter,
+}
+
+pub struct SCharacterController {
+    pub settings: SCharacterControllerSettings,
+
+    // -- bottom of the capsule
+    position: Vec3,
+    vertical_speed: f32,
+    grounded: bool,
+}
+
+#[derive(Copy, Clone)]
+struct SCharacterContact {
+    // -- away from the triangle, towards the capsule
+    normal: Vec3,
+    depth: f32,
+    // -- closest point on the triangle
+    point: Vec3,
+    // -- the triangle's own normal, which differs from normal when we touch an edge or corner
+    face_normal: Vec3,
+}
+
+enum ESweepResult {
+    Clear,
+    // -- fraction of the sweep travelled, and what we touched there
+    Hit(f32, SCharacterContact),
+    // -- already inside something, so there's nothing to sweep from
+    Overlapping,
+}
+
+struct SMoveResult {
+    position: Vec3,
+    ground: Option<SCharacterContact>,
+    hit_wall: bool,
+    hit_ceiling: bool,
+}
+
+const MAX_DEPENETRATION_ITERATIONS : usize = 8;
+const MAX_SLIDE_ITERATIONS : usize = 4;
+// -- sweeps stop this far short of what they hit, so the next one doesn't start out touching it
+const SKIN : f32 = 0.005;
+// -- how far below the feet we look for ground when falling, so landing doesn't flicker
+const GROUND_PROBE_DIST : f32 = 0.02;
+
+impl Default for SCharacterControllerSettings {
+    fn default() -> Self {
+        Self {
+            radius: 0.3,
+            height: 1.8,
+            eye_height: 1.6,
+            step_height: 0.35,
+            max_slope: 50.0 * std::f32::consts::PI / 180.0,
+            walk_speed: 4.0,
+            jump_speed: 5.0,
+            gravity: 9.8,
+            collision: SCollisionFilter::new(ECollisionLayer::Character, COLLISION_MASK_ALL & !ECollisionLayer::Trigger.bit()),
+        }
+    }
+}
+
+impl SMoveResult {
+    fn new(position: Vec3) -> Self {
+        Self {
+            position,
+            ground: None,
+            hit_wall: false,
+            hit_ceiling: false,
+        }
+    }
+}
+
+fn horizontal(v: &Vec3) -> Vec3 {
+    Vec3::new(v.x, 0.0, v.z)
+}
+
+impl SCharacterController {
+    pub fn new(position: Vec3) -> Self {
+        Self {
+            settings: SCharacterControllerSettings::default(),
+            position,
+            vertical_speed: 0.0,
+            grounded: false,
+        }
+    }
+
+    pub fn position(&self) -> Vec3 {
+        self.position
+    }
+
+    pub fn head_position(&self) -> Vec3 {
+        self.position + Vec3::new(0.0, self.settings.eye_height, 0.0)
+    }
+
+    pub fn is_grounded(&self) -> bool {
+        self.grounded
+    }
+
+    // -- puts the character somewhere new, standing still
+    pub fn teleport(&mut self, position: &Vec3) {
+        self.position = *position;
+        self.vertical_speed = 0.0;
+        self.grounded = false;
+    }
+
+    // -- every triangle the next update over dt could touch is inside this
+    pub fn query_aabb(&self, dt: f32) -> SAABB {
+        let s = &self.settings;
+        let reach = s.step_height
+            + dt * (s.walk_speed + s.jump_speed + self.vertical_speed.abs() + s.gravity * dt);
+        let margin = Vec3::new(s.radius + reach, reach, s.radius + reach);
+        SAABB {
+            min: self.position - margin,
+            max: self.position + Vec3::new(0.0, s.height, 0.0) + margin,
+        }
+    }
+
+    fn is_walkable(&self, normal: &Vec3) -> bool {
+        normal.y >= self.settings.max_slope.cos()
+    }
+
+    fn gather_contacts(&self, position: &Vec3, triangles: &[[Vec3; 3]], out: &mut Vec<SCharacterContact>) {
+        let s = &self.settings;
+        let gjk_settings = SGJKSettings {
+            max_iterations: 32,
+            tolerance: 0.0001,
+        };
+
+        // -- capsule is the segment between the centres of its end spheres, grown by radius. Anything
+        // -- within the skin counts too, sweeps can't start from touching
+        let segment = [
+            position + Vec3::new(0.0, s.radius, 0.0),
+            position + Vec3::new(0.0, s.height - s.radius, 0.0),
+        ];
+        let reach = s.radius + SKIN;
+        let capsule_aabb = SAABB {
+            min: position - Vec3::new(reach, SKIN, reach),
+            max: position + Vec3::new(reach, s.height + SKIN, reach),
+        };
+
+        for tri in triangles {
+            if !SAABB::overlaps(&capsule_aabb, &SAABB::new_from_points(tri)) {
+                continue;
+            }
+
+            let tri_normal = Vec3::cross(&(tri[1] - tri[0]), &(tri[2] - tri[0]));
+            if tri_normal.sqmag() < std::f32::EPSILON {
+                continue;
+            }
+            let tri_normal = tri_normal.normalized();
+
+            match gjk_distance(&segment[..], &tri[..], &gjk_settings) {
+                EGJKDistanceResult::Separated(d) | EGJKDistanceResult::DidNotConverge(d) => {
+                    if d.distance < reach {
+                        let normal = -d.witness_dir;
+                        let face_normal = if Vec3::dot(&tri_normal, &normal) >= 0.0 { tri_normal } else { -tri_normal };
+                        out.push(SCharacterContact {
+                            normal,
+                            depth: reach - d.distance,
+                            point: d.closest_b,
+                            face_normal,
+                        });
+                    }
+                },
+                EGJKDistanceResult::Intersecting => {
+                    // -- the segment goes through the triangle, so push out of its plane on the
+                    // -- side the capsule's middle is on
+                    let middle = 0.5 * (segment[0] + segment[1]);
+                    let normal = if Vec3::dot(&(middle - tri[0]), &tri_normal) >= 0.0 { tri_normal } else { -tri_normal };
+
+                    let deepest = Vec3::dot(&(segment[0] - tri[0]), &normal)
+                        .min(Vec3::dot(&(segment[1] - tri[0]), &normal));
+                    out.push(SCharacterContact {
+                        normal,
+                        depth: reach - deepest.min(0.0),
+                        point: middle - Vec3::dot(&(middle - tri[0]), &normal) * normal,
+                        face_normal: normal,
+                    });
+                },
+            }
+        }
+    }
+
+    // -- standing on a walkable face, or on the edge of one below the bottom sphere's centre
+    fn is_ground(&self, position: &Vec3, contact: &SCharacterContact) -> bool {
+        if self.is_walkable(&contact.normal) {
+            return true;
+        }
+
+        contact.normal.y > 0.0
+            && self.is_walkable(&contact.face_normal)
+            && contact.point.y < position.y + self.settings.radius
+    }
+
+    // -- smallest push that resolves the contact, in the direction that suits its slope
+    fn push_for_contact(&self, position: &Vec3, contact: &SCharacterContact) -> Vec3 {
+        let n = &contact.normal;
+        if self.is_walkable(n) {
+            Vec3::new(0.0, contact.depth / n.y, 0.0)
+        }
+        else if self.is_ground(position, contact) {
+            // -- lift the bottom sphere straight up until it sits on the edge
+            let r = self.settings.radius;
+            let sphere_centre = position + Vec3::new(0.0, r, 0.0);
+            let horizontal_sq = horizontal(&(sphere_centre - contact.point)).sqmag();
+            let resting_height = ((r + SKIN) * (r + SKIN) - horizontal_sq).max(0.0).sqrt();
+            Vec3::new(0.0, resting_height - (sphere_centre.y - contact.point.y), 0.0)
+        }
+        else if n.y > 0.0 {
+            let n_horizontal = horizontal(n);
+            (contact.depth / n_horizontal.sqmag()) * n_horizontal
+        }
+        else {
+            contact.depth * n
+        }
+    }
+
+    fn depenetrate(&self, result: &mut SMoveResult, triangles: &[[Vec3; 3]], contacts: &mut Vec<SCharacterContact>) {
+        for _ in 0..MAX_DEPENETRATION_ITERATIONS {
+            contacts.clear();
+            self.gather_contacts(&result.position, triangles, contacts);
+
+            // -- ground goes first, a ledge's edge belongs to its wall as well as its top, and
+            // -- resolving it as a wall would push us off
+            let mut deepest : Option<(bool, SCharacterContact)> = None;
+            for contact in contacts.iter() {
+                let ground = self.is_ground(&result.position, contact);
+                if ground {
+                    result.ground = Some(*contact);
+                }
+                else if contact.normal.y < -0.5 {
+                    result.hit_ceiling = true;
+                }
+                else {
+                    result.hit_wall = true;
+                }
+
+                let better = match deepest {
+                    None => true,
+                    Some((deepest_ground, d)) => (ground && !deepest_ground) || (ground == deepest_ground && contact.depth > d.depth),
+                };
+                if better {
+                    deepest = Some((ground, *contact));
+                }
+            }
+
+            match deepest {
+                Some((_, contact)) => result.position = result.position + self.push_for_contact(&result.position, &contact),
+                None => break,
+            }
+        }
+    }
+
+    fn capsule_at(&self, position: &Vec3) -> STransformedShape<SCapsule> {
+        let s = &self.settings;
+        let capsule = SCapsule {
+            half_height: 0.5 * s.height - s.radius,
+            radius: s.radius,
+        };
+        STransformedShape::new(capsule, STransform::new_translation(&(position + Vec3::new(0.0, 0.5 * s.height, 0.0))))
+    }
+
+    // -- earliest triangle the capsule touches moving from position along delta
+    fn sweep(&self, position: &Vec3, delta: &Vec3, triangles: &[[Vec3; 3]]) -> ESweepResult {
+        let capsule = self.capsule_at(position);
+        let start_aabb = shape_aabb(&capsule);
+        let swept_aabb = SAABB::new_from_points(&[
+            start_aabb.min,
+            start_aabb.max,
+            start_aabb.min + *delta,
+            start_aabb.max + *delta,
+        ]);
+        let settings = SShapeCastSettings::default();
+
+        let mut result = ESweepResult::Clear;
+        let mut closest_toi = std::f32::MAX;
+        for tri in triangles {
+            if !SAABB::overlaps(&swept_aabb, &SAABB::new_from_points(tri)) {
+                continue;
+            }
+
+            let tri_normal = Vec3::cross(&(tri[1] - tri[0]), &(tri[2] - tri[0]));
+            if tri_normal.sqmag() < std::f32::EPSILON {
+                continue;
+            }
+            let tri_normal = tri_normal.normalized();
+
+            let hit = match shape_cast(&capsule, delta, &tri[..], &settings) {
+                EShapeCastResult::Hit(hit) => hit,
+                // -- stopping where the cast got to is safe, the capsule doesn't touch the triangle there yet
+                EShapeCastResult::DidNotConverge(hit) => hit,
+                EShapeCastResult::Miss => continue,
+                EShapeCastResult::InitiallyOverlapping => return ESweepResult::Overlapping,
+            };
+
+            // -- touching something we're moving along or away from doesn't stop us
+            if Vec3::dot(delta, &hit.normal) >= 0.0 || hit.toi >= closest_toi {
+                continue;
+            }
+
+            closest_toi = hit.toi;
+            let face_normal = if Vec3::dot(&tri_normal, &hit.normal) >= 0.0 { tri_normal } else { -tri_normal };
+            result = ESweepResult::Hit(hit.toi, SCharacterContact {
+                normal: hit.normal,
+                depth: 0.0,
+                point: hit.point,
+                face_normal,
+            });
+        }
+
+        result
+    }
+
+    // -- what's left of remaining after running into contact, following the same slope rules as depenetration
+    fn slide(&self, result: &mut SMoveResult, contact: &SCharacterContact, remaining: &Vec3) -> Vec3 {
+        let n = &contact.normal;
+
+        if self.is_ground(&result.position, contact) {
+            result.ground = Some(*contact);
+            if self.is_walkable(n) {
+                // -- keep the horizontal speed and follow the ground up or down
+                let h = horizontal(remaining);
+                return Vec3::new(h.x, -Vec3::dot(&horizontal(n), &h) / n.y, h.z);
+            }
+
+            // -- rolling up over an edge
+            return remaining - Vec3::dot(remaining, n) * n;
+        }
+
+        if n.y < -0.5 {
+            result.hit_ceiling = true;
+            return remaining - Vec3::dot(remaining, n) * n;
+        }
+
+        result.hit_wall = true;
+        let mut slid = *remaining;
+        let n_horizontal = horizontal(n);
+        if n_horizontal.sqmag() > std::f32::EPSILON {
+            let into = Vec3::dot(&slid, &n_horizontal) / n_horizontal.sqmag();
+            if into < 0.0 {
+                slid = slid - into * n_horizontal;
+            }
+        }
+        // -- falling onto a steep slope slides down it
+        if n.y > 0.0 && slid.y < 0.0 {
+            let into = Vec3::dot(&slid, n);
+            if into < 0.0 {
+                slid = slid - into * n;
+            }
+        }
+        slid
+    }
+
+    // -- sweeps the capsule along delta, sliding along whatever it runs into
+    fn move_through(&self, start: &Vec3, delta: &Vec3, triangles: &[[Vec3; 3]]) -> SMoveResult {
+        let mut contacts = Vec::new();
+        let mut result = SMoveResult::new(*start);
+
+        // -- sweeps never end inside anything, but the level can move into us
+        self.depenetrate(&mut result, triangles, &mut contacts);
+
+        let mut remaining = *delta;
+        for _ in 0..MAX_SLIDE_ITERATIONS {
+            let length = remaining.mag();
+            if length < 0.0001 {
+                break;
+            }
+
+            match self.sweep(&result.position, &remaining, triangles) {
+                ESweepResult::Clear => {
+                    result.position = result.position + remaining;
+                    break;
+                },
+                ESweepResult::Overlapping => {
+                    self.depenetrate(&mut result, triangles, &mut contacts);
+                },
+                ESweepResult::Hit(toi, contact) => {
+                    let travelled = (toi * length - SKIN).max(0.0) / length;
+                    result.position = result.position + travelled * remaining;
+                    remaining = self.slide(&mut result, &contact, &((1.0 - travelled) * remaining));
+                },
+            }
+        }
+        result
+    }
+
+    // -- walk_velocity is horizontal, jump only does anything on the ground
+    pub fn update(&mut self, triangles: &[[Vec3; 3]], walk_velocity: &Vec3, jump: bool, dt: f32) {
+        let s = self.settings;
+
+        if self.grounded && jump {
+            self.vertical_speed = s.jump_speed;
+            self.grounded = false;
+        }
+        if !self.grounded {
+            self.vertical_speed -= s.gravity * dt;
+        }
+
+        // -- horizontal movement, trying to step up onto whatever stopped us
+        let walk_delta = dt * horizontal(walk_velocity);
+        let start = self.position;
+        let mut walked = self.move_through(&start, &walk_delta, triangles);
+        if self.grounded && walked.hit_wall {
+            let up = self.move_through(&start, &Vec3::new(0.0, s.step_height, 0.0), triangles);
+            let across = self.move_through(&up.position, &walk_delta, triangles);
+            let down = self.move_through(&across.position, &Vec3::new(0.0, start.y - up.position.y, 0.0), triangles);
+
+            let stepped_dist = horizontal(&(down.position - start)).mag();
+            let walked_dist = horizontal(&(walked.position - start)).mag();
+            // -- measured where we stand rather than at the feet, the rounded bottom can sit on an edge
+            // -- a little above them
+            let step_ok = down.ground.map_or(false, |ground| ground.point.y - start.y <= s.step_height);
+            if step_ok && stepped_dist > walked_dist + 0.01 {
+                walked = down;
+            }
+        }
+        self.position = walked.position;
+
+        // -- vertical movement
+        let fallen = self.move_through(&self.position, &Vec3::new(0.0, self.vertical_speed * dt, 0.0), triangles);
+        self.position = fallen.position;
+        if fallen.hit_ceiling && self.vertical_speed > 0.0 {
+            self.vertical_speed = 0.0;
+        }
+
+        if self.vertical_speed > 0.0 {
+            self.grounded = false;
+            return;
+        }
+
+        // -- look for ground under us, walking keeps us stuck to it down steps and slopes
+        let probe_dist = if self.grounded { s.step_height } else { GROUND_PROBE_DIST };
+        let landed = match fallen.ground {
+            Some(_) => fallen,
+            None => self.move_through(&self.position, &Vec3::new(0.0, -probe_dist, 0.0), triangles),
+        };
+
+        self.grounded = landed.ground.is_some();
+        if self.grounded {
+            self.position = landed.position;
+            self.vertical_speed = 0.0;
+        }
+    }
+}
+
+// -- world space triangles of every model the entity BVH finds in aabb that collides with filter
+fn gather_level_triangles(
+    aabb: &SAABB,
+    filter: &SCollisionFilter,
+    bvh: &SEntityBVH,
+    entities: &SEntityBucket,
+    em: &entity_model::SBucket,
+    render: &render::SRender,
+    out: &mut Vec<[Vec3; 3]>,
+) {
+    bvh.query_with(aabb, |entity| {
+        if !filter.collides_with(&entities.get_collision_filter(*entity)) {
+            return true;
+        }
+
+        if let Some(model_handle) = em.handle_for_entity(*entity) {
+            let location = entities.get_entity_location(*entity);
+            let mesh = render.mesh_instance_loader().get_mesh(em.get_model(model_handle));
+            render.mesh_loader().gather_triangles_in_aabb(mesh, aabb, &location, out);
+        }
+        true
+    });
+}
+
+pub fn update_character_controller(game_context: &SGameContext, frame_context: &SFrameContext) {
+    let dt = frame_context.dt_s;
+
+    let (query_aabb, collision) = game_context.data_bucket.get::<SCharacterController>().with(|controller| {
+        (controller.query_aabb(dt), controller.settings.collision)
+    });
+
+    let mut triangles = Vec::new();
+    game_context.data_bucket.get::<SEntityBVH>()
+        .and::<SEntityBucket>()
+        .and::<entity_model::SBucket>()
+        .and::<render::SRender>()
+        .with_cccc(|bvh, entities, em, render| {
+            gather_level_triangles(&query_aabb, &collision, bvh, entities, em, render, &mut triangles);
+        });
+
+    // -- the camera still does the looking, the controller moves it around
+    game_context.data_bucket.get::<SCharacterController>()
+        .and::<camera::SDebugFPCamera>()
+        .and::<input::SInput>()
+        .with_mmc(|controller, camera, input| {
+            let (forward, right) = camera.walk_axes();
+
+            let mut walk_dir = Vec3::zero();
+            if input.w_down {
+                walk_dir = walk_dir + forward;
+            }
+            if input.s_down {
+                walk_dir = walk_dir - forward;
+            }
+            if input.a_down {
+                walk_dir = walk_dir - right;
+            }
+            if input.d_down {
+                walk_dir = walk_dir + right;
+            }
+            if walk_dir.sqmag() > 0.0 {
+                walk_dir = controller.settings.walk_speed * walk_dir.normalized();
+            }
+
+            controller.update(triangles.as_slice(), &walk_dir, input.space_edge.down(), dt);
+            camera.pos_world = controller.head_position();
+        });
+}
+
+#[allow(dead_code)]
+fn test_quad(a: Vec3, b: Vec3, c: Vec3, d: Vec3) -> [[Vec3; 3]; 2] {
+    [[a, b, c], [a, c, d]]
+}
+
+// -- big floor at y = 0, with a wall facing -x at x = 5
+#[allow(dead_code)]
+fn test_floor_and_wall() -> Vec<[Vec3; 3]> {
+    let mut tris = Vec::new();
+    tris.extend_from_slice(&test_quad(
+        Vec3::new(-10.0, 0.0, -10.0), Vec3::new(-10.0, 0.0, 10.0),
+        Vec3::new(10.0, 0.0, 10.0), Vec3::new(10.0, 0.0, -10.0),
+    ));
+    tris.extend_from_slice(&test_quad(
+        Vec3::new(5.0, 0.0, -10.0), Vec3::new(5.0, 3.0, -10.0),
+        Vec3::new(5.0, 3.0, 10.0), Vec3::new(5.0, 0.0, 10.0),
+    ));
+    tris
+}
+
+#[allow(dead_code)]
+fn test_run(controller: &mut SCharacterController, tris: &[[Vec3; 3]], walk: Vec3, jump: bool, frames: usize) {
+    for _ in 0..frames {
+        controller.update(tris, &walk, jump, 1.0 / 60.0);
+    }
+}
+
+#[test]
+fn test_character_falls_and_slides_along_walls() {
+    let tris = test_floor_and_wall();
+    let mut controller = SCharacterController::new(Vec3::new(0.0, 2.0, 0.0));
+
+    test_run(&mut controller, &tris, Vec3::zero(), false, 60);
+    assert!(controller.is_grounded());
+    assert!(controller.position().y.abs() < 0.01);
+
+    // -- walking diagonally into the wall keeps the motion along it
+    test_run(&mut controller, &tris, Vec3::new(4.0, 0.0, 4.0), false, 120);
+    let p = controller.position();
+    assert!((p.x - (5.0 - controller.settings.radius)).abs() < 0.02);
+    assert!(p.z > 6.0);
+    assert!(p.y.abs() < 0.01);
+    assert!(controller.is_grounded());
+}
+
+#[test]
+fn test_character_steps_up_small_ledges() {
+    let step_tris = |height: f32| {
+        let mut tris = test_floor_and_wall();
+        tris.extend_from_slice(&test_quad(
+            Vec3::new(1.0, 0.0, -10.0), Vec3::new(1.0, height, -10.0),
+            Vec3::new(1.0, height, 10.0), Vec3::new(1.0, 0.0, 10.0),
+        ));
+        tris.extend_from_slice(&test_quad(
+            Vec3::new(1.0, height, -10.0), Vec3::new(1.0, height, 10.0),
+            Vec3::new(4.0, height, 10.0), Vec3::new(4.0, height, -10.0),
+        ));
+        tris
+    };
+
+    let low = step_tris(0.2);
+    let mut controller = SCharacterController::new(Vec3::zero());
+    test_run(&mut controller, &low, Vec3::new(4.0, 0.0, 0.0), false, 40);
+    assert!(controller.position().x > 1.5);
+    assert!((controller.position().y - 0.2).abs() < 0.01);
+
+    let high = step_tris(0.6);
+    let mut controller = SCharacterController::new(Vec3::zero());
+    test_run(&mut controller, &high, Vec3::new(4.0, 0.0, 0.0), false, 40);
+    assert!(controller.position().x < 1.0);
+    assert!(controller.position().y.abs() < 0.01);
+
+    // -- but it can be jumped onto
+    test_run(&mut controller, &high, Vec3::new(1.5, 0.0, 0.0), true, 1);
+    assert!(!controller.is_grounded());
+    test_run(&mut controller, &high, Vec3::new(1.5, 0.0, 0.0), false, 60);
+    assert!(controller.position().x > 1.5);
+    assert!((controller.position().y - 0.6).abs() < 0.01);
+    assert!(controller.is_grounded());
+}
+
+#[test]
+fn test_character_slope_limit() {
+    // -- ramp rising along +x from x = 1
+    let ramp_tris = |degrees: f32| {
+        let rise = (degrees * std::f32::consts::PI / 180.0).tan() * 3.0;
+        let mut tris = test_floor_and_wall();
+        tris.extend_from_slice(&test_quad(
+            Vec3::new(1.0, 0.0, -10.0), Vec3::new(1.0, 0.0, 10.0),
+            Vec3::new(4.0, rise, 10.0), Vec3::new(4.0, rise, -10.0),
+        ));
+        tris
+    };
+
+    let gentle = ramp_tris(30.0);
+    let mut controller = SCharacterController::new(Vec3::zero());
+    test_run(&mut controller, &gentle, Vec3::new(3.0, 0.0, 0.0), false, 60);
+    assert!(controller.position().x > 2.0);
+    assert!(controller.is_grounded());
+    let on_slope = controller.position();
+
+    // -- standing still on it doesn't slide
+    test_run(&mut controller, &gentle, Vec3::zero(), false, 60);
+    assert!((controller.position() - on_slope).mag() < 0.01);
+
+    let steep = ramp_tris(60.0);
+    let mut controller = SCharacterController::new(Vec3::zero());
+    test_run(&mut controller, &steep, Vec3::new(2.0, 0.0, 0.0), false, 60);
+    assert!(controller.position().x < 1.2);
+    assert!(controller.position().y < 0.1);
+}
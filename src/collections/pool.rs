@@ -11,7 +11,7 @@ pub trait TIndexGen : PartialEq + PartialOrd + Copy + std::ops::Add + std::ops::
     fn from_usize(v: usize) -> Self;
 }
 
-#[derive(Copy, Clone, PartialEq, Debug)]
+#[derive(Copy, Clone, PartialEq, PartialOrd, Debug)]
 pub struct SPoolHandle<I, G>
 where I: TIndexGen, G: TIndexGen
 {
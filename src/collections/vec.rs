@@ -133,6 +133,19 @@ impl<T> SVec<T> {
         result
     }
 
+    pub fn insert(&mut self, index: usize, value: T) {
+        assert!(index <= self.len);
+        // -- push to the end, then rotate it down into place so order is preserved
+        self.push(value);
+        self.as_mut_slice()[index..].rotate_right(1);
+    }
+
+    pub fn remove(&mut self, index: usize) -> T {
+        // -- rotate the value to the end so order is preserved, then pop it off
+        self.as_mut_slice()[index..].rotate_left(1);
+        self.pop().expect("index must be in bounds")
+    }
+
     pub fn resize_with<F>(&mut self, new_len: usize, mut f: F)
     where
         F: FnMut() -> T,
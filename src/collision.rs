@@ -0,0 +1,153 @@
+use serde::{Serialize, Deserialize};
+
+use crate::entity::{SEntityHandle};
+use crate::inspect;
+
+// -- what collides with what. Every entity is on one layer and has a mask of the layers it
+// -- collides with, and a pair only collides when each side's mask has the other's layer
+
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum ECollisionLayer {
+    Default,
+    Static,
+    Dynamic,
+    Character,
+    Trigger,
+}
+
+pub const COLLISION_MASK_ALL : u32 = std::u32::MAX;
+
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct SCollisionFilter {
+    pub layer: ECollisionLayer,
+    pub mask: u32,
+}
+
+// -- for scene queries, which only care about the layers they hit
+#[derive(Copy, Clone)]
+pub struct SQueryFilter<'a> {
+    pub mask: u32,
+    pub ignore: &'a [SEntityHandle],
+}
+
+impl ECollisionLayer {
+    pub const ALL: [ECollisionLayer; 5] = [
+        ECollisionLayer::Default,
+        ECollisionLayer::Static,
+        ECollisionLayer::Dynamic,
+        ECollisionLayer::Character,
+        ECollisionLayer::Trigger,
+    ];
+
+    pub fn bit(&self) -> u32 {
+        1 << (*self as u32)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Default => "Default",
+            Self::Static => "Static",
+            Self::Dynamic => "Dynamic",
+            Self::Character => "Character",
+            Self::Trigger => "Trigger",
+        }
+    }
+}
+
+impl Default for SCollisionFilter {
+    fn default() -> Self {
+        Self {
+            layer: ECollisionLayer::Default,
+            mask: COLLISION_MASK_ALL,
+        }
+    }
+}
+
+impl SCollisionFilter {
+    pub fn new(layer: ECollisionLayer, mask: u32) -> Self {
+        Self {
+            layer,
+            mask,
+        }
+    }
+
+    pub fn collides_with(&self, other: &Self) -> bool {
+        (self.mask & other.layer.bit()) != 0 && (other.mask & self.layer.bit()) != 0
+    }
+}
+
+impl<'a> SQueryFilter<'a> {
+    pub fn new() -> Self {
+        Self {
+            mask: COLLISION_MASK_ALL,
+            ignore: &[],
+        }
+    }
+
+    pub fn new_ignoring(ignore: &'a [SEntityHandle]) -> Self {
+        Self {
+            mask: COLLISION_MASK_ALL,
+            ignore,
+        }
+    }
+
+    pub fn accepts(&self, entity: SEntityHandle, filter: &SCollisionFilter) -> bool {
+        (self.mask & filter.layer.bit()) != 0 && !self.ignore.contains(&entity)
+    }
+}
+
+impl inspect::TInspectEnum for ECollisionLayer {
+    fn variants() -> &'static [&'static str] {
+        &["Default", "Static", "Dynamic", "Character", "Trigger"]
+    }
+
+    fn to_index(&self) -> usize {
+        *self as usize
+    }
+
+    fn from_index(index: usize) -> Self {
+        Self::ALL[index]
+    }
+}
+
+impl inspect::TInspect for SCollisionFilter {
+    fn inspect(&mut self, visitor: &mut dyn inspect::TInspectVisitor) -> bool {
+        let mut changed = inspect::inspect_enum(visitor, "Layer", &mut self.layer);
+
+        for layer in &ECollisionLayer::ALL {
+            let mut collides = if (self.mask & layer.bit()) != 0 { 1 } else { 0 };
+            if visitor.visit_enum(layer.name(), &mut collides, &["Ignore", "Collide"]) {
+                if collides == 1 {
+                    self.mask |= layer.bit();
+                }
+                else {
+                    self.mask &= !layer.bit();
+                }
+                changed = true;
+            }
+        }
+
+        changed
+    }
+}
+
+#[test]
+fn test_collision_filter() {
+    let everything = SCollisionFilter::default();
+    let character = SCollisionFilter::new(ECollisionLayer::Character, !ECollisionLayer::Trigger.bit());
+    let trigger = SCollisionFilter::new(ECollisionLayer::Trigger, ECollisionLayer::Character.bit());
+
+    assert!(everything.collides_with(&character));
+    assert!(character.collides_with(&everything));
+
+    // -- both sides have to agree
+    assert!(!character.collides_with(&trigger));
+    assert!(!trigger.collides_with(&everything));
+
+    let entity = SEntityHandle::default();
+    let statics_only = SQueryFilter{ mask: ECollisionLayer::Static.bit(), ignore: &[] };
+    assert!(statics_only.accepts(entity, &SCollisionFilter::new(ECollisionLayer::Static, 0)));
+    assert!(!statics_only.accepts(entity, &everything));
+    assert!(SQueryFilter::new().accepts(entity, &character));
+    assert!(!SQueryFilter::new_ignoring(&[entity]).accepts(entity, &character));
+}
@@ -8,11 +8,14 @@ use crate::collections::{SVec};
 
 use crate::animation;
 use crate::camera;
+use crate::character_controller;
 use crate::bvh;
 use crate::editmode;
 use crate::entity;
 use crate::entity_animation;
 use crate::entity_model;
+use crate::entity_rigid_body;
+use crate::entity_trigger;
 use crate::gjk;
 use crate::input;
 use crate::game_mode;
@@ -22,13 +25,16 @@ pub trait TDataBucketMember : std::any::Any {
 }
 
 pub type SEntityBVH = bvh::STree<entity::SEntityHandle>;
+pub type SEntityPairs = bvh::SPairTracker<entity::SEntityHandle>;
 
 impl TDataBucketMember for SEntityBVH {}
+impl TDataBucketMember for SEntityPairs {}
 impl TDataBucketMember for entity::SEntityBucket {}
 impl TDataBucketMember for render::SRender {}
 impl TDataBucketMember for animation::SAnimationLoader {}
 impl TDataBucketMember for game_mode::SGameMode {}
 impl TDataBucketMember for camera::SDebugFPCamera {}
+impl TDataBucketMember for character_controller::SCharacterController {}
 impl TDataBucketMember for input::SInput {}
 impl TDataBucketMember for gjk::SGJKDebug {}
 impl TDataBucketMember for editmode::SEditModeInput {}
@@ -36,6 +42,8 @@ impl TDataBucketMember for editmode::SEditModeInput {}
 // -- "components"
 impl TDataBucketMember for entity_animation::SBucket {}
 impl TDataBucketMember for entity_model::SBucket {}
+impl TDataBucketMember for entity_rigid_body::SBucket {}
+impl TDataBucketMember for entity_trigger::SBucket {}
 
 struct SData {
     type_id: std::any::TypeId,
@@ -1,19 +1,29 @@
+use std::ops::{Deref};
+
 use crate::allocate::{STACK_ALLOCATOR};
 use crate::bvh;
+use crate::camera;
 use crate::collections::{SVec};
+use crate::databucket;
+use crate::editmode;
 use crate::entity;
 use crate::entity_model;
+use crate::entitytypes::{EEntityInit, EEntityType};
 use crate::game_context::{SGameContext, SFrameContext};
 use crate::game_mode;
+use crate::gjk;
+use crate::inspect;
+use crate::inspect::{TInspectVisitor};
 use crate::math::{Vec3, Vec4};
 use crate::render;
+use crate::shapes::{STransformedShape};
 
 pub fn update_debug_entity_menu(game_context: &SGameContext, frame_context: &SFrameContext) {
     use imgui::*;
 
     game_context.data_bucket.get::<entity::SEntityBucket>()
         .and::<game_mode::SGameMode>()
-        .with_mc(|entity, game_mode| {
+        .with_mm(|entity, game_mode| {
             if game_mode.edit_mode_ctxt.editing_entity().is_none() {
                 return;
             }
@@ -42,15 +52,296 @@ pub fn update_debug_entity_menu(game_context: &SGameContext, frame_context: &SFr
                     if DragFloat3::new(imgui_ui, im_str!("Position"), &mut pos).speed(0.1).build() {
                         entity.set_position(&game_context, e, Vec3::new(pos[0], pos[1], pos[2]));
                     }
+
+                    imgui_ui.separator();
+                    let ctxt = &mut game_mode.edit_mode_ctxt;
+                    imgui_ui.text(im_str!("Selection ({})", ctxt.selected_entities().len()));
+
+                    imgui_ui.radio_button(im_str!("Pivot: centroid"), &mut ctxt.pivot_mode, editmode::EPivotMode::Centroid);
+                    imgui_ui.same_line(0.0);
+                    imgui_ui.radio_button(im_str!("Pivot: active"), &mut ctxt.pivot_mode, editmode::EPivotMode::ActiveEntity);
+
+                    imgui_ui.radio_button(im_str!("World"), &mut ctxt.gizmo_space, editmode::EGizmoSpace::World);
+                    imgui_ui.same_line(0.0);
+                    imgui_ui.radio_button(im_str!("Local"), &mut ctxt.gizmo_space, editmode::EGizmoSpace::Local);
+                    imgui_ui.same_line(0.0);
+                    imgui_ui.text(im_str!("(L cycles)"));
+
+                    if imgui_ui.small_button(im_str!("Duplicate")) {
+                        ctxt.request_duplicate_selection();
+                    }
+                    imgui_ui.same_line(0.0);
+                    if imgui_ui.small_button(im_str!("Delete")) {
+                        ctxt.request_delete_selection();
+                    }
+                    imgui_ui.same_line(0.0);
+                    if imgui_ui.small_button(im_str!("Copy")) {
+                        ctxt.request_clipboard_op(editmode::EClipboardOp::Copy);
+                    }
+                    imgui_ui.same_line(0.0);
+                    if imgui_ui.small_button(im_str!("Cut")) {
+                        ctxt.request_clipboard_op(editmode::EClipboardOp::Cut);
+                    }
+
+                    let mut make_active = None;
+                    for selected in ctxt.selected_entities() {
+                        if !entity.entity_valid(*selected) {
+                            continue;
+                        }
+
+                        let label = match entity.get_entity_debug_name(*selected) {
+                            Some(n) => im_str!("{}##{}.{}", n._debug_ptr.as_ref().expect(""), selected.index(), selected.generation()),
+                            None => im_str!("{}.{}", selected.index(), selected.generation()),
+                        };
+                        if Selectable::new(&label).selected(*selected == e).build(imgui_ui) {
+                            make_active = Some(*selected);
+                        }
+                    }
+                    if let Some(new_active) = make_active {
+                        ctxt.set_active_entity(new_active);
+                    }
                 });
         });
 }
 
+pub fn update_debug_entity_properties(game_context: &SGameContext, frame_context: &SFrameContext) {
+    use imgui::*;
+
+    let e = match game_context.data_bucket.get::<game_mode::SGameMode>().with(|game_mode: &game_mode::SGameMode| {
+        game_mode.edit_mode_ctxt.editing_entity()
+    }) {
+        Some(e) => e,
+        None => return,
+    };
+
+    if !game_context.data_bucket.get::<entity::SEntityBucket>().with(|entities: &entity::SEntityBucket| entities.entity_valid(e)) {
+        return;
+    }
+
+    // -- the init is rebuilt from the live entity every frame, so it also reflects gizmo edits
+    let mut e_init = EEntityInit::new_from_entity(game_context, e);
+    let mut changed = false;
+
+    let imgui_ui = frame_context.imgui_ui.as_ref().expect("shouldn't have rendered ui yet");
+    Window::new(im_str!("Properties"))
+        .size([300.0, 300.0], Condition::FirstUseEver)
+        .build(imgui_ui, || {
+            imgui_ui.text(im_str!("type: {}", e_init.entity_type().name()));
+            let mut inspector = inspect::SImguiInspector::new(imgui_ui);
+            changed = inspector.visit_struct("Init", e_init.inspectable());
+        });
+
+    if changed {
+        e_init.apply_to_entity(game_context, e);
+
+        game_context.data_bucket.get::<game_mode::SGameMode>().with_mut(|game_mode: &mut game_mode::SGameMode| {
+            if let Some(editing_level) = game_mode.edit_mode_ctxt.editing_level_mut() {
+                editing_level.set_entity_init(e, e_init.clone());
+            }
+        });
+    }
+}
+
+pub fn update_debug_outliner(game_context: &SGameContext, frame_context: &SFrameContext) {
+    use imgui::*;
+
+    // -- gather names and types first, renaming and hiding below borrow the entity bucket themselves
+    let mut rows : Vec<(entity::SEntityHandle, String, EEntityType)> = Vec::new();
+    game_context.data_bucket.get::<entity::SEntityBucket>()
+        .and::<game_mode::SGameMode>()
+        .with_cc(|entities, game_mode| {
+            if let Some(editing_level) = game_mode.edit_mode_ctxt.editing_level() {
+                for e in editing_level.owned_entities() {
+                    let name = match entities.get_entity_debug_name(*e) {
+                        Some(n) => String::from(unsafe { n._debug_ptr.as_ref() }.expect("")),
+                        None => String::new(),
+                    };
+                    rows.push((*e, name, entities.get_entity_type(*e)));
+                }
+            }
+        });
+
+    let imgui_ui = frame_context.imgui_ui.as_ref().expect("shouldn't have rendered ui yet");
+
+    game_context.data_bucket.get::<game_mode::SGameMode>().with_mut(|game_mode: &mut game_mode::SGameMode| {
+        let ctxt = &mut game_mode.edit_mode_ctxt;
+        if ctxt.editing_level().is_none() {
+            return;
+        }
+
+        // -- the folder (None for the root) under the cursor, for dropping dragged entities into
+        let mut hovered_folder : Option<Option<String>> = None;
+
+        Window::new(im_str!("Outliner"))
+            .size([300.0, 400.0], Condition::FirstUseEver)
+            .build(imgui_ui, || {
+                imgui_ui.input_text(im_str!("Filter"), &mut ctxt.outliner.filter).build();
+
+                let mut type_names = vec![ImString::new("All")];
+                for entity_type in EEntityType::SPAWNABLE.iter() {
+                    type_names.push(ImString::new(entity_type.name()));
+                }
+                let type_name_refs : Vec<&ImStr> = type_names.iter().map(|n| n.deref()).collect();
+                ComboBox::new(im_str!("Type")).build_simple_string(imgui_ui, &mut ctxt.outliner.type_filter, &type_name_refs);
+
+                if let Some(active) = ctxt.editing_entity() {
+                    if ctxt.outliner.renaming_entity != Some(active) {
+                        ctxt.outliner.renaming_entity = Some(active);
+                        ctxt.outliner.rename.clear();
+                        if let Some((_, name, _)) = rows.iter().find(|(e, _, _)| *e == active) {
+                            ctxt.outliner.rename.push_str(name.as_str());
+                        }
+                    }
+
+                    if imgui_ui.input_text(im_str!("Rename"), &mut ctxt.outliner.rename).enter_returns_true(true).build() {
+                        let name = String::from(ctxt.outliner.rename.to_str());
+                        if let Some(editing_level) = ctxt.editing_level_mut() {
+                            editing_level.rename_entity(game_context, active, name.as_str());
+                        }
+                    }
+                }
+
+                imgui_ui.input_text(im_str!("##new_folder"), &mut ctxt.outliner.new_folder_name).build();
+                imgui_ui.same_line(0.0);
+                if imgui_ui.small_button(im_str!("Add folder")) {
+                    let name = String::from(ctxt.outliner.new_folder_name.to_str());
+                    if let Some(editing_level) = ctxt.editing_level_mut() {
+                        if let Err(e) = editing_level.add_folder(name.as_str()) {
+                            println!("ERROR: failed to add folder: {}", e);
+                        }
+                        else {
+                            ctxt.outliner.new_folder_name.clear();
+                        }
+                    }
+                }
+
+                imgui_ui.separator();
+
+                let folders = ctxt.editing_level().expect("checked above").folders().to_vec();
+                let mut remove_folder = None;
+
+                let root_open = imgui_ui.collapsing_header(im_str!("(root)")).default_open(true).build();
+                if imgui_ui.is_item_hovered() {
+                    hovered_folder = Some(None);
+                }
+                if root_open {
+                    for (e, name, entity_type) in &rows {
+                        if ctxt.editing_level().expect("checked above").entity_editor_data(*e).folder.is_none() {
+                            outliner_row(imgui_ui, game_context, ctxt, *e, name.as_str(), *entity_type);
+                        }
+                    }
+                }
+
+                for folder in &folders {
+                    let folder_open = imgui_ui.collapsing_header(&im_str!("{}##folder", folder)).default_open(true).build();
+                    if imgui_ui.is_item_hovered() {
+                        hovered_folder = Some(Some(folder.clone()));
+                    }
+                    if folder_open {
+                        let id = imgui_ui.push_id(folder.as_str());
+                        if imgui_ui.small_button(im_str!("Remove folder")) {
+                            remove_folder = Some(folder.clone());
+                        }
+                        for (e, name, entity_type) in &rows {
+                            if ctxt.editing_level().expect("checked above").entity_editor_data(*e).folder.as_ref() == Some(folder) {
+                                outliner_row(imgui_ui, game_context, ctxt, *e, name.as_str(), *entity_type);
+                            }
+                        }
+                        id.pop(imgui_ui);
+                    }
+                }
+
+                if let Some(folder) = remove_folder {
+                    if let Some(editing_level) = ctxt.editing_level_mut() {
+                        editing_level.remove_folder(folder.as_str());
+                    }
+                }
+
+                if let Some(dragging) = ctxt.outliner.dragging_entity {
+                    if let Some((_, name, _)) = rows.iter().find(|(e, _, _)| *e == dragging) {
+                        imgui_ui.tooltip_text(format!("Move {}", name));
+                    }
+                }
+            });
+
+        if imgui_ui.is_mouse_released(MouseButton::Left) {
+            if let (Some(dragging), Some(folder)) = (ctxt.outliner.dragging_entity, hovered_folder) {
+                // -- dragging a selected entity moves the whole selection
+                let moving : Vec<entity::SEntityHandle> = if ctxt.is_selected(dragging) {
+                    ctxt.selected_entities().to_vec()
+                }
+                else {
+                    vec![dragging]
+                };
+
+                if let Some(editing_level) = ctxt.editing_level_mut() {
+                    for e in moving {
+                        let mut editor_data = editing_level.entity_editor_data(e);
+                        editor_data.folder = folder.clone();
+                        editing_level.set_entity_editor_data(game_context, e, editor_data);
+                    }
+                }
+            }
+
+            ctxt.outliner.dragging_entity = None;
+        }
+    });
+}
+
+fn outliner_row(
+    ui: &imgui::Ui,
+    game_context: &SGameContext,
+    ctxt: &mut editmode::SEditModeContext,
+    e: entity::SEntityHandle,
+    name: &str,
+    entity_type: EEntityType,
+) {
+    use imgui::*;
+
+    if !ctxt.outliner.passes_filter(name, entity_type) {
+        return;
+    }
+
+    let id = ui.push_id(format!("{}.{}", e.index(), e.generation()).as_str());
+
+    let mut editor_data = ctxt.editing_level().expect("outliner requires a level").entity_editor_data(e);
+    let mut changed = false;
+
+    if ui.small_button(if editor_data.hidden { im_str!("Show") } else { im_str!("Hide") }) {
+        editor_data.hidden = !editor_data.hidden;
+        changed = true;
+    }
+    ui.same_line(0.0);
+    if ui.small_button(if editor_data.locked { im_str!("Unlock") } else { im_str!("Lock") }) {
+        editor_data.locked = !editor_data.locked;
+        changed = true;
+    }
+    ui.same_line(0.0);
+
+    let label = im_str!("{} [{}] {}.{}", name, entity_type.name(), e.index(), e.generation());
+    if Selectable::new(&label).selected(ctxt.is_selected(e)).build(ui) {
+        ctxt.select(e, ui.io().key_shift);
+    }
+    if ui.is_item_active() && ui.is_mouse_dragging(MouseButton::Left) {
+        ctxt.outliner.dragging_entity = Some(e);
+    }
+    // -- entities have no hierarchy yet, so dropping onto another entity does nothing
+
+    if changed {
+        if let Some(editing_level) = ctxt.editing_level_mut() {
+            editing_level.set_entity_editor_data(game_context, e, editor_data);
+        }
+    }
+
+    id.pop(ui);
+}
+
 pub fn update_debug_main_menu(game_context: &SGameContext, frame_context: &SFrameContext) {
     game_context.data_bucket.get::<game_mode::SGameMode>()
         //.and::<gjk::SGJKDebug>() // $$$FRK(TOOD): restore this by making it possible to click two entities
+        .and::<camera::SDebugFPCamera>()
         .and::<bvh::STree<entity::SEntityHandle>>()
-        .with_mc(|game_mode, bvh| {
+        .with_mmm(|game_mode, camera, bvh| {
             let imgui_ui = frame_context.imgui_ui.as_ref().expect("this should happen before imgui render");
 
             if let game_mode::EMode::Edit = game_mode.mode {
@@ -65,6 +356,25 @@ pub fn update_debug_main_menu(game_context: &SGameContext, frame_context: &SFram
                         if imgui::MenuItem::new(imgui::im_str!("Toggle Demo Window")).build(&imgui_ui) {
                             game_mode.show_imgui_demo_window = !game_mode.show_imgui_demo_window;
                         }
+                        imgui::MenuItem::new(imgui::im_str!("Draw colliding entities"))
+                            .build_with_ref(&imgui_ui, &mut game_mode.draw_colliding_entities);
+                    });
+
+                    imgui_ui.menu(imgui::im_str!("Play"), true, || {
+                        if imgui::MenuItem::new(imgui::im_str!("Play (~)")).build(&imgui_ui) {
+                            game_mode.toggle_mode_requested = true;
+                        }
+                        imgui::MenuItem::new(imgui::im_str!("Keep play changes for selection"))
+                            .build_with_ref(&imgui_ui, &mut game_mode.keep_play_changes_for_selection);
+                    });
+
+                    imgui_ui.menu(imgui::im_str!("View"), true, || {
+                        for projection in &camera::EProjection::ALL {
+                            let selected = camera.projection() == *projection;
+                            if imgui::MenuItem::new(&imgui::im_str!("{}", projection.name())).selected(selected).build(&imgui_ui) {
+                                camera.set_projection(*projection);
+                            }
+                        }
                     });
 
                     bvh.imgui_menu(&imgui_ui, &mut game_mode.draw_selected_bvh);
@@ -84,9 +394,8 @@ pub fn update_draw_entity_bvh(game_context: &SGameContext, _frame_context: &SFra
             .and::<game_mode::SGameMode>()
             .with_mccc(|render, em, bvh, game_mode| {
                 if game_mode.draw_selected_bvh {
-                    if let Some(e) = game_mode.edit_mode_ctxt.editing_entity() {
-                        let model_handle = em.handle_for_entity(e).unwrap();
-
+                    // -- entities without a model, like triggers, aren't in the BVH
+                    if let Some(model_handle) = game_mode.edit_mode_ctxt.editing_entity().and_then(|e| em.handle_for_entity(e)) {
                         let mut aabbs = SVec::new(&sa.as_ref(), 32, 0).unwrap();
                         bvh.get_bvh_heirarchy_for_entry(em.get_bvh_entry(model_handle).unwrap(), &mut aabbs);
                         for aabb in aabbs.as_slice() {
@@ -98,52 +407,51 @@ pub fn update_draw_entity_bvh(game_context: &SGameContext, _frame_context: &SFra
         });
 }
 
-/*
-pub fn update_debug_draw_entity_colliding() {
-    // -- draw selected object colliding/not with rotating_entity
-    STACK_ALLOCATOR.with(|sa| {
-        game_context.data_bucket.get::<render::SRender>()
-            .and::<entity::SEntityBucket>()
-            .and::<entity_model::SBucket>()
-            .and::<game_mode::SGameMode>()
-            .with_mccc(|render, entities, em, game_mode| {
-                if let Some(e) = game_mode.edit_mode_ctxt.editing_entity() {
-                    let e_model_handle = em.handle_for_entity(e).unwrap();
-                    let rot_model_handle = em.handle_for_entity(rotating_entity).unwrap();
-                    let loc = entities.get_entity_location(e);
-
-                    let world_verts = {
-                        let model = em.get_model(e_model_handle);
-                        let mesh_local_vs = render.mesh_loader().get_mesh_local_vertices(model.mesh);
-
-                        let mut world_verts = SVec::new(&sa.as_ref(), mesh_local_vs.len(), 0).unwrap();
+pub fn update_debug_draw_entity_colliding(game_context: &SGameContext, _frame_context: &SFrameContext) {
+    // -- run narrowphase on the broadphase pairs, and mark the ones that really collide
+    let draw = game_context.data_bucket.get::<game_mode::SGameMode>().with(|game_mode| {
+        game_mode.draw_colliding_entities
+    });
+    if !draw {
+        return;
+    }
 
-                        for v in mesh_local_vs.as_slice() {
-                            world_verts.push(loc.mul_point(&v));
-                        }
+    game_context.data_bucket.get::<render::SRender>()
+        .and::<entity::SEntityBucket>()
+        .and::<entity_model::SBucket>()
+        .and::<databucket::SEntityPairs>()
+        .with_mccc(|render, entities, em, pairs| {
+            for (entity_a, entity_b) in pairs.pairs() {
+                let filter_a = entities.get_collision_filter(*entity_a);
+                if !filter_a.collides_with(&entities.get_collision_filter(*entity_b)) {
+                    continue;
+                }
 
-                        world_verts
+                // -- shapes GJK couldn't decide on still get drawn, in a different colour
+                let colour = {
+                    let shape_for_entity = |e: entity::SEntityHandle| {
+                        let model_handle = em.handle_for_entity(e)?;
+                        let mesh = render.mesh_instance_loader().get_mesh(em.get_model(model_handle));
+                        let hull = render.mesh_loader().get_mesh_local_hull(mesh);
+                        Some(STransformedShape::new(hull, entities.get_entity_location(e)))
                     };
 
-                    let rot_box_world_verts = {
-                        let model = em.get_model(rot_model_handle);
-                        let loc = entities.get_entity_location(rotating_entity);
-                        let mesh_local_vs = render.mesh_loader().get_mesh_local_vertices(model.mesh);
-
-                        let mut world_verts = SVec::new(&sa.as_ref(), mesh_local_vs.len(), 0).unwrap();
-
-                        for v in mesh_local_vs.as_slice() {
-                            world_verts.push(loc.mul_point(&v));
-                        }
-
-                        world_verts
-                    };
+                    match (shape_for_entity(*entity_a), shape_for_entity(*entity_b)) {
+                        (Some(shape_a), Some(shape_b)) => match gjk::gjk_intersection(&shape_a, &shape_b, &gjk::SGJKSettings::default()) {
+                            gjk::EGJKResult::Intersection(_) => Some(Vec4::new(1.0, 0.0, 0.0, 0.1)),
+                            gjk::EGJKResult::DidNotConverge => Some(Vec4::new(1.0, 1.0, 0.0, 0.1)),
+                            gjk::EGJKResult::NoIntersection => None,
+                        },
+                        _ => None,
+                    }
+                };
 
-                    if gjk::gjk(world_verts.as_slice(), rot_box_world_verts.as_slice()) {
-                        render.temp().draw_sphere(&loc.t, 1.0, &Vec4::new(1.0, 0.0, 0.0, 0.1), true, None);
+                if let Some(colour) = colour {
+                    for e in &[*entity_a, *entity_b] {
+                        let loc = entities.get_entity_location(*e);
+                        render.temp().draw_sphere(&loc.t, 1.0, &colour, true, None);
                     }
                 }
-            });
-    });
+            }
+        });
 }
-*/
@@ -1,21 +1,26 @@
 use::std::ops::{Deref};
 
-use crate::allocate::{STACK_ALLOCATOR, SYSTEM_ALLOCATOR};
-use crate::bvh;
+use crate::allocate::{SYSTEM_ALLOCATOR};
 use crate::camera;
 use crate::collections::{SVec};
+use crate::collision::{SQueryFilter};
 use crate::databucket;
 use crate::game_context::{SGameContext, SFrameContext};
 use crate::game_mode;
 use crate::entity::{SEntityBucket, SEntityHandle};
+use crate::entity_model;
+use crate::entitytypes::{EEntityInit, EEntityType};
 use crate::math::{Vec3, Vec4, Quat, Mat4};
 use crate::input;
 use crate::level;
 use crate::model;
 use crate::render;
+use crate::scene_query;
 use crate::utils;
 use crate::utils::{STransform};
 
+use serde::{Serialize, Deserialize};
+
 pub struct SEditModeInput {
     pub window_width: u32,
     pub window_height: u32,
@@ -23,50 +28,161 @@ pub struct SEditModeInput {
     pub camera_pos_world: Vec3,
     pub camera_forward: Vec3,
     pub world_to_view_matrix: Mat4,
+    pub projection_matrix: Mat4,
     pub fovy: f32,
     pub znear: f32,
+    // -- world space height of the view, only set for orthographic cameras
+    pub ortho_height: Option<f32>,
 
     pub imgui_want_capture_mouse: bool,
+    pub imgui_want_capture_keyboard: bool,
+}
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum EPivotMode {
+    Centroid,
+    ActiveEntity,
+}
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum EClipboardOp {
+    Copy,
+    Cut,
+    Paste,
+}
+
+// -- no parent space until entities can be parented, it would just be world space
+#[derive(PartialEq, Clone, Copy)]
+pub enum EGizmoSpace {
+    World,
+    Local,
+}
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum ESnapTarget {
+    Vertex,
+    Surface,
+}
+
+pub struct SEditModeSnapSettings {
+    pub grid_enabled: bool,
+    pub grid_size: f32,
+    pub rotation_enabled: bool,
+    pub rotation_increment_degrees: f32,
+
+    pub draw_grid: bool,
+    pub grid_draw_half_cells: u32,
 }
 
 #[derive(PartialEq, Clone)]
 pub struct SEditModeTranslationDragging {
-    entity: SEntityHandle,
     axis: usize,
-    start_pos: Vec3,
+    orientation: Quat,
+    start_pivot: Vec3,
     mouse_offset: [i32; 2],
+    start_locations: Vec<(SEntityHandle, STransform)>,
 }
 
 #[derive(PartialEq, Clone)]
 pub struct SEditModeRotationDragging {
-    entity: SEntityHandle,
     axis: usize,
-    start_ori: Quat,
-    start_entity_to_cursor : Vec3,
+    orientation: Quat,
+    start_pivot: Vec3,
+    start_pivot_to_cursor : Vec3,
+    start_locations: Vec<(SEntityHandle, STransform)>,
 }
 
 pub struct SEditingLevel {
     file_path: String,
     level_init: level::SInit,
     level: level::SLevel,
+    camera_bookmarks: SCameraBookmarks,
+}
+
+// -- the edited level's entities as they were when play started, restored on returning to edit
+pub struct SPlaySnapshot {
+    entities: Vec<SEntityHandle>,
+    entity_inits: Vec<EEntityInit>,
+    entity_editor_data: Vec<level::SEntityEditorData>,
+    pub time_s: f32,
+}
+
+// -- saved next to the level file, one slot per number key
+#[derive(Default, Serialize, Deserialize)]
+pub struct SCameraBookmarks {
+    slots: [Option<camera::SCameraBookmark>; 10],
 }
 
 pub struct SEditModeContext {
     editing_level: Option<SEditingLevel>,
 
-    editing_entity: Option<SEntityHandle>,
+    selection: SVec<SEntityHandle>,
+    active_entity: Option<SEntityHandle>,
+    pub pivot_mode: EPivotMode,
+    pub gizmo_space: EGizmoSpace,
+    pub snap_settings: SEditModeSnapSettings,
+
+    // -- entity type placed by the next click in the world, chosen from the spawn palette
+    pub spawn_type: Option<EEntityType>,
+    pub spawn_align_to_normal: bool,
+
     translation_widgets: [model::SMeshInstanceHandle; 3],
     translation_widget_transforms: [STransform; 3],
+    translation_widget_axis_rotations: [Quat; 3],
     rotation_widgets: [model::SMeshInstanceHandle; 3],
     rotation_widget_transforms: [STransform; 3],
+    rotation_widget_axis_rotations: [Quat; 3],
 
     clicked_entity: Option<SEntityHandle>,
     can_select_clicked_entity: bool,
+    marquee_start: Option<[i32; 2]>,
+
+    delete_selection_requested: bool,
+    duplicate_selection_requested: bool,
+    clipboard_op_requested: Option<EClipboardOp>,
+
+    // -- serialized EEntityInits, kept on the context so it survives switching levels
+    clipboard: Option<String>,
+
+    pub outliner: SOutliner,
 
     // -- imgui persistent storage
     imgui_level_name: imgui::ImString,
 }
 
+// -- persistent state for the outliner window
+pub struct SOutliner {
+    pub filter: imgui::ImString,
+    // -- 0 shows every type, otherwise an index + 1 into EEntityType::SPAWNABLE
+    pub type_filter: usize,
+    pub new_folder_name: imgui::ImString,
+    pub rename: imgui::ImString,
+    pub renaming_entity: Option<SEntityHandle>,
+    pub dragging_entity: Option<SEntityHandle>,
+}
+
+impl SOutliner {
+    pub fn new() -> Self {
+        Self {
+            filter: imgui::ImString::with_capacity(64),
+            type_filter: 0,
+            new_folder_name: imgui::ImString::with_capacity(64),
+            rename: imgui::ImString::with_capacity(64),
+            renaming_entity: None,
+            dragging_entity: None,
+        }
+    }
+
+    pub fn passes_filter(&self, name: &str, entity_type: EEntityType) -> bool {
+        if self.type_filter > 0 && EEntityType::SPAWNABLE[self.type_filter - 1] != entity_type {
+            return false;
+        }
+
+        let filter = self.filter.to_str();
+        filter.is_empty() || name.to_lowercase().contains(filter.to_lowercase().as_str())
+    }
+}
+
 #[derive(PartialEq, Clone)]
 pub enum EEditMode {
     None,
@@ -84,6 +200,7 @@ impl SEditModeInput {
         input: &input::SInput,
         render: &render::SRender,
         imgui_want_capture_mouse: bool,
+        imgui_want_capture_keyboard: bool,
     ) -> Self {
         Self {
             window_width,
@@ -92,14 +209,335 @@ impl SEditModeInput {
             camera_pos_world: camera.pos_world,
             camera_forward: camera.forward_world(),
             world_to_view_matrix: camera.world_to_view_matrix(),
+            projection_matrix: camera.projection_matrix(
+                (window_width as f32) / (window_height as f32),
+                render.fovy(),
+                render.znear(),
+            ),
             fovy: render.fovy(),
             znear: render.znear(),
+            ortho_height: camera.ortho_height(),
             imgui_want_capture_mouse,
+            imgui_want_capture_keyboard,
         }
     }
+
+    // -- ortho views clip on both sides of the camera, so nothing is ever behind it
+    pub fn is_in_front_of_camera(&self, pos_world: &Vec3) -> bool {
+        self.ortho_height.is_some() || Vec3::dot(&(*pos_world - self.camera_pos_world), &self.camera_forward) > 0.0
+    }
+}
+
+impl SEditModeSnapSettings {
+    const VERTEX_SNAP_MAX_PIXELS: f32 = 20.0;
+
+    pub fn new() -> Self {
+        Self {
+            grid_enabled: false,
+            grid_size: 0.5,
+            rotation_enabled: false,
+            rotation_increment_degrees: 15.0,
+
+            draw_grid: true,
+            grid_draw_half_cells: 20,
+        }
+    }
+
+    // -- holding control inverts whether grid/angle snapping is on
+    pub fn grid_active(&self, input: &input::SInput) -> bool {
+        self.grid_enabled != input.control_down
+    }
+
+    pub fn rotation_active(&self, input: &input::SInput) -> bool {
+        self.rotation_enabled != input.control_down
+    }
+
+    // -- V snaps to the vertex nearest the cursor, control+shift snaps to the surface under the cursor
+    pub fn snap_target(input: &input::SInput) -> Option<ESnapTarget> {
+        if input.v_down {
+            Some(ESnapTarget::Vertex)
+        }
+        else if input.control_down && input.shift_down {
+            Some(ESnapTarget::Surface)
+        }
+        else {
+            None
+        }
+    }
+
+    pub fn draw_grid(&self, render: &mut render::SRender, editmode_input: &SEditModeInput) {
+        let cell = if self.grid_size > 0.0 { self.grid_size } else { 1.0 };
+        let half_extent = cell * (self.grid_draw_half_cells as f32);
+
+        // -- perspective always uses the ground plane, ortho views use the world plane through the
+        // -- origin facing them, so front and side views get an XY/ZY grid instead of an edge-on XZ one
+        let forward = &editmode_input.camera_forward;
+        let (u_axis, v_axis) = {
+            if editmode_input.ortho_height.is_none() {
+                (0, 2)
+            }
+            else if forward.z.abs() > forward.x.abs() && forward.z.abs() > forward.y.abs() {
+                (0, 1)
+            }
+            else if forward.x.abs() > forward.y.abs() {
+                (2, 1)
+            }
+            else {
+                (0, 2)
+            }
+        };
+
+        let axis_colors = [
+            Vec4::new(1.0, 0.0, 0.0, 0.6),
+            Vec4::new(0.0, 1.0, 0.0, 0.6),
+            Vec4::new(0.0, 0.0, 1.0, 0.6),
+        ];
+        let color = Vec4::new(0.5, 0.5, 0.5, 0.4);
+
+        let to_world = |u: f32, v: f32| -> Vec3 {
+            let mut result = [0.0; 3];
+            result[u_axis] = u;
+            result[v_axis] = v;
+            Vec3::new(result[0], result[1], result[2])
+        };
+
+        // -- keep the grid centered under the camera, aligned to the cell size
+        let camera_pos = [editmode_input.camera_pos_world.x, editmode_input.camera_pos_world.y, editmode_input.camera_pos_world.z];
+        let center_u = utils::snap_to_increment(camera_pos[u_axis], cell);
+        let center_v = utils::snap_to_increment(camera_pos[v_axis], cell);
+
+        for i in 0..=(2 * self.grid_draw_half_cells) {
+            let offset = (i as f32) * cell - half_extent;
+
+            let u = center_u + offset;
+            let line_color = if u.abs() < (cell * 0.5) { &axis_colors[v_axis] } else { &color };
+            render.temp().draw_line(
+                &to_world(u, center_v - half_extent),
+                &to_world(u, center_v + half_extent),
+                line_color,
+                false,
+                None,
+            );
+
+            let v = center_v + offset;
+            let line_color = if v.abs() < (cell * 0.5) { &axis_colors[u_axis] } else { &color };
+            render.temp().draw_line(
+                &to_world(center_u - half_extent, v),
+                &to_world(center_u + half_extent, v),
+                line_color,
+                false,
+                None,
+            );
+        }
+    }
+
+    pub fn imgui_menu(&mut self, ui: &imgui::Ui) {
+        use imgui::*;
+
+        if ui.collapsing_header(im_str!("Snapping")).build() {
+            ui.checkbox(im_str!("Grid snap (ctrl toggles)"), &mut self.grid_enabled);
+            ui.input_float(im_str!("Grid size"), &mut self.grid_size).step(0.1).build();
+            ui.checkbox(im_str!("Rotation snap (ctrl toggles)"), &mut self.rotation_enabled);
+            ui.input_float(im_str!("Rotation increment (deg)"), &mut self.rotation_increment_degrees).step(1.0).build();
+            ui.checkbox(im_str!("Draw grid"), &mut self.draw_grid);
+            ui.text(im_str!("Hold V: snap to vertex"));
+            ui.text(im_str!("Hold ctrl+shift: snap to surface"));
+        }
+    }
+}
+
+impl SCameraBookmarks {
+    fn file_path(level_file_path: &str) -> std::path::PathBuf {
+        std::path::Path::new(level_file_path).with_extension("bookmarks")
+    }
+
+    pub fn load(level_file_path: &str) -> Self {
+        match std::fs::read_to_string(Self::file_path(level_file_path)) {
+            Ok(json_str) => serde_json::from_str(json_str.as_str()).unwrap_or_default(),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn save(&self, level_file_path: &str) -> Result<(), &'static str> {
+        let json_str = serde_json::to_string_pretty(self).map_err(|_| "failed to serialize camera bookmarks")?;
+        std::fs::write(Self::file_path(level_file_path), json_str).map_err(|_| "failed to write camera bookmarks")?;
+        Ok(())
+    }
+}
+
+impl SEditingLevel {
+    pub fn new(file_path: String, level_init: level::SInit, level: level::SLevel) -> Self {
+        let camera_bookmarks = SCameraBookmarks::load(file_path.as_str());
+        Self {
+            file_path,
+            level_init,
+            level,
+            camera_bookmarks,
+        }
+    }
+
+    pub fn save(&mut self, game_context: &SGameContext) -> Result<(), &'static str> {
+        // -- refresh the init from the live entities so edits made with the gizmos are kept
+        self.level_init.refresh_entity_inits(game_context, self.level.owned_entities());
+
+        let level_json_str = serde_json::to_string_pretty(&self.level_init).map_err(|_| "failed to serialize level")?;
+        std::fs::write(&self.file_path, level_json_str).map_err(|_| "failed to write level file")?;
+        Ok(())
+    }
+
+    // -- keep the level init in sync with edits made to a live entity
+    pub fn set_entity_init(&mut self, entity: SEntityHandle, e_init: EEntityInit) {
+        if let Some(idx) = self.level.owned_entities().iter().position(|e| *e == entity) {
+            self.level_init.set_entity_init(idx, e_init);
+        }
+    }
+
+    pub fn owned_entities(&self) -> &[SEntityHandle] {
+        self.level.owned_entities()
+    }
+
+    pub fn folders(&self) -> &[String] {
+        self.level_init.folders()
+    }
+
+    pub fn add_folder(&mut self, name: &str) -> Result<(), &'static str> {
+        self.level_init.add_folder(name)
+    }
+
+    pub fn remove_folder(&mut self, name: &str) {
+        self.level_init.remove_folder(name);
+    }
+
+    pub fn entity_editor_data(&self, entity: SEntityHandle) -> level::SEntityEditorData {
+        match self.level.owned_entities().iter().position(|e| *e == entity) {
+            Some(idx) => self.level_init.entity_editor_data(idx),
+            None => level::SEntityEditorData::default(),
+        }
+    }
+
+    pub fn set_entity_editor_data(&mut self, game_context: &SGameContext, entity: SEntityHandle, editor_data: level::SEntityEditorData) {
+        if let Some(idx) = self.level.owned_entities().iter().position(|e| *e == entity) {
+            game_context.data_bucket.get::<SEntityBucket>().with_mut(|entities: &mut SEntityBucket| {
+                entities.set_entity_hidden(entity, editor_data.hidden);
+            });
+            self.level_init.set_entity_editor_data(idx, editor_data);
+        }
+    }
+
+    // -- hidden and locked entities can't be picked or marquee selected in the viewport
+    pub fn is_pickable(&self, entity: SEntityHandle) -> bool {
+        let editor_data = self.entity_editor_data(entity);
+        !editor_data.hidden && !editor_data.locked
+    }
+
+    // -- locked entities can still be selected from the outliner, but can't be moved, deleted or duplicated
+    pub fn is_locked(&self, entity: SEntityHandle) -> bool {
+        self.entity_editor_data(entity).locked
+    }
+
+    pub fn unpickable_entities(&self) -> Vec<SEntityHandle> {
+        self.level.owned_entities().iter()
+            .filter(|e| !self.is_pickable(**e))
+            .cloned()
+            .collect()
+    }
+
+    pub fn rename_entity(&mut self, game_context: &SGameContext, entity: SEntityHandle, name: &str) {
+        let mut e_init = EEntityInit::new_from_entity(game_context, entity);
+        e_init.set_debug_name(Some(String::from(name)));
+        e_init.apply_to_entity(game_context, entity);
+        self.set_entity_init(entity, e_init);
+    }
+
+    pub fn unique_debug_name(&self, entities: &SEntityBucket, base: &str) -> String {
+        let name_taken = |name: &str| {
+            self.level.owned_entities().iter().any(|e| {
+                match entities.get_entity_debug_name(*e) {
+                    Some(n) => unsafe { n._debug_ptr.as_ref() }.map_or(false, |n_str| n_str == name),
+                    None => false,
+                }
+            })
+        };
+
+        let mut idx = 0;
+        loop {
+            let candidate = format!("{}_{}", base, idx);
+            if !name_taken(candidate.as_str()) {
+                return candidate;
+            }
+            idx += 1;
+        }
+    }
+
+    pub fn add_entity(&mut self, game_context: &SGameContext, e_init: EEntityInit) -> Result<SEntityHandle, &'static str> {
+        self.level.add_entity(game_context, &mut self.level_init, e_init)
+    }
+
+    pub fn remove_entities(&mut self, game_context: &SGameContext, entities: &[SEntityHandle]) {
+        self.level.remove_entities(game_context, &mut self.level_init, entities);
+    }
+
+    pub fn snapshot_for_play(&self, game_context: &SGameContext, time_s: f32) -> SPlaySnapshot {
+        let mut entity_inits = Vec::with_capacity(self.level.owned_entities().len());
+        let mut entity_editor_data = Vec::with_capacity(self.level.owned_entities().len());
+        for e in self.level.owned_entities() {
+            entity_inits.push(EEntityInit::new_from_entity(game_context, *e));
+            entity_editor_data.push(self.entity_editor_data(*e));
+        }
+
+        SPlaySnapshot {
+            entities: self.level.owned_entities().to_vec(),
+            entity_inits,
+            entity_editor_data,
+            time_s,
+        }
+    }
+
+    // -- entities in keep_changes hold on to whatever happened to them during play. Entities destroyed
+    // -- during play are recreated with new handles, returned as (handle in snapshot, new handle)
+    pub fn restore_from_play(
+        &mut self,
+        game_context: &SGameContext,
+        snapshot: &SPlaySnapshot,
+        keep_changes: &[SEntityHandle],
+    ) -> Result<Vec<(SEntityHandle, SEntityHandle)>, &'static str> {
+        // -- anything created while playing isn't part of the edited level
+        let added : Vec<SEntityHandle> = self.level.owned_entities().iter()
+            .filter(|e| !snapshot.entities.contains(e))
+            .cloned()
+            .collect();
+        if added.len() > 0 {
+            self.remove_entities(game_context, added.as_slice());
+        }
+
+        let mut recreated = Vec::new();
+        for (i, (e, e_init)) in snapshot.entities.iter().zip(snapshot.entity_inits.iter()).enumerate() {
+            // -- everything before i already matches the snapshot, so putting it back at i keeps the order
+            if !self.level.owned_entities().contains(e) {
+                let new_e = self.level.insert_entity(game_context, &mut self.level_init, i, e_init.clone())?;
+                self.set_entity_editor_data(game_context, new_e, snapshot.entity_editor_data[i].clone());
+                recreated.push((*e, new_e));
+                continue;
+            }
+
+            if keep_changes.contains(e) {
+                self.set_entity_init(*e, EEntityInit::new_from_entity(game_context, *e));
+            }
+            else {
+                e_init.apply_to_entity(game_context, *e);
+                self.set_entity_init(*e, e_init.clone());
+            }
+        }
+
+        Ok(recreated)
+    }
 }
 
 impl SEditModeContext {
+    const MAX_SELECTION: usize = 1024;
+    const MARQUEE_MIN_PIXELS: i32 = 4;
+    const PASTE_OFFSET: Vec3 = Vec3{ x: 1.0, y: 0.0, z: 0.0 };
+
     pub fn new(render: &mut render::SRender) -> Result<Self, &'static str> {
         // -- set up translation widget
         let mut translation_widgets = [
@@ -118,6 +556,11 @@ impl SEditModeContext {
         ];
         translation_widget_transforms[0].r = Quat::new_angle_axis(utils::PI / 2.0, &Vec3::new(0.0, 1.0, 0.0));
         translation_widget_transforms[1].r = Quat::new_angle_axis(-utils::PI / 2.0, &Vec3::new(1.0, 0.0, 0.0));
+        let translation_widget_axis_rotations = [
+            translation_widget_transforms[0].r,
+            translation_widget_transforms[1].r,
+            translation_widget_transforms[2].r,
+        ];
 
         // -- set up rotation widget
         let mut rotation_widgets = [
@@ -136,18 +579,42 @@ impl SEditModeContext {
         ];
         rotation_widget_transforms[0].r = Quat::new_angle_axis(utils::PI / 2.0, &Vec3::new(0.0, 0.0, 1.0));
         rotation_widget_transforms[2].r = Quat::new_angle_axis(utils::PI / 2.0, &Vec3::new(1.0, 0.0, 0.0));
+        let rotation_widget_axis_rotations = [
+            rotation_widget_transforms[0].r,
+            rotation_widget_transforms[1].r,
+            rotation_widget_transforms[2].r,
+        ];
 
         Ok(Self {
             editing_level: None,
 
-            editing_entity: None,
+            selection: SVec::new(&SYSTEM_ALLOCATOR(), Self::MAX_SELECTION, 0)?,
+            active_entity: None,
+            pivot_mode: EPivotMode::Centroid,
+            gizmo_space: EGizmoSpace::World,
+            snap_settings: SEditModeSnapSettings::new(),
+
+            spawn_type: None,
+            spawn_align_to_normal: false,
+
             translation_widgets,
             translation_widget_transforms,
+            translation_widget_axis_rotations,
             rotation_widgets,
             rotation_widget_transforms,
+            rotation_widget_axis_rotations,
 
             clicked_entity: None,
             can_select_clicked_entity: false,
+            marquee_start: None,
+
+            delete_selection_requested: false,
+            duplicate_selection_requested: false,
+            clipboard_op_requested: None,
+
+            clipboard: None,
+
+            outliner: SOutliner::new(),
 
             imgui_level_name: imgui::ImString::with_capacity(128),
         })
@@ -158,11 +625,8 @@ impl SEditModeContext {
         let level_init : level::SInit = serde_json::from_str(level_json_str.as_str()).unwrap();
         let level = level::SLevel::new(&SYSTEM_ALLOCATOR(), game_context, &level_init).unwrap();
 
-        self.editing_level = Some(SEditingLevel{
-            file_path: String::from(asset_path),
-            level_init,
-            level,
-        });
+        self.clear_selection();
+        self.editing_level = Some(SEditingLevel::new(String::from(asset_path), level_init, level));
     }
 
     pub fn close_level(&mut self, game_context: &SGameContext) {
@@ -171,10 +635,349 @@ impl SEditModeContext {
         }
 
         self.editing_level = None;
+        self.clear_selection();
     }
 
+    // -- the active entity is the most recently selected one, and is the pivot in EPivotMode::ActiveEntity
     pub fn editing_entity(&self) -> Option<SEntityHandle> {
-        self.editing_entity
+        self.active_entity
+    }
+
+    pub fn selected_entities(&self) -> &[SEntityHandle] {
+        self.selection.as_slice()
+    }
+
+    pub fn unlocked_selection(&self) -> Vec<SEntityHandle> {
+        self.selection.iter()
+            .filter(|e| !self.editing_level.as_ref().map_or(false, |l| l.is_locked(**e)))
+            .cloned()
+            .collect()
+    }
+
+    pub fn is_selected(&self, entity: SEntityHandle) -> bool {
+        self.selection.contains(&entity)
+    }
+
+    pub fn select(&mut self, entity: SEntityHandle, additive: bool) {
+        if !additive {
+            self.selection.clear();
+        }
+
+        if let Some(idx) = self.selection.iter().position(|e| *e == entity) {
+            // -- additive select of an already selected entity toggles it off
+            self.selection.remove(idx);
+            self.active_entity = self.selection.last().cloned();
+        }
+        else {
+            self.selection.push(entity);
+            self.active_entity = Some(entity);
+        }
+    }
+
+    pub fn set_active_entity(&mut self, entity: SEntityHandle) {
+        if !self.is_selected(entity) {
+            self.selection.push(entity);
+        }
+        self.active_entity = Some(entity);
+    }
+
+    // -- for a selected entity that was destroyed and recreated with a new handle
+    pub fn remap_selection(&mut self, old: SEntityHandle, new: SEntityHandle) {
+        for e in self.selection.as_mut_slice() {
+            if *e == old {
+                *e = new;
+            }
+        }
+        if self.active_entity == Some(old) {
+            self.active_entity = Some(new);
+        }
+    }
+
+    pub fn clear_selection(&mut self) {
+        self.selection.clear();
+        self.active_entity = None;
+    }
+
+    pub fn request_delete_selection(&mut self) {
+        self.delete_selection_requested = true;
+    }
+
+    pub fn request_duplicate_selection(&mut self) {
+        self.duplicate_selection_requested = true;
+    }
+
+    pub fn request_clipboard_op(&mut self, op: EClipboardOp) {
+        self.clipboard_op_requested = Some(op);
+    }
+
+    pub fn clipboard_has_entities(&self) -> bool {
+        self.clipboard.is_some()
+    }
+
+    fn prune_invalid_selection(&mut self, entities: &SEntityBucket) {
+        let mut i = 0;
+        while i < self.selection.len() {
+            if entities.entity_valid(self.selection[i]) {
+                i += 1;
+            }
+            else {
+                self.selection.remove(i);
+            }
+        }
+
+        if let Some(active) = self.active_entity {
+            if !self.is_selected(active) {
+                self.active_entity = self.selection.last().cloned();
+            }
+        }
+    }
+
+    pub fn selection_pivot(&self, entities: &SEntityBucket) -> Option<Vec3> {
+        if self.selection.len() == 0 {
+            return None;
+        }
+
+        match self.pivot_mode {
+            EPivotMode::ActiveEntity => {
+                self.active_entity.map(|e| entities.get_entity_location(e).t)
+            },
+            EPivotMode::Centroid => {
+                let mut sum = Vec3::zero();
+                for e in self.selection.as_slice() {
+                    sum = sum + entities.get_entity_location(*e).t;
+                }
+                Some((1.0 / (self.selection.len() as f32)) * sum)
+            },
+        }
+    }
+
+    // -- orientation of the gizmo axes, local space follows the active entity
+    pub fn gizmo_orientation(&self, entities: &SEntityBucket) -> Quat {
+        match self.gizmo_space {
+            EGizmoSpace::World => Quat::new_identity(),
+            EGizmoSpace::Local => {
+                match self.active_entity {
+                    Some(e) => entities.get_entity_location(e).r,
+                    None => Quat::new_identity(),
+                }
+            },
+        }
+    }
+
+    pub fn gizmo_axis_dir(&self, entities: &SEntityBucket, axis: usize) -> Vec3 {
+        let mut axis_dir = Vec3::zero();
+        axis_dir[axis] = 1.0;
+        Quat::rotate_vec3(&self.gizmo_orientation(entities), &axis_dir)
+    }
+
+    pub fn cycle_gizmo_space(&mut self) {
+        self.gizmo_space = match self.gizmo_space {
+            EGizmoSpace::World => EGizmoSpace::Local,
+            EGizmoSpace::Local => EGizmoSpace::World,
+        };
+    }
+
+    fn selection_locations(&self, entities: &SEntityBucket) -> Vec<(SEntityHandle, STransform)> {
+        self.unlocked_selection().iter().map(|e| (*e, entities.get_entity_location(*e))).collect()
+    }
+
+    fn select_in_screen_rect(&mut self, entities: &SEntityBucket, editmode_input: &SEditModeInput, corner_a: [i32; 2], corner_b: [i32; 2], additive: bool) {
+        if !additive {
+            self.clear_selection();
+        }
+
+        let min = [std::cmp::min(corner_a[0], corner_b[0]) as f32, std::cmp::min(corner_a[1], corner_b[1]) as f32];
+        let max = [std::cmp::max(corner_a[0], corner_b[0]) as f32, std::cmp::max(corner_a[1], corner_b[1]) as f32];
+
+        let mut in_rect = Vec::new();
+        if let Some(editing_level) = &self.editing_level {
+            for e in editing_level.level.owned_entities() {
+                if !editing_level.is_pickable(*e) {
+                    continue;
+                }
+
+                let pos = entities.get_entity_location(*e).t;
+
+                // -- skip anything behind the camera, the projection mirrors it on screen
+                if !editmode_input.is_in_front_of_camera(&pos) {
+                    continue;
+                }
+
+                let screen_pos = world_pos_to_screen_pos(&pos, editmode_input);
+                if screen_pos.x >= min[0] && screen_pos.x <= max[0] && screen_pos.y >= min[1] && screen_pos.y <= max[1] {
+                    in_rect.push(*e);
+                }
+            }
+        }
+
+        for e in in_rect {
+            if !self.is_selected(e) {
+                self.selection.push(e);
+                self.active_entity = Some(e);
+            }
+        }
+    }
+
+    fn delete_selection(&mut self, game_context: &SGameContext) {
+        let unlocked = self.unlocked_selection();
+        if let Some(editing_level) = &mut self.editing_level {
+            editing_level.remove_entities(game_context, unlocked.as_slice());
+        }
+
+        self.clear_selection();
+    }
+
+    pub fn camera_bookmark(&self, slot: usize) -> Option<&camera::SCameraBookmark> {
+        self.editing_level.as_ref().and_then(|l| l.camera_bookmarks.slots[slot].as_ref())
+    }
+
+    pub fn set_camera_bookmark(&mut self, slot: usize, bookmark: camera::SCameraBookmark) {
+        if let Some(editing_level) = &mut self.editing_level {
+            editing_level.camera_bookmarks.slots[slot] = Some(bookmark);
+            if let Err(e) = editing_level.camera_bookmarks.save(editing_level.file_path.as_str()) {
+                println!("ERROR: {}", e);
+            }
+        }
+    }
+
+    pub fn editing_level(&self) -> Option<&SEditingLevel> {
+        self.editing_level.as_ref()
+    }
+
+    pub fn editing_level_mut(&mut self) -> Option<&mut SEditingLevel> {
+        self.editing_level.as_mut()
+    }
+
+    pub fn spawn_entity(&mut self, game_context: &SGameContext, entity_type: EEntityType, location: STransform) -> Result<SEntityHandle, &'static str> {
+        let editing_level = self.editing_level.as_mut().ok_or("no level open to spawn into")?;
+
+        let debug_name = game_context.data_bucket.get_entities().with(|entities: &SEntityBucket| {
+            editing_level.unique_debug_name(entities, entity_type.name())
+        });
+        let e_init = EEntityInit::new_default(entity_type, Some(debug_name), location);
+        let entity = editing_level.add_entity(game_context, e_init)?;
+
+        self.select(entity, false);
+        Ok(entity)
+    }
+
+    fn serialize_entities(game_context: &SGameContext, entities: &[SEntityHandle]) -> Result<String, &'static str> {
+        let inits : Vec<EEntityInit> = entities.iter()
+            .map(|e| EEntityInit::new_from_entity(game_context, *e))
+            .collect();
+        serde_json::to_string_pretty(&inits).map_err(|_| "failed to serialize entities")
+    }
+
+    // -- add serialized entities to the editing level, offset so their centroid lands on target_pos if provided
+    fn add_serialized_entities(&mut self, game_context: &SGameContext, serialized: &str, target_pos: Option<Vec3>) -> Result<(), &'static str> {
+        let mut inits : Vec<EEntityInit> = serde_json::from_str(serialized).map_err(|_| "clipboard does not contain entities")?;
+        if inits.len() == 0 {
+            return Ok(());
+        }
+
+        let editing_level = self.editing_level.as_mut().ok_or("no level open to add entities to")?;
+
+        let offset = match target_pos {
+            Some(pos) => {
+                let mut sum = Vec3::zero();
+                for init in &inits {
+                    sum = sum + init.starting_location().t;
+                }
+                pos - (1.0 / (inits.len() as f32)) * sum
+            },
+            None => Vec3::zero(),
+        };
+
+        let mut added = Vec::with_capacity(inits.len());
+        for mut init in inits.drain(..) {
+            let mut location = init.starting_location();
+            location.t = location.t + offset;
+            init.set_starting_location(location);
+
+            let debug_name = game_context.data_bucket.get_entities().with(|entities: &SEntityBucket| {
+                let base = init.debug_name().map_or(init.entity_type().name(), |n| debug_name_base(n));
+                editing_level.unique_debug_name(entities, base)
+            });
+            init.set_debug_name(Some(debug_name));
+
+            match editing_level.add_entity(game_context, init) {
+                Ok(e) => added.push(e),
+                Err(e) => println!("ERROR: failed to add entity: {}", e),
+            }
+        }
+
+        // -- added entities become the selection, so they can be dragged into place
+        self.clear_selection();
+        for e in added {
+            self.select(e, true);
+        }
+
+        Ok(())
+    }
+
+    fn duplicate_selection(&mut self, game_context: &SGameContext) {
+        let result = Self::serialize_entities(game_context, self.unlocked_selection().as_slice())
+            .and_then(|serialized| self.add_serialized_entities(game_context, serialized.as_str(), None));
+        if let Err(e) = result {
+            println!("ERROR: failed to duplicate selection: {}", e);
+        }
+    }
+
+    pub fn copy_selection(&mut self, game_context: &SGameContext) {
+        if self.selection.len() == 0 {
+            return;
+        }
+
+        match Self::serialize_entities(game_context, self.selection.as_slice()) {
+            Ok(serialized) => self.clipboard = Some(serialized),
+            Err(e) => println!("ERROR: failed to copy selection: {}", e),
+        }
+    }
+
+    // -- locked entities are left in place, so only the unlocked ones go to the clipboard
+    pub fn cut_selection(&mut self, game_context: &SGameContext) {
+        let unlocked = self.unlocked_selection();
+        if unlocked.len() == 0 {
+            return;
+        }
+
+        match Self::serialize_entities(game_context, unlocked.as_slice()) {
+            Ok(serialized) => self.clipboard = Some(serialized),
+            Err(e) => {
+                println!("ERROR: failed to cut selection: {}", e);
+                return;
+            },
+        }
+        self.delete_selection(game_context);
+    }
+
+    // -- without a target position entities are pasted offset from where they were copied
+    pub fn paste(&mut self, game_context: &SGameContext, target_pos: Option<Vec3>) {
+        let clipboard = match &self.clipboard {
+            Some(c) => c.clone(),
+            None => return,
+        };
+
+        let target_pos = target_pos.or_else(|| {
+            let mut sum = Vec3::zero();
+            let inits : Vec<EEntityInit> = serde_json::from_str(clipboard.as_str()).ok()?;
+            for init in &inits {
+                sum = sum + init.starting_location().t;
+            }
+            Some((1.0 / (inits.len().max(1) as f32)) * sum + Self::PASTE_OFFSET)
+        });
+
+        if let Err(e) = self.add_serialized_entities(game_context, clipboard.as_str(), target_pos) {
+            println!("ERROR: failed to paste: {}", e);
+        }
+    }
+}
+
+// -- strips a trailing "_N" so copies of "crate_3" are named "crate_4" rather than "crate_3_0"
+fn debug_name_base(name: &str) -> &str {
+    match name.rfind('_') {
+        Some(idx) if idx + 1 < name.len() && name[idx + 1..].chars().all(|c| c.is_ascii_digit()) => &name[..idx],
+        _ => name,
     }
 }
 
@@ -210,22 +1013,27 @@ impl EEditMode {
         render: &render::SRender,
         entities: &SEntityBucket,
     ) -> EEditMode {
-        if editmode_input.imgui_want_capture_mouse || !input.left_mouse_edge.down() {
+        if editmode_input.imgui_want_capture_mouse || input.alt_down || !input.left_mouse_edge.down() {
             return EEditMode::Translation;
         }
 
-        let e = em.editing_entity.expect("shouldn't be able to translate without entity picked.");
+        let pivot = em.selection_pivot(entities).expect("shouldn't be able to translate without entity picked.");
 
         let cursor_ray = cursor_ray_world(&editmode_input);
         for axis in 0..=2 {
+            if axis_degenerate_on_screen(&pivot, &em.gizmo_axis_dir(entities, axis), &editmode_input) {
+                continue;
+            }
+
             if let Some(_) = render.ray_intersects(&em.translation_widgets[axis], &cursor_ray.origin, &cursor_ray.dir, &em.translation_widget_transforms[axis]) {
-                let e_pos = entities.get_entity_location(e).t;
-                let e_pos_screen = world_pos_to_screen_pos(&e_pos, &editmode_input);
-                let mouse_offset = [(e_pos_screen.x as i32) - editmode_input.mouse_window_pos[0], (e_pos_screen.y as i32) - editmode_input.mouse_window_pos[1]];
+                let pivot_screen = world_pos_to_screen_pos(&pivot, &editmode_input);
+                let mouse_offset = [(pivot_screen.x as i32) - editmode_input.mouse_window_pos[0], (pivot_screen.y as i32) - editmode_input.mouse_window_pos[1]];
 
                 em.can_select_clicked_entity = false;
 
-                return EEditMode::TranslationDragging(SEditModeTranslationDragging::new(e, axis, e_pos, mouse_offset));
+                let orientation = em.gizmo_orientation(entities);
+                let start_locations = em.selection_locations(entities);
+                return EEditMode::TranslationDragging(SEditModeTranslationDragging::new(axis, orientation, pivot, mouse_offset, start_locations));
             }
         }
 
@@ -242,29 +1050,27 @@ impl EEditMode {
     ) -> EEditMode {
         let mut result = EEditMode::Rotation;
 
-        if editmode_input.imgui_want_capture_mouse || !input.left_mouse_edge.down() {
+        if editmode_input.imgui_want_capture_mouse || input.alt_down || !input.left_mouse_edge.down() {
             return result;
         }
 
-        let e = em.editing_entity.expect("shouldn't be able to rotate without entity picked.");
+        let pivot = em.selection_pivot(entities).expect("shouldn't be able to rotate without entity picked.");
         let cursor_ray = cursor_ray_world(&editmode_input);
         let mut min_t = None;
         for axis in 0..=2 {
             if let Some(_) = render.ray_intersects(&em.rotation_widgets[axis], &cursor_ray.origin, &cursor_ray.dir, &em.rotation_widget_transforms[axis]) {
 
-                let e_loc = entities.get_entity_location(e);
-
-                let mut plane_normal = Vec3::zero();
-                plane_normal[axis] = 1.0;
-                let plane = utils::SPlane::new(&e_loc.t, &plane_normal);
+                let plane_normal = em.gizmo_axis_dir(entities, axis);
+                let plane = utils::SPlane::new(&pivot, &plane_normal);
                 let cursor_ray_world = cursor_ray_world(&editmode_input);
 
                 em.can_select_clicked_entity = false;
 
                 if let Some((cursor_pos_world, t)) = utils::ray_plane_intersection(&cursor_ray_world, &plane) {
                     if min_t.is_none() || min_t.unwrap() > t {
-                        let rotation_start_entity_to_cursor = cursor_pos_world - e_loc.t;
-                        result = EEditMode::RotationDragging(SEditModeRotationDragging::new(e, axis, e_loc.r, rotation_start_entity_to_cursor));
+                        let rotation_start_pivot_to_cursor = cursor_pos_world - pivot;
+                        let start_locations = em.selection_locations(entities);
+                        result = EEditMode::RotationDragging(SEditModeRotationDragging::new(axis, em.gizmo_orientation(entities), pivot, rotation_start_pivot_to_cursor, start_locations));
                         min_t = Some(t);
                     }
                 }
@@ -286,49 +1092,126 @@ impl EEditMode {
 
         drop(self);
 
-        // -- cast ray to select entity for edit mode
-        ctxt.clicked_entity = None;
-        if input.left_mouse_edge.down() && !em_input.imgui_want_capture_mouse && !mode.eats_mouse() {
-            data_bucket.get::<bvh::STree<SEntityHandle>>().with(|bvh: &bvh::STree<SEntityHandle>| {
+        // -- place entity chosen from the spawn palette
+        // -- alt + click drives the camera
+        let mut click_consumed = input.alt_down;
+        // -- hotkeys are ignored while typing into an imgui text field
+        let hotkeys_enabled = !em_input.imgui_want_capture_mouse && !em_input.imgui_want_capture_keyboard;
+        if ctxt.spawn_type.is_some() && ((input.escape_edge.down() && hotkeys_enabled) || input.right_mouse_edge.down()) {
+            ctxt.spawn_type = None;
+        }
+        if let Some(spawn_type) = ctxt.spawn_type {
+            if input.left_mouse_edge.down() && !em_input.imgui_want_capture_mouse && !mode.eats_mouse() {
+                click_consumed = true;
 
-                STACK_ALLOCATOR.with(|sa| {
-                    let cursor_ray = cursor_ray_world(&em_input);
+                if let Some(location) = find_spawn_location(data_bucket, &em_input, ctxt.spawn_align_to_normal) {
+                    if let Err(e) = ctxt.spawn_entity(gc, spawn_type, location) {
+                        println!("ERROR: failed to spawn entity: {}", e);
+                    }
+                }
 
-                    let mut bvh_results = SVec::<(f32, SEntityHandle)>::new(&sa.as_ref(), 256, 0).unwrap();
-                    bvh.cast_ray(&cursor_ray, &mut bvh_results);
+                // -- shift keeps the palette entry armed for placing several
+                if !input.shift_down {
+                    ctxt.spawn_type = None;
+                }
+            }
+        }
 
-                    let mut min_t : Option::<f32> = None;
-                    let mut min_entity : Option<SEntityHandle> = None;
+        // -- cast ray to select entity for edit mode
+        ctxt.clicked_entity = None;
+        if input.left_mouse_edge.down() && !em_input.imgui_want_capture_mouse && !mode.eats_mouse() && !click_consumed {
+            let cursor_ray = cursor_ray_world(&em_input);
+            let unpickable = ctxt.editing_level.as_ref().map_or(Vec::new(), |l| l.unpickable_entities());
+            if let Some(hit) = pick_entity(data_bucket, &cursor_ray, unpickable.as_slice()) {
+                ctxt.clicked_entity = Some(hit.entity);
+                ctxt.can_select_clicked_entity = true;
+            }
+        }
 
-                    for (t, entity) in bvh_results.as_ref() {
-                        if *t < min_t.unwrap_or(std::f32::MAX) {
-                            if let Some(t_mesh) = render::cast_ray_against_entity_model(data_bucket, &cursor_ray, *entity) {
-                                min_t = Some(t_mesh);
-                                min_entity = Some(*entity);
-                            }
+        // -- batch operations on the selection
+        if input.delete_edge.down() && hotkeys_enabled {
+            ctxt.delete_selection_requested = true;
+        }
+        if ctxt.delete_selection_requested {
+            ctxt.delete_selection_requested = false;
+            if !mode.eats_mouse() {
+                ctxt.delete_selection(gc);
+            }
+        }
+        if input.control_down && hotkeys_enabled {
+            if input.c_edge.down() {
+                ctxt.clipboard_op_requested = Some(EClipboardOp::Copy);
+            }
+            else if input.x_edge.down() {
+                ctxt.clipboard_op_requested = Some(EClipboardOp::Cut);
+            }
+            else if input.v_edge.down() {
+                ctxt.clipboard_op_requested = Some(EClipboardOp::Paste);
+            }
+            else if input.d_edge.down() {
+                ctxt.duplicate_selection_requested = true;
+            }
+        }
+        if let Some(op) = ctxt.clipboard_op_requested.take() {
+            if !mode.eats_mouse() {
+                match op {
+                    EClipboardOp::Copy => ctxt.copy_selection(gc),
+                    EClipboardOp::Cut => ctxt.cut_selection(gc),
+                    EClipboardOp::Paste => {
+                        // -- pasting with the keyboard places the entities under the cursor
+                        let target_pos = if em_input.imgui_want_capture_mouse {
+                            None
                         }
-                    }
+                        else {
+                            find_spawn_location(data_bucket, &em_input, false).map(|loc| loc.t)
+                        };
+                        ctxt.paste(gc, target_pos);
+                    },
+                }
+            }
+        }
+        if ctxt.duplicate_selection_requested {
+            ctxt.duplicate_selection_requested = false;
+            if !mode.eats_mouse() {
+                ctxt.duplicate_selection(gc);
+            }
+        }
 
-                    if let Some(_) = min_entity {
-                        ctxt.clicked_entity = min_entity;
-                        ctxt.can_select_clicked_entity = true;
-                    }
-                });
+        data_bucket.get_entities().with(|entities: &SEntityBucket| {
+            ctxt.prune_invalid_selection(entities);
+        });
 
-            });
+        // -- toggle edit modes
+        if input.l_edge.down() && hotkeys_enabled && !mode.eats_mouse() {
+            ctxt.cycle_gizmo_space();
         }
 
-        // -- toggle edit modes
-        if input.t_edge.down() && ctxt.editing_entity.is_some() {
+        // -- a selection of only locked entities gets no gizmo
+        let has_selection = ctxt.unlocked_selection().len() > 0;
+        if input.t_edge.down() && hotkeys_enabled && has_selection {
             mode = EEditMode::Translation;
         }
-        else if input.r_edge.down() && ctxt.editing_entity.is_some() {
+        else if input.r_edge.down() && hotkeys_enabled && has_selection {
             mode = EEditMode::Rotation;
         }
+        else if !has_selection && !mode.eats_mouse() {
+            mode = EEditMode::None;
+        }
+
+        let mut snap_target_pos = None;
+        if let EEditMode::TranslationDragging(_) = &mode {
+            if let Some(snap_target) = SEditModeSnapSettings::snap_target(input) {
+                snap_target_pos = find_snap_target_pos(snap_target, ctxt.selected_entities(), &em_input, data_bucket);
+            }
+        }
 
         data_bucket.get_renderer()
             .and::<SEntityBucket>()
             .with_mm(|render, entities| {
+                if ctxt.snap_settings.draw_grid {
+                    ctxt.snap_settings.draw_grid(render, &em_input);
+                }
+
                 if mode == EEditMode::Translation {
                     mode = EEditMode::update_translation(ctxt, &em_input, &input, &render, &entities);
                 }
@@ -336,57 +1219,87 @@ impl EEditMode {
                     mode = EEditMode::update_rotation(ctxt, &em_input, &input, &render, &entities);
                 }
                 else if let EEditMode::TranslationDragging(data) = mode.clone() {
-                    mode = data.update(&input, &em_input, gc, render, entities);
+                    mode = data.update(&input, &em_input, &ctxt.snap_settings, snap_target_pos, gc, render, entities);
                 }
                 else if let EEditMode::RotationDragging(data) = mode.clone() {
-                    mode = data.update(&input, &em_input, gc, render, entities);
+                    mode = data.update(&input, &em_input, &ctxt.snap_settings, gc, render, entities);
                 }
             });
 
-        if ctxt.can_select_clicked_entity && ctxt.clicked_entity.is_some() {
-            ctxt.editing_entity = ctxt.clicked_entity;
+        if ctxt.can_select_clicked_entity {
+            if let Some(e) = ctxt.clicked_entity {
+                ctxt.select(e, input.shift_down);
+            }
         }
 
-        // -- move/scale edit widgets
-        if let Some(e) = ctxt.editing_entity {
-            let mut entity_valid = false;
-            data_bucket.get_entities().with(|entities: &SEntityBucket| {
-                if entities.entity_valid(e) {
-                    entity_valid = true;
-
-                    ctxt.translation_widget_transforms[0].t = entities.get_entity_location(e).t;
-                    ctxt.translation_widget_transforms[1].t = entities.get_entity_location(e).t;
-                    ctxt.translation_widget_transforms[2].t = entities.get_entity_location(e).t;
-                    //println!("Set translation widget: {:?}", translation_widget_transform.t);
-                    scale_to_fixed_screen_size(&mut ctxt.translation_widget_transforms[0], 0.02, &em_input);
-                    scale_to_fixed_screen_size(&mut ctxt.translation_widget_transforms[1], 0.02, &em_input);
-                    scale_to_fixed_screen_size(&mut ctxt.translation_widget_transforms[2], 0.02, &em_input);
-
-                    ctxt.rotation_widget_transforms[0].t = entities.get_entity_location(e).t;
-                    ctxt.rotation_widget_transforms[1].t = entities.get_entity_location(e).t;
-                    ctxt.rotation_widget_transforms[2].t = entities.get_entity_location(e).t;
-                    //println!("Set translation widget: {:?}", translation_widget_transform.t);
-                    scale_to_fixed_screen_size(&mut ctxt.rotation_widget_transforms[0], 0.034, &em_input);
-                    scale_to_fixed_screen_size(&mut ctxt.rotation_widget_transforms[1], 0.034, &em_input);
-                    scale_to_fixed_screen_size(&mut ctxt.rotation_widget_transforms[2], 0.034, &em_input);
+        // -- marquee selection, started by clicking empty space
+        if input.left_mouse_edge.down() && !em_input.imgui_want_capture_mouse && !mode.eats_mouse() && ctxt.clicked_entity.is_none() && !click_consumed {
+            ctxt.marquee_start = Some(em_input.mouse_window_pos);
+        }
+        if let Some(marquee_start) = ctxt.marquee_start {
+            let marquee_end = em_input.mouse_window_pos;
+            let dragged = (marquee_end[0] - marquee_start[0]).abs() > SEditModeContext::MARQUEE_MIN_PIXELS ||
+                          (marquee_end[1] - marquee_start[1]).abs() > SEditModeContext::MARQUEE_MIN_PIXELS;
+
+            if !input.left_mouse_down {
+                ctxt.marquee_start = None;
+
+                if dragged {
+                    data_bucket.get_entities().with(|entities: &SEntityBucket| {
+                        ctxt.select_in_screen_rect(entities, &em_input, marquee_start, marquee_end, input.shift_down);
+                    });
                 }
-            });
+                else if !input.shift_down {
+                    ctxt.clear_selection();
+                }
+            }
+            else if dragged {
+                data_bucket.get_renderer().with_mut(|render: &mut render::SRender| {
+                    draw_screen_rect(render, marquee_start, marquee_end, &Vec4::new(1.0, 1.0, 1.0, 1.0), &em_input);
+                });
+            }
+        }
+
+        // -- move/scale edit widgets
+        let mut pivot = None;
+        let mut orientation = Quat::new_identity();
+        data_bucket.get_entities().with(|entities: &SEntityBucket| {
+            pivot = ctxt.selection_pivot(entities);
+            orientation = ctxt.gizmo_orientation(entities);
+        });
+
+        if let Some(pivot_pos) = pivot {
+            // -- while dragging, the gizmo keeps the orientation it started with
+            match &mode {
+                EEditMode::TranslationDragging(data) => orientation = data.orientation,
+                EEditMode::RotationDragging(data) => orientation = data.orientation,
+                _ => {},
+            }
+
+            for axis in 0..=2 {
+                ctxt.translation_widget_transforms[axis].r = orientation * ctxt.translation_widget_axis_rotations[axis];
+                ctxt.rotation_widget_transforms[axis].r = orientation * ctxt.rotation_widget_axis_rotations[axis];
+
+                ctxt.translation_widget_transforms[axis].t = pivot_pos;
+                scale_to_fixed_screen_size(&mut ctxt.translation_widget_transforms[axis], 0.02, &em_input);
+
+                ctxt.rotation_widget_transforms[axis].t = pivot_pos;
+                scale_to_fixed_screen_size(&mut ctxt.rotation_widget_transforms[axis], 0.034, &em_input);
+            }
 
             // -- draw edit widgets
-            if entity_valid {
-                data_bucket.get_renderer().with_mut(|render: &mut render::SRender| {
-                    for axis in 0..=2 {
-                        if mode.show_translation_widget(axis) {
-                                render.temp().draw_model(&ctxt.translation_widgets[axis], &ctxt.translation_widget_transforms[axis], true);
-                        }
+            data_bucket.get_renderer().with_mut(|render: &mut render::SRender| {
+                for axis in 0..=2 {
+                    if mode.show_translation_widget(axis) {
+                            render.temp().draw_model(&ctxt.translation_widgets[axis], &ctxt.translation_widget_transforms[axis], true);
                     }
-                    for axis in 0..=2 {
-                        if mode.show_rotation_widget(axis) {
-                            render.temp().draw_model(&ctxt.rotation_widgets[axis], &ctxt.rotation_widget_transforms[axis], true);
-                        }
+                }
+                for axis in 0..=2 {
+                    if mode.show_rotation_widget(axis) {
+                        render.temp().draw_model(&ctxt.rotation_widgets[axis], &ctxt.rotation_widget_transforms[axis], true);
                     }
-                });
-            }
+                }
+            });
         }
 
         return mode;
@@ -394,12 +1307,13 @@ impl EEditMode {
 }
 
 impl SEditModeTranslationDragging {
-    pub fn new(entity: SEntityHandle, axis: usize, start_pos: Vec3, mouse_offset: [i32; 2]) -> Self {
+    pub fn new(axis: usize, orientation: Quat, start_pivot: Vec3, mouse_offset: [i32; 2], start_locations: Vec<(SEntityHandle, STransform)>) -> Self {
         Self{
-            entity,
             axis,
-            start_pos,
+            orientation,
+            start_pivot,
             mouse_offset,
+            start_locations,
         }
     }
 
@@ -407,6 +1321,8 @@ impl SEditModeTranslationDragging {
         &self,
         input: &input::SInput,
         editmode_input: &SEditModeInput,
+        snap_settings: &SEditModeSnapSettings,
+        snap_target_pos: Option<Vec3>,
         gc: &super::SGameContext,
         render: &mut render::SRender,
         entities: &mut SEntityBucket,
@@ -417,16 +1333,17 @@ impl SEditModeTranslationDragging {
         else {
             let mut line_dir = Vec3::zero();
             line_dir[self.axis] = 1.0;
+            let line_dir = Quat::rotate_vec3(&self.orientation, &line_dir);
 
-            let line_p0 = self.start_pos + -line_dir;
-            let line_p1 = self.start_pos + line_dir;
+            let line_p0 = self.start_pivot + -line_dir;
+            let line_p1 = self.start_pivot + line_dir;
 
             let mut render_color : Vec4 = Vec4::zero();
             render_color[self.axis] = 1.0;
             render_color.w = 1.0;
             render.temp().draw_line(
-                &(self.start_pos + -100.0 * line_dir),
-                &(self.start_pos + 100.0 * line_dir),
+                &(self.start_pivot + -100.0 * line_dir),
+                &(self.start_pivot + 100.0 * line_dir),
                 &render_color,
                 true,
                 None,
@@ -435,17 +1352,38 @@ impl SEditModeTranslationDragging {
             let offset_mouse_pos = [editmode_input.mouse_window_pos[0] + self.mouse_offset[0],
                                     editmode_input.mouse_window_pos[1] + self.mouse_offset[1]];
 
-            let new_world_pos = pos_on_screen_space_line_to_world(
+            let mut new_pivot = match pos_on_screen_space_line_to_world(
                 &line_p0,
                 &line_p1,
                 offset_mouse_pos,
                 &editmode_input,
-            );
+            ) {
+                Some(pos) => pos,
+                None => return EEditMode::TranslationDragging(self.clone()),
+            };
+
+            if let Some(target) = snap_target_pos {
+                // -- vertex/surface snapping is not constrained to the drag axis
+                new_pivot = target;
+            }
+            else if snap_settings.grid_active(input) {
+                // -- snap the pivot's coordinate along the drag axis, in world space this is just new_pivot[axis]
+                let coord = Vec3::dot(&new_pivot, &line_dir);
+                let snapped_coord = utils::snap_to_increment(coord, snap_settings.grid_size);
+                new_pivot = new_pivot + (snapped_coord - coord) * line_dir;
+            }
 
-            let mut new_e_loc = entities.get_entity_location(self.entity);
-            new_e_loc.t = new_world_pos;
+            let delta = new_pivot - self.start_pivot;
 
-            entities.set_location(gc, self.entity, new_e_loc);
+            for (e, start_loc) in &self.start_locations {
+                if !entities.entity_valid(*e) {
+                    continue;
+                }
+
+                let mut new_e_loc = *start_loc;
+                new_e_loc.t = start_loc.t + delta;
+                entities.set_location(gc, *e, new_e_loc);
+            }
         }
 
         return EEditMode::TranslationDragging(self.clone());
@@ -453,12 +1391,13 @@ impl SEditModeTranslationDragging {
 }
 
 impl SEditModeRotationDragging {
-    pub fn new(entity: SEntityHandle, axis: usize, start_ori: Quat, start_entity_to_cursor: Vec3) -> Self {
+    pub fn new(axis: usize, orientation: Quat, start_pivot: Vec3, start_pivot_to_cursor: Vec3, start_locations: Vec<(SEntityHandle, STransform)>) -> Self {
         Self{
-            entity,
             axis,
-            start_ori,
-            start_entity_to_cursor,
+            orientation,
+            start_pivot,
+            start_pivot_to_cursor,
+            start_locations,
         }
     }
 
@@ -466,6 +1405,7 @@ impl SEditModeRotationDragging {
         &self,
         input: &input::SInput,
         editmode_input: &SEditModeInput,
+        snap_settings: &SEditModeSnapSettings,
         gc: &super::SGameContext,
         render: &mut render::SRender,
         entities: &mut SEntityBucket,
@@ -474,38 +1414,50 @@ impl SEditModeRotationDragging {
             return EEditMode::Rotation;
         }
         else {
-            let e_loc = entities.get_entity_location(self.entity);
-
             let mut plane_normal : Vec3 = Vec3::zero();
             plane_normal[self.axis] = 1.0;
-            let plane = utils::SPlane::new(&e_loc.t, &plane_normal);
+            let plane_normal = Quat::rotate_vec3(&self.orientation, &plane_normal);
+            let plane = utils::SPlane::new(&self.start_pivot, &plane_normal);
 
             let cursor_ray_world = cursor_ray_world(&editmode_input);
             if let Some((cursor_pos_world, _)) = utils::ray_plane_intersection(&cursor_ray_world, &plane) {
-                let entity_to_cursor = cursor_pos_world - e_loc.t;
+                let pivot_to_cursor = cursor_pos_world - self.start_pivot;
+
+                // -- both vectors lie in the rotation plane, so the signed angle between them
+                // -- around the plane normal fully describes the rotation
+                let mut angle = Vec3::dot(&Vec3::cross(&self.start_pivot_to_cursor, &pivot_to_cursor), &plane_normal)
+                    .atan2(Vec3::dot(&self.start_pivot_to_cursor, &pivot_to_cursor));
+                if snap_settings.rotation_active(input) {
+                    angle = utils::snap_to_increment(angle, snap_settings.rotation_increment_degrees.to_radians());
+                }
 
-                let rotation = Quat::new_from_orig_to_dest(&self.start_entity_to_cursor,
-                                                           &entity_to_cursor);
+                let rotation = Quat::new_angle_axis(angle, &plane_normal);
 
-                let new_entity_ori = rotation * self.start_ori;
+                // -- every entity orbits the shared pivot as well as rotating in place
+                for (e, start_loc) in &self.start_locations {
+                    if !entities.entity_valid(*e) {
+                        continue;
+                    }
 
-                let mut new_e_loc = e_loc;
-                new_e_loc.r = new_entity_ori;
+                    let mut new_e_loc = *start_loc;
+                    new_e_loc.t = self.start_pivot + Quat::rotate_vec3(&rotation, &(start_loc.t - self.start_pivot));
+                    new_e_loc.r = rotation * start_loc.r;
 
-                entities.set_location(gc, self.entity, new_e_loc);
+                    entities.set_location(gc, *e, new_e_loc);
+                }
 
                 let mut render_color : Vec4 = Vec4::zero();
                 render_color[self.axis] = 1.0;
                 render_color.w = 1.0;
                 render.temp().draw_line(
-                    &e_loc.t,
-                    &(e_loc.t + self.start_entity_to_cursor),
+                    &self.start_pivot,
+                    &(self.start_pivot + self.start_pivot_to_cursor),
                     &render_color,
                     true,
                     None,
                 );
                 render.temp().draw_line(
-                    &e_loc.t,
+                    &self.start_pivot,
                     &cursor_pos_world,
                     &render_color,
                     true,
@@ -525,6 +1477,13 @@ pub fn scale_to_fixed_screen_size(
     pct_of_near_plane_for_one_unit: f32,
     editmode_input: &SEditModeInput,
 ) {
+    // -- no perspective foreshortening, one unit is a fixed fraction of the view width
+    if let Some(ortho_height) = editmode_input.ortho_height {
+        let aspect = (editmode_input.window_width as f32) / (editmode_input.window_height as f32);
+        transform.s = aspect * ortho_height * pct_of_near_plane_for_one_unit;
+        return;
+    }
+
     let fovx = utils::fovx(editmode_input.fovy, editmode_input.window_width, editmode_input.window_height);
 
     let to_fixed = transform.t - editmode_input.camera_pos_world;
@@ -549,13 +1508,162 @@ pub fn scale_to_fixed_screen_size(
     transform.s = scale;
 }
 
+// -- closest hit along ray on a model instance that is pickable, skipping the entities in ignore
+pub fn pick_entity(
+    data_bucket: &databucket::SDataBucket,
+    ray: &utils::SRay,
+    ignore: &[SEntityHandle],
+) -> Option<scene_query::SSceneRaycastHit> {
+    let settings = scene_query::SRaycastSettings {
+        pickable_only: true,
+        ..scene_query::SRaycastSettings::default()
+    };
+    scene_query::raycast(data_bucket, ray, &settings, &SQueryFilter::new_ignoring(ignore))
+}
+
+pub fn entities_aabb(
+    selection: &[SEntityHandle],
+    entities: &SEntityBucket,
+    em: &entity_model::SBucket,
+    render: &render::SRender,
+) -> Option<utils::SAABB> {
+    let mut result : Option<utils::SAABB> = None;
+
+    for e in selection {
+        let location = entities.get_entity_location(*e);
+        let entity_aabb = match em.handle_for_entity(*e) {
+            Some(model_handle) => {
+                let mesh = render.mesh_instance_loader().get_mesh(em.get_model(model_handle));
+                utils::SAABB::transform(render.mesh_loader().get_mesh_local_aabb(mesh), &location)
+            },
+            None => utils::SAABB::new(&location.t),
+        };
+
+        result = Some(match result {
+            Some(aabb) => utils::SAABB::union(&aabb, &entity_aabb),
+            None => entity_aabb,
+        });
+    }
+
+    result
+}
+
+pub fn find_spawn_location(
+    data_bucket: &databucket::SDataBucket,
+    editmode_input: &SEditModeInput,
+    align_to_normal: bool,
+) -> Option<STransform> {
+    let cursor_ray = cursor_ray_world(editmode_input);
+    let mut result = STransform::default();
+
+    // -- spawns can land on anything, pickable or not
+    let settings = scene_query::SRaycastSettings::default();
+    if let Some(hit) = scene_query::raycast(data_bucket, &cursor_ray, &settings, &SQueryFilter::new()) {
+        result.t = hit.position;
+
+        if align_to_normal {
+            result.r = Quat::new_from_orig_to_dest(&Vec3::new(0.0, 1.0, 0.0), &hit.normal);
+        }
+
+        return Some(result);
+    }
+
+    // -- nothing under the cursor, fall back to the ground plane
+    let ground_plane = utils::SPlane::new(&Vec3::zero(), &Vec3::new(0.0, 1.0, 0.0));
+    utils::ray_plane_intersection(&cursor_ray, &ground_plane).map(|(pos, _)| {
+        result.t = pos;
+        result
+    })
+}
+
+pub fn find_snap_target_pos(
+    snap_target: ESnapTarget,
+    ignore: &[SEntityHandle],
+    editmode_input: &SEditModeInput,
+    data_bucket: &databucket::SDataBucket,
+) -> Option<Vec3> {
+    match snap_target {
+        ESnapTarget::Surface => {
+            let cursor_ray = cursor_ray_world(editmode_input);
+            pick_entity(data_bucket, &cursor_ray, ignore).map(|hit| hit.position)
+        },
+        ESnapTarget::Vertex => {
+            let mut result = None;
+            let mouse_pos = Vec3::new(editmode_input.mouse_window_pos[0] as f32, editmode_input.mouse_window_pos[1] as f32, 0.0);
+            let mut best_sq_dist = SEditModeSnapSettings::VERTEX_SNAP_MAX_PIXELS * SEditModeSnapSettings::VERTEX_SNAP_MAX_PIXELS;
+
+            data_bucket.get::<entity_model::SBucket>()
+                .and::<SEntityBucket>()
+                .and::<render::SRender>()
+                .with_ccc(|em, entities, render| {
+                    for model_handle in 0..em.owners.len() {
+                        let entity = em.get_entity(model_handle);
+                        if ignore.contains(&entity) {
+                            continue;
+                        }
+
+                        let loc = entities.get_entity_location(entity);
+                        let mesh = render.mesh_instance_loader().get_mesh(em.get_model(model_handle));
+
+                        for v in render.mesh_loader().get_mesh_local_vertices(mesh).as_slice() {
+                            let world_v = loc.mul_point(v);
+                            if !editmode_input.is_in_front_of_camera(&world_v) {
+                                continue;
+                            }
+
+                            let screen_v = world_pos_to_screen_pos(&world_v, editmode_input);
+                            let sq_dist = (screen_v - mouse_pos).sqmag();
+                            if sq_dist < best_sq_dist {
+                                best_sq_dist = sq_dist;
+                                result = Some(world_v);
+                            }
+                        }
+                    }
+                });
+
+            result
+        },
+    }
+}
+
 pub fn cursor_ray_world(
     editmode_input: &SEditModeInput,
 ) -> utils::SRay {
-    let (x_pos, y_pos) = (editmode_input.mouse_window_pos[0], editmode_input.mouse_window_pos[1]);
+    screen_pos_ray_world(editmode_input.mouse_window_pos, editmode_input)
+}
+
+// -- the resulting ray's origin + dir lies on the near plane
+pub fn screen_pos_ray_world(
+    screen_pos: [i32; 2],
+    editmode_input: &SEditModeInput,
+) -> utils::SRay {
+    let (x_pos, y_pos) = (screen_pos[0], screen_pos[1]);
 
     //println!("Left button down: {}, {}", x_pos, y_pos);
 
+    // -- ortho rays are all parallel to forward, starting on the near plane behind the camera
+    if let Some(ortho_height) = editmode_input.ortho_height {
+        let half_height = ortho_height / 2.0;
+        let half_width = ((editmode_input.window_width as f32) / (editmode_input.window_height as f32)) * half_height;
+
+        let pct_width = (x_pos as f32) / (editmode_input.window_width as f32);
+        let pct_height = (y_pos as f32) / (editmode_input.window_height as f32);
+
+        let origin_camera_space = Vec3::new(
+            -half_width + pct_width * 2.0 * half_width,
+            half_height - pct_height * 2.0 * half_height,
+            -camera::SDebugFPCamera::ORTHO_DEPTH,
+        );
+
+        let view_to_world = editmode_input.world_to_view_matrix.inverse();
+        let origin_world = view_to_world * utils::vec3_to_homogenous(&origin_camera_space, 1.0);
+
+        return utils::SRay{
+            origin: origin_world.xyz(),
+            dir: editmode_input.camera_forward,
+        };
+    }
+
     let half_camera_near_clip_height = (editmode_input.fovy/2.0).tan() * editmode_input.znear;
     let half_camera_near_clip_width = ((editmode_input.window_width as f32) / (editmode_input.window_height as f32)) * half_camera_near_clip_height;
 
@@ -583,16 +1691,36 @@ pub fn cursor_ray_world(
     }
 }
 
+pub fn draw_screen_rect(
+    render: &mut render::SRender,
+    corner_a: [i32; 2],
+    corner_b: [i32; 2],
+    color: &Vec4,
+    editmode_input: &SEditModeInput,
+) {
+    // -- lines are drawn just in front of the near plane so they cover everything
+    let near_plane_pos = |screen_pos: [i32; 2]| -> Vec3 {
+        let ray = screen_pos_ray_world(screen_pos, editmode_input);
+        ray.origin + 1.01 * ray.dir
+    };
+
+    let corners = [
+        near_plane_pos(corner_a),
+        near_plane_pos([corner_b[0], corner_a[1]]),
+        near_plane_pos(corner_b),
+        near_plane_pos([corner_a[0], corner_b[1]]),
+    ];
+
+    for i in 0..4 {
+        render.temp().draw_line(&corners[i], &corners[(i + 1) % 4], color, true, None);
+    }
+}
+
 pub fn world_pos_to_screen_pos(
     world_pos: &Vec3,
     editmode_input: &SEditModeInput,
 ) -> Vec3 {
-    let perspective_matrix = {
-        let aspect = (editmode_input.window_width as f32) / (editmode_input.window_height as f32);
-        let zfar = 100.0;
-
-        Mat4::new_perspective(aspect, editmode_input.fovy, editmode_input.znear, zfar)
-    };
+    let perspective_matrix = editmode_input.projection_matrix;
 
     let view_perspective_matrix = perspective_matrix * editmode_input.world_to_view_matrix;
 
@@ -611,12 +1739,22 @@ pub fn world_pos_to_screen_pos(
     screen_space
 }
 
+// -- an axis pointing along the view direction collapses to a point on screen, so there's no line to drag along
+const MIN_AXIS_SCREEN_LENGTH: f32 = 1.0;
+
+pub fn axis_degenerate_on_screen(pivot: &Vec3, axis_dir: &Vec3, editmode_input: &SEditModeInput) -> bool {
+    let p0_screen = world_pos_to_screen_pos(&(*pivot - *axis_dir), editmode_input);
+    let p1_screen = world_pos_to_screen_pos(&(*pivot + *axis_dir), editmode_input);
+    (p1_screen - p0_screen).mag() < MIN_AXIS_SCREEN_LENGTH
+}
+
+// -- None if the line is degenerate on screen
 pub fn pos_on_screen_space_line_to_world(
     world_line_p0: &Vec3,
     world_line_p1: &Vec3,
     screen_space_pos: [i32; 2],
     editmode_input: &SEditModeInput,
-) -> Vec3 {
+) -> Option<Vec3> {
     // -- how to move with translation widget:
     // + create two very distant points from the widget in world space on the translation axis
     // + get those points in screen space
@@ -627,12 +1765,7 @@ pub fn pos_on_screen_space_line_to_world(
     //println!("Line p0 : {:?}", line_p0);
     //println!("Line p1 : {:?}", line_p1);
 
-    let perspective_matrix = {
-        let aspect = (editmode_input.window_width as f32) / (editmode_input.window_height as f32);
-        let zfar = 100.0;
-
-        Mat4::new_perspective(aspect, editmode_input.fovy, editmode_input.znear, zfar)
-    };
+    let perspective_matrix = editmode_input.projection_matrix;
 
     let view_perspective_matrix = perspective_matrix * editmode_input.world_to_view_matrix;
 
@@ -664,6 +1797,9 @@ pub fn pos_on_screen_space_line_to_world(
     );
 
     let line_screen_space = line_p1_screen_space - line_p0_screen_space;
+    if line_screen_space.mag() < MIN_AXIS_SCREEN_LENGTH {
+        return None;
+    }
 
     //println!("Line p0 screen space: {:?}", line_p0_screen_space);
     //println!("Line p1 screen space: {:?}", line_p1_screen_space);
@@ -715,22 +1851,22 @@ pub fn pos_on_screen_space_line_to_world(
     let row_1_dot_d = Vec4::dot(&row_1, &d_vec4);
     let row_3_dot_d = Vec4::dot(&row_3, &d_vec4);
 
-    let t = {
+    let (t_numer, t_denom) = {
         if line_screen_space.x.abs() > line_screen_space.y.abs() {
-            let t_numer = closest_pos_clip_ndc.x * row_3_dot_p0 - row_0_dot_p0;
-            let t_denom = row_0_dot_d - closest_pos_clip_ndc.x * row_3_dot_d;
-            t_numer / t_denom
+            (closest_pos_clip_ndc.x * row_3_dot_p0 - row_0_dot_p0, row_0_dot_d - closest_pos_clip_ndc.x * row_3_dot_d)
         }
         else {
-            let t_numer = closest_pos_clip_ndc.y * row_3_dot_p0 - row_1_dot_p0;
-            let t_denom = row_1_dot_d - closest_pos_clip_ndc.y * row_3_dot_d;
-            t_numer / t_denom
+            (closest_pos_clip_ndc.y * row_3_dot_p0 - row_1_dot_p0, row_1_dot_d - closest_pos_clip_ndc.y * row_3_dot_d)
         }
     };
+    if t_denom.abs() < 0.000001 {
+        return None;
+    }
+    let t = t_numer / t_denom;
 
     let closest_pos_world_space = world_line_p0 + t * d;
 
-    closest_pos_world_space
+    Some(closest_pos_world_space)
 }
 
 pub fn update_create_input_for_frame(game_context: &SGameContext, frame_context: &SFrameContext) -> SEditModeInput {
@@ -745,6 +1881,7 @@ pub fn update_create_input_for_frame(game_context: &SGameContext, frame_context:
                 input,
                 render,
                 frame_context.imgui_want_capture_mouse,
+                frame_context.imgui_want_capture_keyboard,
             )
         })
 }
@@ -768,7 +1905,8 @@ pub fn update_edit_mode_level_editor_ui(game_context: &SGameContext, frame_conte
     let ui = frame_context.imgui_ui.as_ref().expect("shouldn't have rendered ui yet");
 
     game_context.data_bucket.get::<game_mode::SGameMode>()
-        .with_mut(|game_mode| {
+        .and::<camera::SDebugFPCamera>()
+        .with_mm(|game_mode, camera| {
             Window::new(im_str!("Level edit"))
                 .size([200.0, 200.0], Condition::FirstUseEver)
                 .build(ui, || {
@@ -791,11 +1929,11 @@ pub fn update_edit_mode_level_editor_ui(game_context: &SGameContext, frame_conte
                                 println!("{:?}", game_mode.edit_mode_ctxt.imgui_level_name);
                                 let level_init = level::SInit::new();
                                 let level = level::SLevel::new(&SYSTEM_ALLOCATOR(), game_context, &level_init).unwrap();
-                                game_mode.edit_mode_ctxt.editing_level = Some(SEditingLevel {
-                                    file_path: String::from(format!("assets/{}.level", game_mode.edit_mode_ctxt.imgui_level_name.to_str())),
+                                game_mode.edit_mode_ctxt.editing_level = Some(SEditingLevel::new(
+                                    String::from(format!("assets/{}.level", game_mode.edit_mode_ctxt.imgui_level_name.to_str())),
                                     level_init,
                                     level,
-                                });
+                                ));
                                 ui.close_current_popup();
                             }
                         });
@@ -821,7 +1959,88 @@ pub fn update_edit_mode_level_editor_ui(game_context: &SGameContext, frame_conte
                             }
                         });
 
+                    game_mode.edit_mode_ctxt.snap_settings.imgui_menu(ui);
+
+                    if is_level && ui.collapsing_header(im_str!("Spawn")).build() {
+                        let ctxt = &mut game_mode.edit_mode_ctxt;
+                        for entity_type in EEntityType::SPAWNABLE.iter() {
+                            let selected = ctxt.spawn_type == Some(*entity_type);
+                            if Selectable::new(&im_str!("{}", entity_type.name())).selected(selected).build(ui) {
+                                ctxt.spawn_type = if selected { None } else { Some(*entity_type) };
+                            }
+                        }
+                        ui.checkbox(im_str!("Align to surface normal"), &mut ctxt.spawn_align_to_normal);
+                        ui.text(im_str!("Click to place, shift-click to keep placing"));
+                    }
+
+                    if is_level && ui.collapsing_header(im_str!("Camera bookmarks")).build() {
+                        let mut changed = false;
+                        let mut recall = None;
+                        let editing_level = game_mode.edit_mode_ctxt.editing_level.as_mut().expect("checked is_level");
+                        for slot in 0..10 {
+                            let key = (slot + 1) % 10;
+                            let id = ui.push_id(slot as i32);
+                            match &mut editing_level.camera_bookmarks.slots[slot] {
+                                Some(bookmark) => {
+                                    let mut name = ImString::with_capacity(64);
+                                    name.push_str(bookmark.name.as_str());
+                                    if ui.input_text(&im_str!("[{}]", key), &mut name).build() {
+                                        bookmark.name = String::from(name.to_str());
+                                        changed = true;
+                                    }
+                                    ui.same_line(0.0);
+                                    if ui.small_button(im_str!("Go")) {
+                                        recall = Some(bookmark.clone());
+                                    }
+                                    ui.same_line(0.0);
+                                    if ui.small_button(im_str!("Store")) {
+                                        let name = bookmark.name.clone();
+                                        *bookmark = camera.bookmark(name.as_str());
+                                        changed = true;
+                                    }
+                                    ui.same_line(0.0);
+                                    if ui.small_button(im_str!("Clear")) {
+                                        editing_level.camera_bookmarks.slots[slot] = None;
+                                        changed = true;
+                                    }
+                                },
+                                None => {
+                                    ui.text(im_str!("[{}] empty", key));
+                                    ui.same_line(0.0);
+                                    if ui.small_button(im_str!("Store")) {
+                                        editing_level.camera_bookmarks.slots[slot] = Some(camera.bookmark(format!("Bookmark {}", key).as_str()));
+                                        changed = true;
+                                    }
+                                },
+                            }
+                            id.pop(ui);
+                        }
+                        ui.text(im_str!("Number keys recall, ctrl+number stores"));
+
+                        if let Some(bookmark) = recall {
+                            camera.apply_bookmark(&bookmark);
+                        }
+                        if changed {
+                            if let Err(e) = editing_level.camera_bookmarks.save(editing_level.file_path.as_str()) {
+                                println!("ERROR: {}", e);
+                            }
+                        }
+                    }
+
+                    if is_level && game_mode.edit_mode_ctxt.clipboard_has_entities() {
+                        if ui.button(im_str!("Paste (ctrl+V)"), [0.0, 0.0]) {
+                            game_mode.edit_mode_ctxt.request_clipboard_op(EClipboardOp::Paste);
+                        }
+                    }
+
                     if is_level {
+                        if ui.button(im_str!("Save level"), [0.0, 0.0]) {
+                            let game_mode_ctxt = &mut game_mode.edit_mode_ctxt;
+                            if let Err(e) = game_mode_ctxt.editing_level.as_mut().expect("checked is_level").save(game_context) {
+                                println!("ERROR: failed to save level: {}", e);
+                            }
+                        }
+
                         if ui.button(im_str!("Close level"), [0.0, 0.0]) {
                             game_mode.edit_mode_ctxt.editing_level.as_mut().expect("checked is_level").level.destroy(game_context);
                             game_mode.edit_mode_ctxt.editing_level = None;
@@ -1,5 +1,6 @@
 use crate::allocate::{SYSTEM_ALLOCATOR};
 use crate::collections::{SStoragePool, SPoolHandle};
+use crate::collision::{SCollisionFilter};
 use crate::entitytypes::{EEntityType};
 use crate::math::{Vec3};
 use crate::utils::{STransform};
@@ -11,6 +12,11 @@ pub struct SEntity {
     pub type_: EEntityType,
     pub location: STransform,
     pub location_update_frame: u64,
+    // -- how far location moved during location_update_frame
+    pub frame_displacement: Vec3,
+    // -- hidden entities are skipped by rendering
+    pub hidden: bool,
+    pub collision: SCollisionFilter,
 }
 
 #[allow(dead_code)]
@@ -27,6 +33,9 @@ impl SEntity {
             type_,
             location: STransform::default(),
             location_update_frame: 0,
+            frame_displacement: Vec3::zero(),
+            hidden: false,
+            collision: SCollisionFilter::default(),
         }
     }
 }
@@ -60,6 +69,22 @@ impl SEntityBucket {
         &self.entities.get(entity).expect("invalid entity").debug_name
     }
 
+    pub fn set_entity_hidden(&mut self, entity: SEntityHandle, hidden: bool) {
+        self.entities.get_mut(entity).expect("invalid entity").hidden = hidden;
+    }
+
+    pub fn is_entity_hidden(&self, entity: SEntityHandle) -> bool {
+        self.entities.get(entity).expect("invalid entity").hidden
+    }
+
+    pub fn set_collision_filter(&mut self, entity: SEntityHandle, filter: SCollisionFilter) {
+        self.entities.get_mut(entity).expect("invalid entity").collision = filter;
+    }
+
+    pub fn get_collision_filter(&self, entity: SEntityHandle) -> SCollisionFilter {
+        self.entities.get(entity).expect("invalid entity").collision
+    }
+
     pub fn get_entity_type(&self, entity: SEntityHandle) -> EEntityType {
         self.entities.get(entity).expect("invalid entity").type_
     }
@@ -72,8 +97,14 @@ impl SEntityBucket {
         self.entities.get(entity).expect("invalid entity").location_update_frame
     }
 
+    pub fn get_frame_displacement(&self, entity: SEntityHandle) -> Vec3 {
+        self.entities.get(entity).expect("invalid entity").frame_displacement
+    }
+
     pub fn set_location(&mut self, gc: &super::SGameContext, entity: SEntityHandle, location: STransform) {
         let entity = self.entities.get_mut(entity).expect("invalid entity");
+        let moved = location.t - entity.location.t;
+        entity.frame_displacement = if entity.location_update_frame == gc.cur_frame { entity.frame_displacement + moved } else { moved };
         entity.location = location;
         entity.location_update_frame = gc.cur_frame;
     }
@@ -1,8 +1,10 @@
 use crate::allocate::{SAllocatorRef};
 use crate::collections::{SVec};
-use crate::entity::{SEntityHandle};
+use crate::entity::{SEntityHandle, SEntityBucket};
 use crate::bvh;
 use crate::model::SMeshInstanceHandle;
+use crate::render;
+use crate::utils::{SAABB};
 
 pub struct SBucket {
     pub owners: SVec<SEntityHandle>,
@@ -72,6 +74,10 @@ impl SBucket {
         self.models[handle]
     }
 
+    pub fn set_model(&mut self, handle: SHandle, model: SMeshInstanceHandle) {
+        self.models[handle] = model;
+    }
+
     pub fn set_bvh_entry(&mut self, handle: SHandle, entry: bvh::SNodeHandle) {
         self.bvh_entries[handle] = Some(entry);
     }
@@ -79,4 +85,17 @@ impl SBucket {
     pub fn get_bvh_entry(&self, handle: SHandle) -> Option<bvh::SNodeHandle> {
         self.bvh_entries[handle]
     }
+
+    // -- the file the model was loaded from
+    pub fn asset_path<'a>(&self, handle: SHandle, render: &'a render::SRender) -> &'a str {
+        let mesh = render.mesh_instance_loader().get_mesh(self.get_model(handle));
+        render.mesh_loader().get_mesh_asset_path(mesh)
+    }
+
+    // -- the model's local bounds placed wherever its entity is
+    pub fn world_aabb(&self, handle: SHandle, entities: &SEntityBucket, render: &render::SRender) -> SAABB {
+        let mesh = render.mesh_instance_loader().get_mesh(self.get_model(handle));
+        let local_aabb = render.mesh_loader().get_mesh_local_aabb(mesh);
+        SAABB::transform(&local_aabb, &entities.get_entity_location(self.get_entity(handle)))
+    }
 }
\ No newline at end of file
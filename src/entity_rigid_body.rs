@@ -0,0 +1,249 @@
+use serde::{Serialize, Deserialize};
+
+use crate::allocate::{SAllocatorRef};
+use crate::collections::{SVec};
+use crate::entity::{SEntityBucket, SEntityHandle};
+use crate::entity_model;
+use crate::game_context::{SGameContext, SFrameContext};
+use crate::inspect;
+use crate::math::{Vec3};
+use crate::physics;
+use crate::render;
+use crate::shapes::{SSphere, SBox, SCapsule};
+use crate::utils::{STransform};
+
+// -- shape in the entity's unscaled local space, the entity's scale is baked in when the body is created
+#[derive(Copy, Clone, Serialize, Deserialize)]
+pub enum ERigidBodyShape {
+    MeshHull,
+    Sphere{ radius: f32 },
+    Box{ half_extents: Vec3 },
+    Capsule{ half_height: f32, radius: f32 },
+    // -- collides with the model's actual triangles, static bodies only
+    TriangleMesh,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SRigidBodyInit {
+    pub dynamic: bool,
+    pub shape: ERigidBodyShape,
+    pub mass: f32,
+    pub friction: f32,
+    pub restitution: f32,
+}
+
+pub struct SEntityRigidBody {
+    pub owner: SEntityHandle,
+    pub body: physics::SBodyHandle,
+    pub init: SRigidBodyInit,
+
+    // -- the last frame we moved the entity, any other location change is a teleport
+    last_written_frame: u64,
+}
+
+pub struct SBucket {
+    pub instances: SVec<SEntityRigidBody>,
+    pub world: physics::SPhysicsWorld,
+}
+pub type SHandle = usize;
+
+impl Default for SRigidBodyInit {
+    fn default() -> Self {
+        Self {
+            dynamic: true,
+            shape: ERigidBodyShape::MeshHull,
+            mass: 1.0,
+            friction: 0.5,
+            restitution: 0.0,
+        }
+    }
+}
+
+impl SBucket {
+    pub fn new(allocator: &SAllocatorRef, max_entries: usize) -> Result<Self, &'static str> {
+        Ok(Self {
+            instances: SVec::new(allocator, max_entries, 0)?,
+            world: physics::SPhysicsWorld::new(max_entries as u16, 4 * max_entries)?,
+        })
+    }
+
+    pub fn add_instance(
+        &mut self,
+        gc: &SGameContext,
+        entity: SEntityHandle,
+        init: &SRigidBodyInit,
+        entities: &SEntityBucket,
+        model: Option<(&entity_model::SBucket, &render::SRender)>,
+    ) -> Result<SHandle, &'static str> {
+        let location = entities.get_entity_location(entity);
+        let s = location.s;
+
+        let shape = match init.shape {
+            ERigidBodyShape::MeshHull => {
+                let (em, render) = model.ok_or("mesh hull bodies need the entity's model")?;
+                let model_handle = em.handle_for_entity(entity).ok_or("entity has no model for its mesh hull")?;
+                let mesh = render.mesh_instance_loader().get_mesh(em.get_model(model_handle));
+                let hull = render.mesh_loader().get_mesh_local_hull(mesh);
+                physics::ECollisionShape::Hull(hull.verts.iter().map(|v| s * v).collect())
+            },
+            ERigidBodyShape::TriangleMesh => {
+                let (em, render) = model.ok_or("triangle mesh bodies need the entity's model")?;
+                let model_handle = em.handle_for_entity(entity).ok_or("entity has no model for its triangle mesh")?;
+                let mesh = render.mesh_instance_loader().get_mesh(em.get_model(model_handle));
+                let triangle_mesh = render.mesh_loader().get_mesh_triangle_mesh(mesh)
+                    .ok_or("mesh has no triangle mesh, it was over the triangle mesh budget")?;
+                physics::ECollisionShape::TriangleMesh{ mesh: triangle_mesh.clone(), scale: s }
+            },
+            ERigidBodyShape::Sphere{ radius } => physics::ECollisionShape::Sphere(SSphere{ radius: s * radius }),
+            ERigidBodyShape::Box{ half_extents } => physics::ECollisionShape::Box(SBox{ half_extents: s * half_extents }),
+            ERigidBodyShape::Capsule{ half_height, radius } => physics::ECollisionShape::Capsule(SCapsule{
+                half_height: s * half_height,
+                radius: s * radius,
+            }),
+        };
+
+        let desc = physics::SRigidBodyDesc {
+            body_type: if init.dynamic { physics::EBodyType::Dynamic } else { physics::EBodyType::Static },
+            shape,
+            mass: init.mass,
+            friction: init.friction,
+            restitution: init.restitution,
+            filter: entities.get_collision_filter(entity),
+        };
+        let body = self.world.add_body(&desc, &location)?;
+
+        self.instances.push(SEntityRigidBody{
+            owner: entity,
+            body,
+            init: init.clone(),
+            last_written_frame: gc.cur_frame,
+        });
+        Ok(self.instances.len() - 1)
+    }
+
+    pub fn purge_entities(&mut self, entities: &[SEntityHandle]) {
+        let mut i = 0;
+        while i < self.instances.len() {
+            if entities.contains(&self.instances[i].owner) {
+                self.world.remove_body(self.instances[i].body);
+                self.instances.swap_remove(i);
+            }
+            else {
+                i = i + 1;
+            }
+        }
+    }
+
+    pub fn handle_for_entity(&self, entity: SEntityHandle) -> Option<SHandle> {
+        for i in 0..self.instances.len() {
+            if self.instances[i].owner == entity {
+                return Some(i);
+            }
+        }
+
+        None
+    }
+
+    pub fn init_for_entity(&self, entity: SEntityHandle) -> Option<SRigidBodyInit> {
+        self.handle_for_entity(entity).map(|handle| self.instances[handle].init.clone())
+    }
+}
+
+pub fn update_rigid_bodies(gc: &SGameContext, frame_context: &SFrameContext) {
+    gc.data_bucket.get::<SBucket>()
+        .and::<SEntityBucket>()
+        .with_mm(|e_rigid_body, entities| {
+            // -- anything else that moved an entity since we last did teleports its body
+            for instance in e_rigid_body.instances.as_slice() {
+                if entities.get_location_update_frame(instance.owner) != instance.last_written_frame {
+                    let location = entities.get_entity_location(instance.owner);
+                    e_rigid_body.world.set_body_location(instance.body, &location);
+                }
+            }
+
+            e_rigid_body.world.update(frame_context.dt_s);
+
+            let world = &e_rigid_body.world;
+            for instance in e_rigid_body.instances.as_mut_slice() {
+                let body = world.body(instance.body);
+                if body.is_dynamic() && !body.is_asleep() {
+                    let scale = entities.get_entity_location(instance.owner).s;
+                    entities.set_location(gc, instance.owner, STransform::new(&body.position(), &body.rotation(), scale));
+                }
+                instance.last_written_frame = entities.get_location_update_frame(instance.owner);
+            }
+        });
+}
+
+impl inspect::TInspectEnum for ERigidBodyShape {
+    fn variants() -> &'static [&'static str] {
+        &["MeshHull", "Sphere", "Box", "Capsule", "TriangleMesh"]
+    }
+
+    fn to_index(&self) -> usize {
+        match self {
+            Self::MeshHull => 0,
+            Self::Sphere{..} => 1,
+            Self::Box{..} => 2,
+            Self::Capsule{..} => 3,
+            Self::TriangleMesh => 4,
+        }
+    }
+
+    fn from_index(index: usize) -> Self {
+        match index {
+            0 => Self::MeshHull,
+            1 => Self::Sphere{ radius: 0.5 },
+            2 => Self::Box{ half_extents: Vec3::new(0.5, 0.5, 0.5) },
+            3 => Self::Capsule{ half_height: 0.5, radius: 0.5 },
+            4 => Self::TriangleMesh,
+            _ => panic!("invalid rigid body shape index"),
+        }
+    }
+}
+
+impl inspect::TInspect for SRigidBodyInit {
+    fn inspect(&mut self, visitor: &mut dyn inspect::TInspectVisitor) -> bool {
+        let mut body_type = if self.dynamic { 1 } else { 0 };
+        let mut changed = visitor.visit_enum("Body type", &mut body_type, &["Static", "Dynamic"]);
+        self.dynamic = body_type == 1;
+
+        changed |= inspect::inspect_enum(visitor, "Shape", &mut self.shape);
+        changed |= match &mut self.shape {
+            ERigidBodyShape::MeshHull => false,
+            ERigidBodyShape::TriangleMesh => {
+                // -- only static bodies can use their triangles
+                let was_dynamic = self.dynamic;
+                self.dynamic = false;
+                was_dynamic
+            },
+            ERigidBodyShape::Sphere{ radius } => visitor.visit_f32("Radius", radius),
+            ERigidBodyShape::Box{ half_extents } => visitor.visit_vec3("Half extents", half_extents),
+            ERigidBodyShape::Capsule{ half_height, radius } => {
+                visitor.visit_f32("Half height", half_height) | visitor.visit_f32("Radius", radius)
+            },
+        };
+
+        if self.dynamic {
+            changed |= visitor.visit_f32("Mass", &mut self.mass);
+        }
+        changed |= visitor.visit_f32("Friction", &mut self.friction);
+        changed |= visitor.visit_f32("Restitution", &mut self.restitution);
+        changed
+    }
+}
+
+// -- rigid body is optional on entities, so edit it through an Option
+pub fn inspect_optional_rigid_body(visitor: &mut dyn inspect::TInspectVisitor, name: &str, value: &mut Option<SRigidBodyInit>) -> bool {
+    let mut has_body = if value.is_some() { 1 } else { 0 };
+    let mut changed = false;
+    if visitor.visit_enum(name, &mut has_body, &["None", "Rigid body"]) {
+        *value = if has_body == 1 { Some(SRigidBodyInit::default()) } else { None };
+        changed = true;
+    }
+
+    if let Some(init) = value {
+        changed |= visitor.visit_struct(name, init);
+    }
+    changed
+}
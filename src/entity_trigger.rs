@@ -0,0 +1,238 @@
+use crate::allocate::{SAllocatorRef, STACK_ALLOCATOR};
+use crate::bvh;
+use crate::collections::{SVec};
+use crate::collision::{SQueryFilter};
+use crate::entity::{SEntityBucket, SEntityHandle};
+use crate::entity_model;
+use crate::game_context::{SGameContext, SFrameContext};
+use crate::game_mode;
+use crate::gjk;
+use crate::math::{Vec3, Vec4};
+use crate::render;
+use crate::scene_query;
+use crate::shapes::{SBox, STransformedShape};
+use crate::utils::{SAABB, STransform};
+
+// -- volumes that report what's inside them instead of pushing it out. They never make contacts,
+// -- they just turn the overlaps found each update into events for gameplay to read.
+// -- Triggers are boxes aligned to the world axes, the entity's rotation is ignored
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ETriggerEventKind {
+    Enter,
+    Stay,
+    Exit,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct STriggerEvent {
+    pub trigger: SEntityHandle,
+    pub other: SEntityHandle,
+    pub kind: ETriggerEventKind,
+}
+
+pub struct SEntityTrigger {
+    pub owner: SEntityHandle,
+    // -- in the entity's unscaled space
+    pub half_extents: Vec3,
+}
+
+pub struct SBucket {
+    pub instances: SVec<SEntityTrigger>,
+    tracker: bvh::SPairTracker<SEntityHandle>,
+    events: SVec<STriggerEvent>,
+    max_pairs: usize,
+}
+pub type SHandle = usize;
+
+const MAX_OVERLAPS_PER_TRIGGER : usize = 4;
+
+impl SEntityTrigger {
+    pub fn world_aabb(&self, location: &STransform) -> SAABB {
+        let half_extents = location.s * self.half_extents;
+        SAABB {
+            min: location.t - half_extents,
+            max: location.t + half_extents,
+        }
+    }
+}
+
+impl SBucket {
+    pub fn new(allocator: &SAllocatorRef, max_entries: usize) -> Result<Self, &'static str> {
+        let max_pairs = MAX_OVERLAPS_PER_TRIGGER * max_entries;
+        Ok(Self {
+            instances: SVec::new(allocator, max_entries, 0)?,
+            tracker: bvh::SPairTracker::new(allocator, max_pairs)?,
+            // -- every current pair can stay and every previous pair can exit in the same update
+            events: SVec::new(allocator, 2 * max_pairs, 0)?,
+            max_pairs,
+        })
+    }
+
+    pub fn add_instance(&mut self, entity: SEntityHandle, half_extents: Vec3) -> Result<SHandle, &'static str> {
+        self.instances.push(SEntityTrigger{
+            owner: entity,
+            half_extents,
+        });
+        Ok(self.instances.len() - 1)
+    }
+
+    pub fn purge_entities(&mut self, entities: &[SEntityHandle]) {
+        let mut i = 0;
+        while i < self.instances.len() {
+            if entities.contains(&self.instances[i].owner) {
+                self.instances.swap_remove(i);
+            }
+            else {
+                i = i + 1;
+            }
+        }
+    }
+
+    pub fn handle_for_entity(&self, entity: SEntityHandle) -> Option<SHandle> {
+        for i in 0..self.instances.len() {
+            if self.instances[i].owner == entity {
+                return Some(i);
+            }
+        }
+
+        None
+    }
+
+    pub fn half_extents_for_entity(&self, entity: SEntityHandle) -> Option<Vec3> {
+        self.handle_for_entity(entity).map(|handle| self.instances[handle].half_extents)
+    }
+
+    pub fn set_half_extents(&mut self, handle: SHandle, half_extents: Vec3) {
+        self.instances[handle].half_extents = half_extents;
+    }
+
+    // -- everything that happened in the last update
+    pub fn events(&self) -> &[STriggerEvent] {
+        self.events.as_slice()
+    }
+
+    fn update_events(&mut self, pairs: &[(SEntityHandle, SEntityHandle)]) -> Result<(), &'static str> {
+        self.events.clear();
+        self.tracker.set_pairs(pairs)?;
+
+        let began = self.tracker.began();
+        for (trigger, other) in began {
+            self.events.push(STriggerEvent{ trigger: *trigger, other: *other, kind: ETriggerEventKind::Enter });
+        }
+        for (trigger, other) in self.tracker.pairs() {
+            // -- began is a sorted subset of pairs
+            if began.binary_search_by(|p| p.partial_cmp(&(*trigger, *other)).unwrap_or(std::cmp::Ordering::Equal)).is_err() {
+                self.events.push(STriggerEvent{ trigger: *trigger, other: *other, kind: ETriggerEventKind::Stay });
+            }
+        }
+        for (trigger, other) in self.tracker.ended() {
+            self.events.push(STriggerEvent{ trigger: *trigger, other: *other, kind: ETriggerEventKind::Exit });
+        }
+
+        Ok(())
+    }
+}
+
+pub fn update_triggers(gc: &SGameContext, _frame_context: &SFrameContext) {
+    // -- nothing is inside a trigger while editing, so the next play starts with fresh enters
+    if !game_mode::is_playing(gc) {
+        gc.data_bucket.get::<SBucket>().with_mut(|triggers| {
+            // -- an empty pair list always fits
+            triggers.update_events(&[]).unwrap();
+            triggers.events.clear();
+        });
+        return;
+    }
+
+    let max_pairs = gc.data_bucket.get::<SBucket>().with(|triggers| triggers.max_pairs);
+
+    STACK_ALLOCATOR.with(|sa| {
+        let mut pairs = SVec::<(SEntityHandle, SEntityHandle)>::new(&sa.as_ref(), max_pairs, 0).unwrap();
+        let mut overlaps = SVec::<SEntityHandle>::new(&sa.as_ref(), 256, 0).unwrap();
+        let mut complete = true;
+
+        gc.data_bucket.get::<SBucket>()
+            .and::<SEntityBucket>()
+            .and::<entity_model::SBucket>()
+            .and::<render::SRender>()
+            .with_cccc(|triggers, entities, em, render| {
+                for trigger in triggers.instances.as_slice() {
+                    let location = entities.get_entity_location(trigger.owner);
+                    let aabb = trigger.world_aabb(&location);
+                    let trigger_shape = SBox{ half_extents: 0.5 * (aabb.max - aabb.min) };
+                    let trigger_shape = STransformedShape::new(trigger_shape, STransform::new_translation(&location.t));
+
+                    let filter = entities.get_collision_filter(trigger.owner);
+                    let ignore = [trigger.owner];
+
+                    overlaps.clear();
+                    if !scene_query::overlap_aabb(&gc.data_bucket, &aabb, &SQueryFilter::new_ignoring(&ignore), &mut overlaps) {
+                        complete = false;
+                    }
+
+                    for other in overlaps.as_ref() {
+                        if !filter.collides_with(&entities.get_collision_filter(*other)) {
+                            continue;
+                        }
+
+                        let inside = match em.handle_for_entity(*other) {
+                            Some(model_handle) => {
+                                let mesh = render.mesh_instance_loader().get_mesh(em.get_model(model_handle));
+                                let hull = render.mesh_loader().get_mesh_local_hull(mesh);
+                                let other_shape = STransformedShape::new(hull, entities.get_entity_location(*other));
+                                match gjk::gjk_intersection(&trigger_shape, &other_shape, &gjk::SGJKSettings::default()) {
+                                    gjk::EGJKResult::Intersection(_) => true,
+                                    gjk::EGJKResult::NoIntersection => false,
+                                    // -- the bounds overlap and we can't prove otherwise, so it stays inside
+                                    // -- rather than reading as an exit
+                                    gjk::EGJKResult::DidNotConverge => true,
+                                }
+                            },
+                            None => true,
+                        };
+
+                        if inside {
+                            if pairs.remaining_capacity() == 0 {
+                                complete = false;
+                                break;
+                            }
+                            pairs.push((trigger.owner, *other));
+                        }
+                    }
+                }
+            });
+
+        gc.data_bucket.get::<SBucket>().with_mut(|triggers| {
+            // -- a missing overlap would read as an exit, so an incomplete update reports nothing
+            // -- and the tracker keeps the pairs it had
+            let result = if complete {
+                triggers.update_events(pairs.as_slice())
+            }
+            else {
+                triggers.events.clear();
+                Err("more trigger overlaps than there is room for")
+            };
+
+            if let Err(e) = result {
+                println!("ERROR: trigger events: {}", e);
+            }
+        });
+    });
+}
+
+pub fn update_draw_triggers(gc: &SGameContext, _frame_context: &SFrameContext) {
+    if game_mode::is_playing(gc) {
+        return;
+    }
+
+    gc.data_bucket.get::<render::SRender>()
+        .and::<SBucket>()
+        .and::<SEntityBucket>()
+        .with_mcc(|render, triggers, entities| {
+            for trigger in triggers.instances.as_slice() {
+                let location = entities.get_entity_location(trigger.owner);
+                render.temp().draw_aabb(&trigger.world_aabb(&location), &Vec4::new(0.2, 1.0, 0.4, 1.0), true);
+            }
+        });
+}
@@ -1,38 +1,55 @@
 use serde::{Serialize, Deserialize};
 
+use crate::collision::{SCollisionFilter};
 use crate::entity::*;
 use crate::entity_model;
+use crate::entity_rigid_body;
 use crate::entitytypes::{EEntityType};
 use crate::game_context::{SGameContext};
+use crate::inspect;
 use crate::math::{Vec4};
 use crate::render;
 use crate::utils::{STransform};
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct SInit {
-    debug_name: Option<String>,
+    pub(super) debug_name: Option<String>,
+    #[serde(default = "default_model_path")]
+    model_path: String,
     diffuse_colour: Option<Vec4>,
-    starting_location: STransform,
+    pub(super) starting_location: STransform,
+    #[serde(default)]
+    collision: SCollisionFilter,
+    #[serde(default)]
+    rigid_body: Option<entity_rigid_body::SRigidBodyInit>,
+}
+
+fn default_model_path() -> String {
+    String::from("assets/test_untextured_flat_colour_cube.gltf")
 }
 
 pub fn create_from_init(gc: &SGameContext, init: &SInit) -> Result<SEntityHandle, &'static str> {
-    create(gc, init.debug_name.as_deref(), init.diffuse_colour, init.starting_location)
+    create(gc, init.debug_name.as_deref(), init.model_path.as_str(), init.diffuse_colour, init.starting_location, init.collision, init.rigid_body.as_ref())
 }
 
 pub fn create(
     gc: &SGameContext,
     debug_name: Option<&str>,
+    model_path: &str,
     diffuse_colour: Option<Vec4>,
     starting_location: STransform,
+    collision: SCollisionFilter,
+    rigid_body: Option<&entity_rigid_body::SRigidBodyInit>,
 ) -> Result<SEntityHandle, &'static str> {
 
     gc.data_bucket.get::<SEntityBucket>()
         .and::<render::SRender>()
         .and::<entity_model::SBucket>()
-        .with_mmm(|entities, render, em| {
+        .and::<entity_rigid_body::SBucket>()
+        .with_mmmm(|entities, render, em, e_rigid_body| {
             let ent = entities.create_entity(EEntityType::FlatShadedCube)?;
 
-            let mut model = render.new_model_from_gltf("assets/test_untextured_flat_colour_cube.gltf", 1.0, true)?;
+            let mut model = render.new_model_from_gltf(model_path, 1.0, true)?;
             if let Some(c) = diffuse_colour {
                 model.diffuse_colour = c;
             }
@@ -41,18 +58,77 @@ pub fn create(
                 entities.set_entity_debug_name(ent, n);
             }
 
+            entities.set_collision_filter(ent, collision);
+
             em.add_instance(ent, model)?;
             entities.set_location(gc, ent, starting_location);
 
+            if let Some(rb) = rigid_body {
+                e_rigid_body.add_instance(gc, ent, rb, entities, Some((em, render)))?;
+            }
+
             Ok(ent)
         })
 }
 
 impl SInit {
+    pub fn new(debug_name: Option<String>, starting_location: STransform) -> Self {
+        Self{
+            debug_name,
+            model_path: default_model_path(),
+            diffuse_colour: None,
+            starting_location,
+            collision: SCollisionFilter::default(),
+            rigid_body: None,
+        }
+    }
+
+    pub fn apply_to_entity(&self, gc: &SGameContext, entity: SEntityHandle) {
+        gc.data_bucket.get::<SEntityBucket>()
+            .and::<render::SRender>()
+            .and::<entity_model::SBucket>()
+            .with_mmm(|entities, render, em| {
+                if let Some(n) = &self.debug_name {
+                    entities.set_entity_debug_name(entity, n.as_str());
+                }
+
+                let m_handle = em.handle_for_entity(entity).expect("somehow model wasn't created");
+                if self.model_path != em.asset_path(m_handle, render) {
+                    if super::model_path_loadable(self.model_path.as_str(), "gltf") {
+                        match render.new_mesh_instance_from_gltf(self.model_path.as_str(), 1.0, true) {
+                            Ok(model) => em.set_model(m_handle, model),
+                            Err(e) => println!("ERROR: failed to reload model {}: {}", self.model_path, e),
+                        }
+                    }
+                }
+
+                if let Some(c) = &self.diffuse_colour {
+                    render.mesh_instance_loader_mut().set_diffuse_colour(em.get_model(m_handle), c);
+                }
+
+                entities.set_location(gc, entity, self.starting_location);
+                entities.set_collision_filter(entity, self.collision);
+            });
+
+        // -- the body's shape and mass are baked at creation, so rebuild it
+        gc.data_bucket.get::<entity_rigid_body::SBucket>()
+            .and::<SEntityBucket>()
+            .and::<entity_model::SBucket>()
+            .and::<render::SRender>()
+            .with_mccc(|e_rigid_body, entities, em, render| {
+                e_rigid_body.purge_entities(&[entity]);
+                if let Some(rb) = &self.rigid_body {
+                    e_rigid_body.add_instance(gc, entity, rb, entities, Some((em, render))).expect("failed to create rigid body");
+                }
+            });
+    }
+
     pub fn new_from_entity(gc: &SGameContext, entity: SEntityHandle) -> Self {
         gc.data_bucket.get::<SEntityBucket>()
             .and::<entity_model::SBucket>()
-            .with_cc(|entities, em| {
+            .and::<entity_rigid_body::SBucket>()
+            .and::<render::SRender>()
+            .with_cccc(|entities, em, e_rigid_body, render| {
                 assert_eq!(entities.get_entity_type(entity), EEntityType::FlatShadedCube);
 
                 let debug_name = entities.get_entity_debug_name(entity).map(|n| {
@@ -60,14 +136,32 @@ impl SInit {
                     String::from(name_raw_str)
                 });
                 let m_handle = em.handle_for_entity(entity).expect("somehow model wasn't created");
+                let model_path = String::from(em.asset_path(m_handle, render));
                 let diffuse_colour = Some(em.get_model(m_handle).diffuse_colour);
                 let starting_location = entities.get_entity_location(entity);
+                let collision = entities.get_collision_filter(entity);
+                let rigid_body = e_rigid_body.init_for_entity(entity);
 
                 Self{
                     debug_name,
+                    model_path,
                     diffuse_colour,
                     starting_location,
+                    collision,
+                    rigid_body,
                 }
             })
     }
-}
\ No newline at end of file
+}
+
+impl inspect::TInspect for SInit {
+    fn inspect(&mut self, visitor: &mut dyn inspect::TInspectVisitor) -> bool {
+        let mut changed = inspect::inspect_optional_string(visitor, "Debug name", &mut self.debug_name);
+        changed |= visitor.visit_asset_path("Model", &mut self.model_path, "gltf");
+        changed |= inspect::inspect_optional_colour(visitor, "Diffuse colour", &mut self.diffuse_colour, &Vec4::new(1.0, 1.0, 1.0, 1.0));
+        changed |= visitor.visit_struct("Starting location", &mut self.starting_location);
+        changed |= visitor.visit_struct("Collision", &mut self.collision);
+        changed |= entity_rigid_body::inspect_optional_rigid_body(visitor, "Rigid body", &mut self.rigid_body);
+        changed
+    }
+}
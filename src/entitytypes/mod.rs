@@ -2,18 +2,22 @@ use serde::{Serialize, Deserialize};
 
 use crate::entity::{SEntityBucket, SEntityHandle};
 use crate::game_context::{SGameContext};
+use crate::inspect;
+use crate::utils::{STransform};
 
 pub mod flatshadedcubeentity;
 pub mod testtexturedcubeentity;
 pub mod testopenroomentity;
 pub mod tstskinnedentity;
+pub mod triggervolumeentity;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub enum EEntityInit {
     FlatShadedCube(flatshadedcubeentity::SInit),
     TestOpenRoom(testopenroomentity::SInit),
     TestTexturedCube(testtexturedcubeentity::SInit),
     TestSkinnedEntity(tstskinnedentity::SInit),
+    TriggerVolume(triggervolumeentity::SInit),
 }
 
 #[derive(Clone, Copy, PartialEq, Debug)]
@@ -23,6 +27,34 @@ pub enum EEntityType {
     TestOpenRoom,
     TestTexturedCube,
     TestSkinnedEntity,
+    TriggerVolume,
+}
+
+impl EEntityType {
+    pub const SPAWNABLE: [EEntityType; 5] = [
+        EEntityType::FlatShadedCube,
+        EEntityType::TestOpenRoom,
+        EEntityType::TestTexturedCube,
+        EEntityType::TestSkinnedEntity,
+        EEntityType::TriggerVolume,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Invalid => "Invalid",
+            Self::FlatShadedCube => "FlatShadedCube",
+            Self::TestOpenRoom => "TestOpenRoom",
+            Self::TestTexturedCube => "TestTexturedCube",
+            Self::TestSkinnedEntity => "TestSkinnedEntity",
+            Self::TriggerVolume => "TriggerVolume",
+        }
+    }
+}
+
+// -- the inspector edits paths as free text, so only swap models for a file that is actually there
+fn model_path_loadable(path: &str, extension: &str) -> bool {
+    let path = std::path::Path::new(path);
+    path.is_file() && path.extension().map_or(false, |e| e == extension)
 }
 
 /*
@@ -35,6 +67,17 @@ trait TEntityType {
 */
 
 impl EEntityInit {
+    pub fn new_default(entity_type: EEntityType, debug_name: Option<String>, starting_location: STransform) -> EEntityInit {
+        match entity_type {
+            EEntityType::FlatShadedCube => EEntityInit::FlatShadedCube(flatshadedcubeentity::SInit::new(debug_name, starting_location)),
+            EEntityType::TestOpenRoom => EEntityInit::TestOpenRoom(testopenroomentity::SInit::new(debug_name, starting_location)),
+            EEntityType::TestTexturedCube => EEntityInit::TestTexturedCube(testtexturedcubeentity::SInit::new(debug_name, starting_location)),
+            EEntityType::TestSkinnedEntity => EEntityInit::TestSkinnedEntity(tstskinnedentity::SInit::new(debug_name, starting_location)),
+            EEntityType::TriggerVolume => EEntityInit::TriggerVolume(triggervolumeentity::SInit::new(debug_name, starting_location)),
+            EEntityType::Invalid => panic!("Trying to create init for invalid entity type"),
+        }
+    }
+
     pub fn new_from_entity(game_context: &SGameContext, entity: SEntityHandle) -> EEntityInit {
         let entity_type = game_context.data_bucket.get::<SEntityBucket>()
             .with(|entities| {
@@ -45,16 +88,89 @@ impl EEntityInit {
             EEntityType::TestOpenRoom => EEntityInit::TestOpenRoom(testopenroomentity::SInit::new_from_entity(game_context, entity)),
             EEntityType::TestTexturedCube => EEntityInit::TestTexturedCube(testtexturedcubeentity::SInit::new_from_entity(game_context, entity)),
             EEntityType::TestSkinnedEntity => EEntityInit::TestSkinnedEntity(tstskinnedentity::SInit::new_from_entity(game_context, entity)),
+            EEntityType::TriggerVolume => EEntityInit::TriggerVolume(triggervolumeentity::SInit::new_from_entity(game_context, entity)),
             EEntityType::Invalid => panic!("Trying to create init for invalid entity"),
         }
     }
 
+    pub fn entity_type(&self) -> EEntityType {
+        match self {
+            Self::FlatShadedCube(_) => EEntityType::FlatShadedCube,
+            Self::TestOpenRoom(_) => EEntityType::TestOpenRoom,
+            Self::TestTexturedCube(_) => EEntityType::TestTexturedCube,
+            Self::TestSkinnedEntity(_) => EEntityType::TestSkinnedEntity,
+            Self::TriggerVolume(_) => EEntityType::TriggerVolume,
+        }
+    }
+
+    pub fn debug_name(&self) -> Option<&str> {
+        match self {
+            Self::FlatShadedCube(init) => init.debug_name.as_deref(),
+            Self::TestOpenRoom(init) => init.debug_name.as_deref(),
+            Self::TestTexturedCube(init) => init.debug_name.as_deref(),
+            Self::TestSkinnedEntity(init) => init.debug_name.as_deref(),
+            Self::TriggerVolume(init) => init.debug_name.as_deref(),
+        }
+    }
+
+    pub fn set_debug_name(&mut self, debug_name: Option<String>) {
+        match self {
+            Self::FlatShadedCube(init) => init.debug_name = debug_name,
+            Self::TestOpenRoom(init) => init.debug_name = debug_name,
+            Self::TestTexturedCube(init) => init.debug_name = debug_name,
+            Self::TestSkinnedEntity(init) => init.debug_name = debug_name,
+            Self::TriggerVolume(init) => init.debug_name = debug_name,
+        }
+    }
+
+    pub fn starting_location(&self) -> STransform {
+        match self {
+            Self::FlatShadedCube(init) => init.starting_location,
+            Self::TestOpenRoom(init) => init.starting_location,
+            Self::TestTexturedCube(init) => init.starting_location,
+            Self::TestSkinnedEntity(init) => init.starting_location,
+            Self::TriggerVolume(init) => init.starting_location,
+        }
+    }
+
+    pub fn set_starting_location(&mut self, starting_location: STransform) {
+        match self {
+            Self::FlatShadedCube(init) => init.starting_location = starting_location,
+            Self::TestOpenRoom(init) => init.starting_location = starting_location,
+            Self::TestTexturedCube(init) => init.starting_location = starting_location,
+            Self::TestSkinnedEntity(init) => init.starting_location = starting_location,
+            Self::TriggerVolume(init) => init.starting_location = starting_location,
+        }
+    }
+
+    pub fn inspectable(&mut self) -> &mut dyn inspect::TInspect {
+        match self {
+            Self::FlatShadedCube(init) => init,
+            Self::TestOpenRoom(init) => init,
+            Self::TestTexturedCube(init) => init,
+            Self::TestSkinnedEntity(init) => init,
+            Self::TriggerVolume(init) => init,
+        }
+    }
+
+    // -- push the init's values onto an existing entity of the same type
+    pub fn apply_to_entity(&self, game_context: &SGameContext, entity: SEntityHandle) {
+        match self {
+            Self::FlatShadedCube(init) => init.apply_to_entity(game_context, entity),
+            Self::TestOpenRoom(init) => init.apply_to_entity(game_context, entity),
+            Self::TestTexturedCube(init) => init.apply_to_entity(game_context, entity),
+            Self::TestSkinnedEntity(init) => init.apply_to_entity(game_context, entity),
+            Self::TriggerVolume(init) => init.apply_to_entity(game_context, entity),
+        }
+    }
+
     pub fn init(&self, game_context: &SGameContext) -> Result<SEntityHandle, &'static str> {
         match self {
             Self::FlatShadedCube(init) => flatshadedcubeentity::create_from_init(game_context, init),
             Self::TestOpenRoom(init) => testopenroomentity::create_from_init(game_context, init),
             Self::TestTexturedCube(init) => testtexturedcubeentity::create_from_init(game_context, init),
             Self::TestSkinnedEntity(init) => tstskinnedentity::create_from_init(game_context, init),
+            Self::TriggerVolume(init) => triggervolumeentity::create_from_init(game_context, init),
         }
     }
 }
\ No newline at end of file
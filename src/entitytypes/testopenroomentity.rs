@@ -1,26 +1,38 @@
 use serde::{Serialize, Deserialize};
 
+use crate::collision::{SCollisionFilter};
 use crate::entity::*;
 use crate::entity_model;
 use crate::entitytypes::{EEntityType};
 use crate::game_context::{SGameContext};
+use crate::inspect;
 use crate::render;
 use crate::utils::{STransform};
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct SInit {
-    debug_name: Option<String>,
-    starting_location: STransform,
+    pub(super) debug_name: Option<String>,
+    #[serde(default = "default_model_path")]
+    model_path: String,
+    pub(super) starting_location: STransform,
+    #[serde(default)]
+    collision: SCollisionFilter,
+}
+
+fn default_model_path() -> String {
+    String::from("assets/test_open_room.obj")
 }
 
 pub fn create_from_init(gc: &SGameContext, init: &SInit) -> Result<SEntityHandle, &'static str> {
-    create(gc, init.debug_name.as_deref(), init.starting_location)
+    create(gc, init.debug_name.as_deref(), init.model_path.as_str(), init.starting_location, init.collision)
 }
 
 pub fn create(
     game_context: &SGameContext,
     debug_name: Option<&str>,
+    model_path: &str,
     starting_location: STransform,
+    collision: SCollisionFilter,
 ) -> Result<SEntityHandle, &'static str> {
 
     game_context.data_bucket.get::<SEntityBucket>()
@@ -29,12 +41,14 @@ pub fn create(
         .with_mmm(|entities, render, em| {
             let ent = entities.create_entity(EEntityType::TestOpenRoom)?;
 
-            let model = render.new_model_from_obj("assets/test_open_room.obj", 1.0, true)?;
+            let model = render.new_model_from_obj(model_path, 1.0, true)?;
 
             if let Some(n) = debug_name {
                 entities.set_entity_debug_name(ent, n);
             }
 
+            entities.set_collision_filter(ent, collision);
+
             em.add_instance(ent, model)?;
             entities.set_location(game_context, ent, starting_location);
 
@@ -43,21 +57,71 @@ pub fn create(
 }
 
 impl SInit {
+    pub fn new(debug_name: Option<String>, starting_location: STransform) -> Self {
+        Self{
+            debug_name,
+            model_path: default_model_path(),
+            starting_location,
+            collision: SCollisionFilter::default(),
+        }
+    }
+
+    pub fn apply_to_entity(&self, gc: &SGameContext, entity: SEntityHandle) {
+        gc.data_bucket.get::<SEntityBucket>()
+            .and::<render::SRender>()
+            .and::<entity_model::SBucket>()
+            .with_mmm(|entities, render, em| {
+                if let Some(n) = &self.debug_name {
+                    entities.set_entity_debug_name(entity, n.as_str());
+                }
+
+                let m_handle = em.handle_for_entity(entity).expect("somehow model wasn't created");
+                if self.model_path != em.asset_path(m_handle, render) {
+                    if super::model_path_loadable(self.model_path.as_str(), "obj") {
+                        match render.new_mesh_instance_from_obj(self.model_path.as_str(), 1.0, true) {
+                            Ok(model) => em.set_model(m_handle, model),
+                            Err(e) => println!("ERROR: failed to reload model {}: {}", self.model_path, e),
+                        }
+                    }
+                }
+
+                entities.set_location(gc, entity, self.starting_location);
+                entities.set_collision_filter(entity, self.collision);
+            });
+    }
+
     pub fn new_from_entity(gc: &SGameContext, entity: SEntityHandle) -> Self {
         gc.data_bucket.get::<SEntityBucket>()
-            .with(|entities| {
+            .and::<entity_model::SBucket>()
+            .and::<render::SRender>()
+            .with_ccc(|entities, em, render| {
                 assert_eq!(entities.get_entity_type(entity), EEntityType::TestOpenRoom);
 
                 let debug_name = entities.get_entity_debug_name(entity).map(|n| {
                     let name_raw_str = unsafe{ n._debug_ptr.as_ref().unwrap() };
                     String::from(name_raw_str)
                 });
+                let m_handle = em.handle_for_entity(entity).expect("somehow model wasn't created");
+                let model_path = String::from(em.asset_path(m_handle, render));
                 let starting_location = entities.get_entity_location(entity);
+                let collision = entities.get_collision_filter(entity);
 
                 Self{
                     debug_name,
+                    model_path,
                     starting_location,
+                    collision,
                 }
             })
     }
 }
+
+impl inspect::TInspect for SInit {
+    fn inspect(&mut self, visitor: &mut dyn inspect::TInspectVisitor) -> bool {
+        let mut changed = inspect::inspect_optional_string(visitor, "Debug name", &mut self.debug_name);
+        changed |= visitor.visit_asset_path("Model", &mut self.model_path, "obj");
+        changed |= visitor.visit_struct("Starting location", &mut self.starting_location);
+        changed |= visitor.visit_struct("Collision", &mut self.collision);
+        changed
+    }
+}
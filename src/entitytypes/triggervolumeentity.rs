@@ -0,0 +1,113 @@
+use serde::{Serialize, Deserialize};
+
+use crate::collision::{SCollisionFilter, ECollisionLayer, COLLISION_MASK_ALL};
+use crate::entity::*;
+use crate::entity_trigger;
+use crate::entitytypes::{EEntityType};
+use crate::game_context::{SGameContext};
+use crate::inspect;
+use crate::math::{Vec3};
+use crate::utils::{STransform};
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SInit {
+    pub(super) debug_name: Option<String>,
+    pub(super) starting_location: STransform,
+    half_extents: Vec3,
+    #[serde(default = "default_collision")]
+    collision: SCollisionFilter,
+}
+
+fn default_collision() -> SCollisionFilter {
+    SCollisionFilter::new(ECollisionLayer::Trigger, COLLISION_MASK_ALL)
+}
+
+pub fn create_from_init(gc: &SGameContext, init: &SInit) -> Result<SEntityHandle, &'static str> {
+    create(gc, init.debug_name.as_deref(), init.starting_location, init.half_extents, init.collision)
+}
+
+pub fn create(
+    gc: &SGameContext,
+    debug_name: Option<&str>,
+    starting_location: STransform,
+    half_extents: Vec3,
+    collision: SCollisionFilter,
+) -> Result<SEntityHandle, &'static str> {
+
+    gc.data_bucket.get::<SEntityBucket>()
+        .and::<entity_trigger::SBucket>()
+        .with_mm(|entities, e_trigger| {
+            let ent = entities.create_entity(EEntityType::TriggerVolume)?;
+
+            if let Some(n) = debug_name {
+                entities.set_entity_debug_name(ent, n);
+            }
+
+            entities.set_collision_filter(ent, collision);
+
+            e_trigger.add_instance(ent, half_extents)?;
+            entities.set_location(gc, ent, starting_location);
+
+            Ok(ent)
+        })
+}
+
+impl SInit {
+    pub fn new(debug_name: Option<String>, starting_location: STransform) -> Self {
+        Self{
+            debug_name,
+            starting_location,
+            half_extents: Vec3::new(1.0, 1.0, 1.0),
+            collision: default_collision(),
+        }
+    }
+
+    pub fn apply_to_entity(&self, gc: &SGameContext, entity: SEntityHandle) {
+        gc.data_bucket.get::<SEntityBucket>()
+            .and::<entity_trigger::SBucket>()
+            .with_mm(|entities, e_trigger| {
+                if let Some(n) = &self.debug_name {
+                    entities.set_entity_debug_name(entity, n.as_str());
+                }
+
+                let t_handle = e_trigger.handle_for_entity(entity).expect("somehow trigger wasn't created");
+                e_trigger.set_half_extents(t_handle, self.half_extents);
+
+                entities.set_location(gc, entity, self.starting_location);
+                entities.set_collision_filter(entity, self.collision);
+            });
+    }
+
+    pub fn new_from_entity(gc: &SGameContext, entity: SEntityHandle) -> Self {
+        gc.data_bucket.get::<SEntityBucket>()
+            .and::<entity_trigger::SBucket>()
+            .with_cc(|entities, e_trigger| {
+                assert_eq!(entities.get_entity_type(entity), EEntityType::TriggerVolume);
+
+                let debug_name = entities.get_entity_debug_name(entity).map(|n| {
+                    let name_raw_str = unsafe{ n._debug_ptr.as_ref().unwrap() };
+                    String::from(name_raw_str)
+                });
+                let starting_location = entities.get_entity_location(entity);
+                let half_extents = e_trigger.half_extents_for_entity(entity).expect("somehow trigger wasn't created");
+                let collision = entities.get_collision_filter(entity);
+
+                Self{
+                    debug_name,
+                    starting_location,
+                    half_extents,
+                    collision,
+                }
+            })
+    }
+}
+
+impl inspect::TInspect for SInit {
+    fn inspect(&mut self, visitor: &mut dyn inspect::TInspectVisitor) -> bool {
+        let mut changed = inspect::inspect_optional_string(visitor, "Debug name", &mut self.debug_name);
+        changed |= visitor.visit_struct("Starting location", &mut self.starting_location);
+        changed |= visitor.visit_vec3("Half extents", &mut self.half_extents);
+        changed |= visitor.visit_struct("Collision", &mut self.collision);
+        changed
+    }
+}
@@ -1,31 +1,45 @@
 use serde::{Serialize, Deserialize};
 
 use crate::animation;
+use crate::collision::{SCollisionFilter};
 use crate::entity::*;
 use crate::entity_model;
 use crate::entity_animation;
 use crate::entitytypes::{EEntityType};
 use crate::game_context::{SGameContext};
+use crate::inspect;
 use crate::math::{Vec4};
 use crate::render;
 use crate::utils::{STransform};
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct SInit {
-    debug_name: Option<String>,
+    pub(super) debug_name: Option<String>,
+    #[serde(default = "default_model_path")]
+    model_path: String,
     diffuse_colour: Option<Vec4>,
-    starting_location: STransform,
+    pub(super) starting_location: STransform,
+    #[serde(default)]
+    collision: SCollisionFilter,
+}
+
+const ANIMATION_PATH: &str = "assets/test_armature_animation.gltf";
+
+fn default_model_path() -> String {
+    String::from("assets/test_armature.gltf")
 }
 
 pub fn create_from_init(gc: &SGameContext, init: &SInit) -> Result<SEntityHandle, &'static str> {
-    create(gc, init.debug_name.as_deref(), init.diffuse_colour, init.starting_location)
+    create(gc, init.debug_name.as_deref(), init.model_path.as_str(), init.diffuse_colour, init.starting_location, init.collision)
 }
 
 pub fn create(
     game_context: &SGameContext,
     debug_name: Option<&str>,
+    model_path: &str,
     diffuse_colour: Option<Vec4>,
     starting_location: STransform,
+    collision: SCollisionFilter,
 ) -> Result<SEntityHandle, &'static str> {
 
     game_context.data_bucket.get::<SEntityBucket>()
@@ -36,7 +50,7 @@ pub fn create(
         .with_mmmmm(|entities, render, e_model, e_animation, anim_loader| {
             let ent = entities.create_entity(EEntityType::TestSkinnedEntity)?;
 
-            let mut model = render.new_model_from_gltf("assets/test_armature.gltf", 1.0, true)?;
+            let mut model = render.new_model_from_gltf(model_path, 1.0, true)?;
             if let Some(c) = diffuse_colour {
                 model.diffuse_colour = c;
             }
@@ -45,25 +59,73 @@ pub fn create(
                 entities.set_entity_debug_name(ent, n);
             }
 
+            entities.set_collision_filter(ent, collision);
+
             let model_handle = e_model.add_instance(ent, model)?;
             let anim_handle = e_animation.add_instance(ent, (&e_model, model_handle), render.mesh_loader())?;
 
             entities.set_location(game_context, ent, starting_location);
 
-            {
-                let asset_file_path = "assets/test_armature_animation.gltf";
-                e_animation.play_animation(anim_handle, anim_loader, render.mesh_loader(), asset_file_path, 0.0);
-            }
+            e_animation.play_animation(anim_handle, anim_loader, render.mesh_loader(), ANIMATION_PATH, 0.0);
 
             Ok(ent)
         })
 }
 
 impl SInit {
+    pub fn new(debug_name: Option<String>, starting_location: STransform) -> Self {
+        Self{
+            debug_name,
+            model_path: default_model_path(),
+            diffuse_colour: None,
+            starting_location,
+            collision: SCollisionFilter::default(),
+        }
+    }
+
+    pub fn apply_to_entity(&self, gc: &SGameContext, entity: SEntityHandle) {
+        gc.data_bucket.get::<SEntityBucket>()
+            .and::<render::SRender>()
+            .and::<entity_model::SBucket>()
+            .and::<entity_animation::SBucket>()
+            .and::<animation::SAnimationLoader>()
+            .with_mmmmm(|entities, render, em, e_animation, anim_loader| {
+                if let Some(n) = &self.debug_name {
+                    entities.set_entity_debug_name(entity, n.as_str());
+                }
+
+                // -- the skinning is bound to the old mesh, so the animation instance is rebuilt along with the model
+                let m_handle = em.handle_for_entity(entity).expect("somehow model wasn't created");
+                if self.model_path != em.asset_path(m_handle, render) {
+                    if super::model_path_loadable(self.model_path.as_str(), "gltf") {
+                        match render.new_mesh_instance_from_gltf(self.model_path.as_str(), 1.0, true) {
+                            Ok(model) => {
+                                em.set_model(m_handle, model);
+                                e_animation.purge_entities(&[entity]);
+                                match e_animation.add_instance(entity, (&*em, m_handle), render.mesh_instance_loader_mut()) {
+                                    Ok(anim_handle) => e_animation.play_animation(anim_handle, anim_loader, render.mesh_loader(), ANIMATION_PATH, 0.0),
+                                    Err(e) => println!("ERROR: failed to rebind animation for {}: {}", self.model_path, e),
+                                }
+                            },
+                            Err(e) => println!("ERROR: failed to reload model {}: {}", self.model_path, e),
+                        }
+                    }
+                }
+
+                if let Some(c) = &self.diffuse_colour {
+                    render.mesh_instance_loader_mut().set_diffuse_colour(em.get_model(m_handle), c);
+                }
+
+                entities.set_location(gc, entity, self.starting_location);
+                entities.set_collision_filter(entity, self.collision);
+            });
+    }
+
     pub fn new_from_entity(gc: &SGameContext, entity: SEntityHandle) -> Self {
         gc.data_bucket.get::<SEntityBucket>()
             .and::<entity_model::SBucket>()
-            .with_cc(|entities, em| {
+            .and::<render::SRender>()
+            .with_ccc(|entities, em, render| {
                 assert_eq!(entities.get_entity_type(entity), EEntityType::TestSkinnedEntity);
 
                 let debug_name = entities.get_entity_debug_name(entity).map(|n| {
@@ -71,14 +133,29 @@ impl SInit {
                     String::from(name_raw_str)
                 });
                 let m_handle = em.handle_for_entity(entity).expect("somehow model wasn't created");
+                let model_path = String::from(em.asset_path(m_handle, render));
                 let diffuse_colour = Some(em.get_model(m_handle).diffuse_colour);
                 let starting_location = entities.get_entity_location(entity);
+                let collision = entities.get_collision_filter(entity);
 
                 Self{
                     debug_name,
+                    model_path,
                     diffuse_colour,
                     starting_location,
+                    collision,
                 }
             })
     }
 }
+
+impl inspect::TInspect for SInit {
+    fn inspect(&mut self, visitor: &mut dyn inspect::TInspectVisitor) -> bool {
+        let mut changed = inspect::inspect_optional_string(visitor, "Debug name", &mut self.debug_name);
+        changed |= visitor.visit_asset_path("Model", &mut self.model_path, "gltf");
+        changed |= inspect::inspect_optional_colour(visitor, "Diffuse colour", &mut self.diffuse_colour, &Vec4::new(1.0, 1.0, 1.0, 1.0));
+        changed |= visitor.visit_struct("Starting location", &mut self.starting_location);
+        changed |= visitor.visit_struct("Collision", &mut self.collision);
+        changed
+    }
+}
@@ -25,6 +25,7 @@ pub struct SFrameContext<'ui> {
     pub imgui_ui: Option<imgui::Ui<'ui>>, // -- goes away partway through the frame
     pub imgui_draw_data: Option<&'ui imgui::DrawData>, // -- created partway through the frame
     pub imgui_want_capture_mouse: bool,
+    pub imgui_want_capture_keyboard: bool,
 
     pub data_bucket: SDataBucket,
     pub linear_allocator: SAllocator,
@@ -55,6 +56,7 @@ impl SGameContext {
         let total_time_s = (total_time_micro_s as f32) / 1_000_000.0;
 
         let imgui_want_capture_mouse = imgui_ctxt.io().want_capture_mouse;
+        let imgui_want_capture_keyboard = imgui_ctxt.io().want_capture_keyboard;
 
         SFrameContext {
             start_time_micro_s,
@@ -68,6 +70,7 @@ impl SGameContext {
             imgui_ui: Some(imgui_ctxt.frame()),
             imgui_draw_data: None,
             imgui_want_capture_mouse,
+            imgui_want_capture_keyboard,
 
             data_bucket: SDataBucket::new(32, &allocator.as_ref()),
             linear_allocator: allocator,
@@ -1,6 +1,11 @@
-use crate::editmode::{EEditMode, SEditModeContext};
-use crate::game_context::{SGameContext};
+use crate::animation::{SAnimationLoader};
+use crate::camera;
+use crate::character_controller::{SCharacterController};
+use crate::editmode::{EEditMode, SEditModeContext, SPlaySnapshot};
+use crate::entity_animation;
+use crate::game_context::{SGameContext, SFrameContext};
 use crate::input;
+use crate::math::{Vec3};
 use crate::render;
 
 #[derive(PartialEq)]
@@ -30,7 +35,12 @@ pub struct SGameMode {
     pub edit_mode_ctxt: SEditModeContext,
 
     pub draw_selected_bvh: bool,
+    pub draw_colliding_entities: bool,
     pub show_imgui_demo_window: bool,
+
+    play_snapshot: Option<SPlaySnapshot>,
+    pub keep_play_changes_for_selection: bool,
+    pub toggle_mode_requested: bool,
 }
 
 impl SGameMode {
@@ -40,17 +50,76 @@ impl SGameMode {
             edit_mode: EEditMode::None,
             edit_mode_ctxt: SEditModeContext::new(render).unwrap(),
             draw_selected_bvh: false,
+            draw_colliding_entities: false,
             show_imgui_demo_window: false,
+            play_snapshot: None,
+            keep_play_changes_for_selection: false,
+            toggle_mode_requested: false,
+        }
+    }
+
+    pub fn toggle_mode(&mut self, game_context: &SGameContext, frame_context: &SFrameContext) {
+        match self.mode {
+            EMode::Edit => {
+                self.play_snapshot = self.edit_mode_ctxt.editing_level()
+                    .map(|editing_level| editing_level.snapshot_for_play(game_context, frame_context.total_time_s));
+
+                // -- start playing from wherever the editor camera is looking from
+                game_context.data_bucket.get::<SCharacterController>()
+                    .and::<camera::SDebugFPCamera>()
+                    .with_mc(|controller, camera| {
+                        let feet = camera.pos_world - Vec3::new(0.0, controller.settings.eye_height, 0.0);
+                        controller.teleport(&feet);
+                    });
+            },
+            EMode::Play => {
+                if let Some(snapshot) = self.play_snapshot.take() {
+                    let keep_changes : Vec<_> = if self.keep_play_changes_for_selection {
+                        self.edit_mode_ctxt.selected_entities().to_vec()
+                    }
+                    else {
+                        Vec::new()
+                    };
+
+                    let restored = self.edit_mode_ctxt.editing_level_mut()
+                        .map(|editing_level| editing_level.restore_from_play(game_context, &snapshot, keep_changes.as_slice()));
+                    match restored {
+                        Some(Ok(recreated)) => {
+                            for (old, new) in recreated {
+                                self.edit_mode_ctxt.remap_selection(old, new);
+                            }
+                        },
+                        Some(Err(e)) => println!("ERROR: failed to restore level from play: {}", e),
+                        None => {},
+                    }
+
+                    // -- animation only advances in play, so put poses back where they were
+                    game_context.data_bucket.get::<entity_animation::SBucket>()
+                        .and::<SAnimationLoader>()
+                        .with_mc(|e_animation, anim_loader| {
+                            e_animation.update_joints(anim_loader, snapshot.time_s);
+                        });
+                }
+            },
         }
+
+        self.mode.toggle(&mut self.edit_mode);
     }
 }
 
-pub fn update_toggle_mode(game_context: &SGameContext) {
+pub fn is_playing(game_context: &SGameContext) -> bool {
+    game_context.data_bucket.get::<SGameMode>().with(|game_mode: &SGameMode| {
+        game_mode.mode == EMode::Play
+    })
+}
+
+pub fn update_toggle_mode(game_context: &SGameContext, frame_context: &SFrameContext) {
     game_context.data_bucket.get::<SGameMode>()
         .and::<input::SInput>()
         .with_mc(|game_mode, input| {
-            if input.tilde_edge.down() {
-                game_mode.mode.toggle(&mut game_mode.edit_mode);
+            if input.tilde_edge.down() || game_mode.toggle_mode_requested {
+                game_mode.toggle_mode_requested = false;
+                game_mode.toggle_mode(game_context, frame_context);
             }
         });
 }
\ No newline at end of file
@@ -5,6 +5,7 @@ use crate::entity::{SEntityHandle};
 use crate::databucket::{SDataBucket};
 use crate::render;
 use crate::render::{SRender};
+use crate::shapes::{TConvexShape, SMinkowskiSum};
 use ::imgui;
 
 // Implementation of GJK
@@ -101,6 +102,9 @@ pub struct SGJKDebug {
     steps: Vec<EGJKDebugStep>,
     pts_a: Vec<Vec3>,
     pts_b: Vec<Vec3>,
+    settings: SGJKSettings,
+    // -- computed once the stepping finds no intersection
+    distance: Option<EGJKDistanceResult>,
     temp_render_token: render::temp::SToken,
 }
 
@@ -371,30 +375,717 @@ pub fn step_gjk(pts_a: &[Vec3], pts_b: &[Vec3], mut simplex: ESimplex, dir: &Vec
     simplex.update_simplex()
 }
 
-#[allow(dead_code)]
-pub fn gjk(pts_a: &[Vec3], pts_b: &[Vec3]) -> bool {
-    let s = minkowski_support_mapping(pts_a, pts_b, &Vec3::new(1.0, 1.0, 1.0));
-    let mut simplex = ESimplex::One(S1Simplex{ a: s.pos, });
-    let mut dir = -s.pos;
-
-    let max_iters = 64;
-    for _ in 0..max_iters {
-        match step_gjk(pts_a, pts_b, simplex.clone(), &dir) {
+#[derive(Clone, Copy, Debug)]
+pub struct SGJKSettings {
+    pub max_iterations: usize,
+    // -- stop once the distance estimate is within this of the true distance
+    pub tolerance: f32,
+}
+
+impl Default for SGJKSettings {
+    fn default() -> Self {
+        Self {
+            max_iterations: 64,
+            tolerance: 0.0001,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SGJKDistance {
+    pub distance: f32,
+    pub closest_a: Vec3,
+    pub closest_b: Vec3,
+    // -- unit direction from closest_a to closest_b
+    pub witness_dir: Vec3,
+    pub iterations: usize,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum EGJKDistanceResult {
+    Separated(SGJKDistance),
+    Intersecting,
+    // -- ran out of iterations, holds the best estimate so far
+    DidNotConverge(SGJKDistance),
+}
+
+// -- vertex of the minkowski difference, remembering which points of A and B made it
+#[derive(Clone, Copy, Default)]
+struct SSupportPoint {
+    pos: Vec3,
+    a: Vec3,
+    b: Vec3,
+}
+
+// -- unlike ESimplex, this simplex tracks the closest point rather than just a search direction
+#[derive(Clone, Copy, Default)]
+struct SDistanceSimplex {
+    pts: [SSupportPoint; 4],
+    weights: [f32; 4],
+    len: usize,
+}
+
+impl SDistanceSimplex {
+    fn push(&mut self, pt: SSupportPoint) {
+        assert!(self.len < 4);
+        self.pts[self.len] = pt;
+        self.len += 1;
+    }
+
+    fn contains(&self, pos: &Vec3) -> bool {
+        self.pts[0..self.len].iter().any(|p| p.pos == *pos)
+    }
+
+    fn closest_point(&self) -> Vec3 {
+        let mut result = Vec3::zero();
+        for i in 0..self.len {
+            result = result + self.weights[i] * self.pts[i].pos;
+        }
+        result
+    }
+
+    fn closest_points_ab(&self) -> (Vec3, Vec3) {
+        let mut a = Vec3::zero();
+        let mut b = Vec3::zero();
+        for i in 0..self.len {
+            a = a + self.weights[i] * self.pts[i].a;
+            b = b + self.weights[i] * self.pts[i].b;
+        }
+        (a, b)
+    }
+
+    // -- keep only the given points, with their barycentric weights
+    fn reduce(&mut self, keep: &[(usize, f32)]) {
+        let old = *self;
+        self.len = 0;
+        for (idx, weight) in keep {
+            if *weight > 0.0 {
+                self.pts[self.len] = old.pts[*idx];
+                self.weights[self.len] = *weight;
+                self.len += 1;
+            }
+        }
+    }
+
+    // -- reduce to the smallest sub-simplex containing the point closest to the origin,
+    // -- returns false if the origin is inside the tetrahedron
+    fn reduce_to_closest(&mut self) -> bool {
+        match self.len {
+            1 => {
+                self.weights[0] = 1.0;
+            },
+            2 => {
+                let weights = closest_on_segment_to_origin(&self.pts[0].pos, &self.pts[1].pos);
+                self.reduce(&[(0, weights[0]), (1, weights[1])]);
+            },
+            3 => {
+                let weights = closest_on_triangle_to_origin(&self.pts[0].pos, &self.pts[1].pos, &self.pts[2].pos);
+                self.reduce(&[(0, weights[0]), (1, weights[1]), (2, weights[2])]);
+            },
+            4 => {
+                const FACES : [[usize; 4]; 4] = [
+                    [0, 1, 2, 3],
+                    [0, 1, 3, 2],
+                    [0, 2, 3, 1],
+                    [1, 2, 3, 0],
+                ];
+
+                // -- a flat tetrahedron has no inside, and on a nearly flat one the side tests below are
+                // -- just rounding error, so every face is tested
+                let p = &self.pts;
+                let ab = p[1].pos - p[0].pos;
+                let ac = p[2].pos - p[0].pos;
+                let ad = p[3].pos - p[0].pos;
+                let volume = Vec3::dot(&ab, &Vec3::cross(&ac, &ad));
+                let flat = volume.abs() <= 0.0001 * ab.mag() * ac.mag() * ad.mag();
+
+                let mut best : Option<(f32, [(usize, f32); 3])> = None;
+                for face in &FACES {
+                    let a = &self.pts[face[0]].pos;
+                    let b = &self.pts[face[1]].pos;
+                    let c = &self.pts[face[2]].pos;
+                    let opposite = &self.pts[face[3]].pos;
+
+                    // -- only faces with the origin on their outer side can hold the closest point
+                    let n = Vec3::cross(&(b - a), &(c - a));
+                    if !flat && Vec3::dot(&-a, &n) * Vec3::dot(&(opposite - a), &n) > 0.0 {
+                        continue;
+                    }
+
+                    let weights = closest_on_triangle_to_origin(a, b, c);
+                    let closest = weights[0] * a + weights[1] * b + weights[2] * c;
+                    let sq_dist = closest.sqmag();
+                    if best.map_or(true, |(best_sq_dist, _)| sq_dist < best_sq_dist) {
+                        best = Some((sq_dist, [(face[0], weights[0]), (face[1], weights[1]), (face[2], weights[2])]));
+                    }
+                }
+
+                match best {
+                    Some((_, keep)) => self.reduce(&keep),
+                    None => return false,
+                }
+            },
+            _ => unreachable!(),
+        }
+
+        true
+    }
+}
+
+// -- barycentric weights of the point on segment ab closest to the origin
+fn closest_on_segment_to_origin(a: &Vec3, b: &Vec3) -> [f32; 2] {
+    let ab = b - a;
+    let denom = Vec3::dot(&ab, &ab);
+    if denom <= 0.0 {
+        return [1.0, 0.0];
+    }
+
+    let t = crate::utils::clamp(Vec3::dot(&-a, &ab) / denom, 0.0, 1.0);
+    [1.0 - t, t]
+}
+
+// -- barycentric weights of the point on triangle abc closest to the origin
+// -- from Real-Time Collision Detection 5.1.5, with p at the origin
+fn closest_on_triangle_to_origin(a: &Vec3, b: &Vec3, c: &Vec3) -> [f32; 3] {
+    let ab = b - a;
+    let ac = c - a;
+
+    let ap = -a;
+    let d1 = Vec3::dot(&ab, &ap);
+    let d2 = Vec3::dot(&ac, &ap);
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return [1.0, 0.0, 0.0];
+    }
+
+    let bp = -b;
+    let d3 = Vec3::dot(&ab, &bp);
+    let d4 = Vec3::dot(&ac, &bp);
+    if d3 >= 0.0 && d4 <= d3 {
+        return [0.0, 1.0, 0.0];
+    }
+
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        let v = d1 / (d1 - d3);
+        return [1.0 - v, v, 0.0];
+    }
+
+    let cp = -c;
+    let d5 = Vec3::dot(&ab, &cp);
+    let d6 = Vec3::dot(&ac, &cp);
+    if d6 >= 0.0 && d5 <= d6 {
+        return [0.0, 0.0, 1.0];
+    }
+
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        let w = d2 / (d2 - d6);
+        return [1.0 - w, 0.0, w];
+    }
+
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && (d4 - d3) >= 0.0 && (d5 - d6) >= 0.0 {
+        let w = (d4 - d3) / ((d4 - d3) + (d5 - d6));
+        return [0.0, 1.0 - w, w];
+    }
+
+    let denom = va + vb + vc;
+    if denom.abs() <= std::f32::EPSILON {
+        // -- degenerate triangle, fall back to the closest edge
+        let ab_weights = closest_on_segment_to_origin(a, b);
+        let ac_weights = closest_on_segment_to_origin(a, c);
+        let ab_closest = ab_weights[0] * a + ab_weights[1] * b;
+        let ac_closest = ac_weights[0] * a + ac_weights[1] * c;
+        if ab_closest.sqmag() <= ac_closest.sqmag() {
+            return [ab_weights[0], ab_weights[1], 0.0];
+        }
+        return [ac_weights[0], 0.0, ac_weights[1]];
+    }
+
+    let v = vb / denom;
+    let w = vc / denom;
+    [1.0 - v - w, v, w]
+}
+
+fn support_point<A: TConvexShape + ?Sized, B: TConvexShape + ?Sized>(shape_a: &A, shape_b: &B, dir: &Vec3) -> SSupportPoint {
+    let a = shape_a.support(dir);
+    let b = shape_b.support(&-dir);
+    SSupportPoint {
+        pos: a - b,
+        a,
+        b,
+    }
+}
+
+fn distance_from_simplex(simplex: &SDistanceSimplex, iterations: usize) -> SGJKDistance {
+    let (closest_a, closest_b) = simplex.closest_points_ab();
+    let a_to_b = closest_b - closest_a;
+    let distance = a_to_b.mag();
+
+    SGJKDistance {
+        distance,
+        closest_a,
+        closest_b,
+        witness_dir: if distance > 0.0 { (1.0 / distance) * a_to_b } else { Vec3::zero() },
+        iterations,
+    }
+}
+
+// -- distance between two convex shapes, plus the closest point on each
+pub fn gjk_distance<A: TConvexShape + ?Sized, B: TConvexShape + ?Sized>(shape_a: &A, shape_b: &B, settings: &SGJKSettings) -> EGJKDistanceResult {
+    let mut simplex = SDistanceSimplex::default();
+    simplex.push(support_point(shape_a, shape_b, &Vec3::new(1.0, 1.0, 1.0)));
+    simplex.weights[0] = 1.0;
+
+    let mut v = simplex.closest_point();
+
+    for iteration in 0..settings.max_iterations {
+        let v_sqmag = v.sqmag();
+        if v_sqmag <= settings.tolerance * settings.tolerance {
+            return EGJKDistanceResult::Intersecting;
+        }
+
+        let w = support_point(shape_a, shape_b, &-v);
+
+        // -- |v| is an upper bound on the distance and dot(v, w) / |v| a lower bound,
+        // -- stop when they are within tolerance, or when w adds nothing new
+        if v_sqmag - Vec3::dot(&v, &w.pos) <= settings.tolerance * v_sqmag.sqrt() || simplex.contains(&w.pos) {
+            return EGJKDistanceResult::Separated(distance_from_simplex(&simplex, iteration));
+        }
+
+        simplex.push(w);
+        if !simplex.reduce_to_closest() {
+            return EGJKDistanceResult::Intersecting;
+        }
+
+        let new_v = simplex.closest_point();
+
+        // -- numerical issues can stop the estimate from shrinking, the previous answer is as good as it gets
+        if new_v.sqmag() >= v_sqmag {
+            return EGJKDistanceResult::Separated(distance_from_simplex(&simplex, iteration + 1));
+        }
+
+        v = new_v;
+    }
+
+    EGJKDistanceResult::DidNotConverge(distance_from_simplex(&simplex, settings.max_iterations))
+}
+
+// -- the ESimplex::Four that GJK terminated with, plus the points of A and B that made each vertex
+pub struct SGJKTerminatingSimplex {
+    simplex: ESimplex,
+    witnesses: [SSupportPoint; 4],
+}
+
+pub enum EGJKResult {
+    NoIntersection,
+    Intersection(SGJKTerminatingSimplex),
+    DidNotConverge,
+}
+
+impl SGJKTerminatingSimplex {
+    fn new(simplex: ESimplex, support_history: &[SSupportPoint]) -> Self {
+        let positions = match &simplex {
+            ESimplex::Four(s4) => [s4.a, s4.b, s4.c, s4.d],
+            _ => unreachable!("GJK only terminates with an intersection on a tetrahedron"),
+        };
+
+        let mut witnesses = [SSupportPoint::default(); 4];
+        for (i, pos) in positions.iter().enumerate() {
+            witnesses[i] = *support_history.iter().find(|s| s.pos == *pos).expect("simplex vertex must come from a support call");
+        }
+
+        Self {
+            simplex,
+            witnesses,
+        }
+    }
+
+    pub fn simplex(&self) -> &ESimplex {
+        &self.simplex
+    }
+}
+
+pub fn gjk_intersection<A: TConvexShape + ?Sized, B: TConvexShape + ?Sized>(shape_a: &A, shape_b: &B, settings: &SGJKSettings) -> EGJKResult {
+    // -- remember every support point so the terminating simplex can recover its witnesses
+    let mut support_history = Vec::with_capacity(settings.max_iterations + 1);
+
+    let first = support_point(shape_a, shape_b, &Vec3::new(1.0, 1.0, 1.0));
+    support_history.push(first);
+    let mut simplex = ESimplex::One(S1Simplex{ a: first.pos, });
+    let mut dir = -first.pos;
+
+    for _ in 0..settings.max_iterations {
+        let s = support_point(shape_a, shape_b, &dir);
+        if Vec3::dot(&s.pos, &dir) < 0.0 {
+            return EGJKResult::NoIntersection;
+        }
+        support_history.push(s);
+
+        simplex.expand(&s.pos);
+        match simplex.update_simplex() {
             EGJKStepResult::Intersection => {
-                return true;
+                return EGJKResult::Intersection(SGJKTerminatingSimplex::new(simplex, support_history.as_slice()));
             },
             EGJKStepResult::NewSimplexAndDir(new_simplex, new_dir) => {
                 simplex = new_simplex;
                 dir = new_dir;
             },
             EGJKStepResult::NoIntersection => {
-                return false;
+                return EGJKResult::NoIntersection;
+            },
+        }
+    }
+
+    EGJKResult::DidNotConverge
+}
+
+// -- Expanding Polytope Algorithm
+// -- grows the terminating GJK tetrahedron towards the boundary of the minkowski difference until
+// -- the face closest to the origin is on the boundary, which gives the penetration depth and normal
+// -- structure follows Real-Time Collision Detection / van den Bergen
+
+#[derive(Clone, Copy, Debug)]
+pub struct SPenetration {
+    pub depth: f32,
+    // -- unit direction from A towards B, moving B by depth along it separates the shapes
+    pub normal: Vec3,
+    // -- deepest points of each shape, contact_a - contact_b = depth * normal
+    pub contact_a: Vec3,
+    pub contact_b: Vec3,
+    pub iterations: usize,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum EEPAResult {
+    Penetration(SPenetration),
+    // -- ran out of iterations, holds the best estimate so far
+    DidNotConverge(SPenetration),
+}
+
+#[derive(Clone, Copy)]
+struct SEPAFace {
+    verts: [usize; 3],
+    normal: Vec3,
+    distance: f32,
+}
+
+struct SEPAPolytope {
+    verts: Vec<SSupportPoint>,
+    faces: Vec<SEPAFace>,
+}
+
+const EPA_DEGENERATE_EPSILON : f32 = 0.000001;
+
+impl SEPAPolytope {
+    fn new_face(&self, i0: usize, i1: usize, i2: usize) -> Option<SEPAFace> {
+        let a = &self.verts[i0].pos;
+        let b = &self.verts[i1].pos;
+        let c = &self.verts[i2].pos;
+
+        let n = Vec3::cross(&(b - a), &(c - a));
+        let mag = n.mag();
+        if mag <= EPA_DEGENERATE_EPSILON {
+            return None;
+        }
+
+        let normal = (1.0 / mag) * n;
+        Some(SEPAFace {
+            verts: [i0, i1, i2],
+            normal,
+            distance: Vec3::dot(&normal, a),
+        })
+    }
+
+    // -- faces wound so their normals point away from the tetrahedron's centroid
+    fn new_tetrahedron(verts: [SSupportPoint; 4]) -> Option<Self> {
+        let mut result = Self {
+            verts: verts.to_vec(),
+            faces: Vec::with_capacity(4),
+        };
+
+        let centroid = 0.25 * (verts[0].pos + verts[1].pos + verts[2].pos + verts[3].pos);
+        for [i0, i1, i2] in &[[0, 1, 2], [0, 3, 1], [0, 2, 3], [1, 3, 2]] {
+            let mut face = result.new_face(*i0, *i1, *i2)?;
+            if Vec3::dot(&face.normal, &(result.verts[*i0].pos - centroid)) < 0.0 {
+                face = result.new_face(*i0, *i2, *i1)?;
+            }
+            result.faces.push(face);
+        }
+
+        Some(result)
+    }
+
+    fn contains_origin(&self, tolerance: f32) -> bool {
+        self.faces.iter().all(|f| f.distance >= -tolerance)
+    }
+
+    fn closest_face(&self) -> usize {
+        let mut best = 0;
+        for (i, face) in self.faces.iter().enumerate() {
+            if face.distance < self.faces[best].distance {
+                best = i;
+            }
+        }
+        best
+    }
+
+    fn penetration(&self, face: &SEPAFace, iterations: usize) -> SPenetration {
+        // -- the origin projected on the face is the closest boundary point, its barycentric
+        // -- coordinates interpolate the witness points on A and B
+        let a = &self.verts[face.verts[0]];
+        let b = &self.verts[face.verts[1]];
+        let c = &self.verts[face.verts[2]];
+        let weights = closest_on_triangle_to_origin(&a.pos, &b.pos, &c.pos);
+
+        SPenetration {
+            depth: face.distance.max(0.0),
+            normal: face.normal,
+            contact_a: weights[0] * a.a + weights[1] * b.a + weights[2] * c.a,
+            contact_b: weights[0] * a.b + weights[1] * b.b + weights[2] * c.b,
+            iterations,
+        }
+    }
+
+    fn add_vertex(&mut self, vert: SSupportPoint) {
+        let new_idx = self.verts.len();
+        self.verts.push(vert);
+
+        // -- remove every face that can see the new vertex, the boundary of the hole is the
+        // -- set of edges used by exactly one removed face
+        let mut horizon : Vec<(usize, usize)> = Vec::new();
+        let mut i = 0;
+        while i < self.faces.len() {
+            let face = self.faces[i];
+            if Vec3::dot(&face.normal, &(vert.pos - self.verts[face.verts[0]].pos)) > 0.0 {
+                for e in 0..3 {
+                    let edge = (face.verts[e], face.verts[(e + 1) % 3]);
+                    if let Some(reverse_idx) = horizon.iter().position(|h| *h == (edge.1, edge.0)) {
+                        horizon.swap_remove(reverse_idx);
+                    }
+                    else {
+                        horizon.push(edge);
+                    }
+                }
+                self.faces.swap_remove(i);
+            }
+            else {
+                i += 1;
+            }
+        }
+
+        // -- horizon edges keep the winding of the removed faces, so the new faces face outward
+        for (e0, e1) in horizon {
+            if let Some(face) = self.new_face(e0, e1, new_idx) {
+                self.faces.push(face);
+            }
+        }
+    }
+}
+
+fn penetration_on_plane(verts: &[SSupportPoint; 3], normal: &Vec3, iterations: usize) -> SPenetration {
+    let weights = closest_on_triangle_to_origin(&verts[0].pos, &verts[1].pos, &verts[2].pos);
+    SPenetration {
+        depth: 0.0,
+        normal: *normal,
+        contact_a: weights[0] * verts[0].a + weights[1] * verts[1].a + weights[2] * verts[2].a,
+        contact_b: weights[0] * verts[0].b + weights[1] * verts[1].b + weights[2] * verts[2].b,
+        iterations,
+    }
+}
+
+// -- a flat GJK tetrahedron has no volume to expand, so lift one of its triangles off the
+// -- plane with a support point along the plane normal
+fn epa_initial_polytope<A: TConvexShape + ?Sized, B: TConvexShape + ?Sized>(shape_a: &A, shape_b: &B, tetrahedron: &[SSupportPoint; 4], settings: &SGJKSettings) -> Result<SEPAPolytope, SPenetration> {
+    if let Some(polytope) = SEPAPolytope::new_tetrahedron(*tetrahedron) {
+        if polytope.contains_origin(settings.tolerance) {
+            return Ok(polytope);
+        }
+    }
+
+    const TRIANGLES : [[usize; 3]; 4] = [[0, 1, 2], [0, 1, 3], [0, 2, 3], [1, 2, 3]];
+
+    // -- the largest triangle gives the most reliable plane normal
+    let mut best_normal = None;
+    let mut best_area = 0.0;
+    for tri in &TRIANGLES {
+        let a = &tetrahedron[tri[0]].pos;
+        let n = Vec3::cross(&(tetrahedron[tri[1]].pos - a), &(tetrahedron[tri[2]].pos - a));
+        if n.mag() > best_area {
+            best_area = n.mag();
+            best_normal = Some((1.0 / n.mag()) * n);
+        }
+    }
+
+    // -- every point is the same or on a line, the shapes are only touching
+    let normal = match best_normal {
+        Some(n) if best_area > EPA_DEGENERATE_EPSILON => n,
+        _ => {
+            let touching = [tetrahedron[0], tetrahedron[1], tetrahedron[2]];
+            return Err(penetration_on_plane(&touching, &Vec3::zero(), 0));
+        },
+    };
+
+    let plane_d = Vec3::dot(&normal, &tetrahedron[0].pos);
+    let above = support_point(shape_a, shape_b, &normal);
+    let below = support_point(shape_a, shape_b, &-normal);
+    let above_dist = Vec3::dot(&normal, &above.pos) - plane_d;
+    let below_dist = plane_d - Vec3::dot(&normal, &below.pos);
+
+    let (lift, lift_normal) = if above_dist >= below_dist { (above, normal) } else { (below, -normal) };
+
+    // -- the origin is in a triangle of the tetrahedron, so one of them with the lift point
+    // -- forms a tetrahedron holding the origin on its boundary
+    for tri in &TRIANGLES {
+        let candidate = [tetrahedron[tri[0]], tetrahedron[tri[1]], tetrahedron[tri[2]], lift];
+        if let Some(polytope) = SEPAPolytope::new_tetrahedron(candidate) {
+            if polytope.contains_origin(settings.tolerance) {
+                return Ok(polytope);
+            }
+        }
+    }
+
+    // -- the minkowski difference is flat, so the shapes overlap without any depth
+    let flat = [tetrahedron[0], tetrahedron[1], tetrahedron[2]];
+    Err(penetration_on_plane(&flat, &-lift_normal, 0))
+}
+
+pub fn epa<A: TConvexShape + ?Sized, B: TConvexShape + ?Sized>(shape_a: &A, shape_b: &B, terminating_simplex: &SGJKTerminatingSimplex, settings: &SGJKSettings) -> EEPAResult {
+    let mut polytope = match epa_initial_polytope(shape_a, shape_b, &terminating_simplex.witnesses, settings) {
+        Ok(p) => p,
+        Err(touching) => return EEPAResult::Penetration(touching),
+    };
+
+    for iteration in 0..settings.max_iterations {
+        let closest = polytope.faces[polytope.closest_face()];
+
+        let s = support_point(shape_a, shape_b, &closest.normal);
+        let s_distance = Vec3::dot(&s.pos, &closest.normal);
+
+        // -- the face is on the boundary when nothing lies further out along its normal
+        let on_boundary = s_distance - closest.distance <= settings.tolerance;
+        let already_in_polytope = polytope.verts.iter().any(|v| v.pos == s.pos);
+        if on_boundary || already_in_polytope {
+            return EEPAResult::Penetration(polytope.penetration(&closest, iteration));
+        }
+
+        polytope.add_vertex(s);
+
+        // -- numerical trouble can eat every face, fall back to the last good answer
+        if polytope.faces.len() == 0 {
+            return EEPAResult::DidNotConverge(polytope.penetration(&closest, iteration));
+        }
+    }
+
+    let closest = polytope.faces[polytope.closest_face()];
+    EEPAResult::DidNotConverge(polytope.penetration(&closest, settings.max_iterations))
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SShapeCastSettings {
+    pub gjk: SGJKSettings,
+    pub max_iterations: usize,
+    // -- the cast stops once the shapes are this close, must be more than the GJK tolerance
+    pub contact_distance: f32,
+}
+
+impl Default for SShapeCastSettings {
+    fn default() -> Self {
+        Self {
+            gjk: SGJKSettings::default(),
+            max_iterations: 32,
+            contact_distance: 0.001,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SShapeCastHit {
+    // -- fraction of the translation travelled before touching
+    pub toi: f32,
+    // -- on the surface of B
+    pub point: Vec3,
+    // -- unit normal of B at point, facing back towards A
+    pub normal: Vec3,
+    pub iterations: usize,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum EShapeCastResult {
+    Hit(SShapeCastHit),
+    Miss,
+    // -- the shapes already overlap before moving, so there is no time of impact
+    InitiallyOverlapping,
+    // -- ran out of iterations, or GJK couldn't pin down the distance. Holds the last toi the shapes
+    // -- were known to be apart at, or the start if GJK failed on the first step
+    DidNotConverge(SShapeCastHit),
+}
+
+// -- sweeps shape_a along translation_a against a stationary shape_b, using conservative advancement:
+// -- the closing speed along the closest direction bounds how fast the distance can shrink, so we can
+// -- always step forward by distance / closing speed without passing through B
+// -- Brian Mirtich - "Impulse-based Dynamic Simulation of Rigid Body Systems" (1996), section 2.3
+pub fn shape_cast<A: TConvexShape + ?Sized, B: TConvexShape + ?Sized>(
+    shape_a: &A,
+    translation_a: &Vec3,
+    shape_b: &B,
+    settings: &SShapeCastSettings,
+) -> EShapeCastResult {
+    let mut toi = 0.0;
+    let mut last = None;
+
+    for iteration in 0..settings.max_iterations {
+        // -- a single point is a valid convex shape, so adding one translates A
+        let offset = [toi * translation_a];
+        let moved_a = SMinkowskiSum::new(shape_a, &offset[..]);
+
+        let distance = match gjk_distance(&moved_a, shape_b, &settings.gjk) {
+            EGJKDistanceResult::Separated(d) => d,
+            EGJKDistanceResult::DidNotConverge(d) => {
+                // -- an unproven distance could step us through B, and its witness_dir can't be trusted for a miss
+                return EShapeCastResult::DidNotConverge(last.unwrap_or(SShapeCastHit {
+                    toi,
+                    point: d.closest_b,
+                    normal: -d.witness_dir,
+                    iterations: iteration,
+                }));
             },
+            EGJKDistanceResult::Intersecting => {
+                // -- every step forward leaves the shapes apart, so past the start this is numerical trouble
+                return match last {
+                    Some(hit) => EShapeCastResult::DidNotConverge(hit),
+                    None => EShapeCastResult::InitiallyOverlapping,
+                };
+            },
+        };
+
+        let hit = SShapeCastHit {
+            toi,
+            point: distance.closest_b,
+            normal: -distance.witness_dir,
+            iterations: iteration,
+        };
+        if distance.distance <= settings.contact_distance {
+            return EShapeCastResult::Hit(hit);
+        }
+        last = Some(hit);
+
+        let closing_speed = Vec3::dot(translation_a, &distance.witness_dir);
+        if closing_speed <= 0.0 {
+            return EShapeCastResult::Miss;
+        }
+
+        // -- stop a little short so we finish just outside contact_distance rather than inside B
+        toi += (distance.distance - 0.5 * settings.contact_distance) / closing_speed;
+        if toi > 1.0 {
+            return EShapeCastResult::Miss;
         }
     }
 
-    println!("ERROR: Minkowski did not converge.");
-    return false;
+    match last {
+        Some(hit) => EShapeCastResult::DidNotConverge(hit),
+        None => EShapeCastResult::InitiallyOverlapping,
+    }
 }
 
 #[allow(dead_code)]
@@ -407,6 +1098,8 @@ impl SGJKDebug {
                 steps: Vec::new(),
                 pts_a: Vec::new(),
                 pts_b: Vec::new(),
+                settings: SGJKSettings::default(),
+                distance: None,
                 temp_render_token: render.temp().get_token(),
             }
         })
@@ -425,7 +1118,7 @@ impl SGJKDebug {
                     let model = em.get_model(e1_model_handle);
 
                     let loc = entities.get_entity_location(entity_1);
-                    let mesh_local_vs = render.mesh_loader().get_mesh_local_vertices(model.mesh);
+                    let mesh_local_vs = &render.mesh_loader().get_mesh_local_hull(model.mesh).verts;
 
                     let mut world_verts = Vec::new();
 
@@ -440,7 +1133,7 @@ impl SGJKDebug {
                     let e2_model_handle = em.handle_for_entity(entity_2).unwrap();
                     let model = em.get_model(e2_model_handle);
                     let loc = entities.get_entity_location(entity_2);
-                    let mesh_local_vs = render.mesh_loader().get_mesh_local_vertices(model.mesh);
+                    let mesh_local_vs = &render.mesh_loader().get_mesh_local_hull(model.mesh).verts;
 
                     let mut world_verts = Vec::new();
 
@@ -454,6 +1147,7 @@ impl SGJKDebug {
                 self.has_pts = true;
                 self.cur_step = 0;
                 self.steps.clear();
+                self.distance = None;
                 self.pts_a = world_verts_a;
                 self.pts_b = world_verts_b;
             })
@@ -520,6 +1214,10 @@ impl SGJKDebug {
             };
 
             if let Some(result) = next_step_result {
+                if let EGJKDebugStep::NoIntersection = result {
+                    self.distance = Some(gjk_distance(self.pts_a.as_slice(), self.pts_b.as_slice(), &self.settings));
+                }
+
                 self.steps.push(result);
 
                 if self.steps.len() > 1 { // handle pushing the very first step
@@ -639,6 +1337,15 @@ impl SGJKDebug {
                     render.temp().draw_line(&(a + offset), &(a + offset + dir), &dir_color, false, tok);
                 }
             }
+
+            // -- draw the closest points, and the matching closest point of the minkowski difference
+            if let Some(EGJKDistanceResult::Separated(d)) | Some(EGJKDistanceResult::DidNotConverge(d)) = &self.distance {
+                let closest_color = Vec4::new(1.0, 1.0, 0.0, 1.0);
+                render.temp().draw_sphere(&d.closest_a, 0.05, &closest_color, false, tok);
+                render.temp().draw_sphere(&d.closest_b, 0.05, &closest_color, false, tok);
+                render.temp().draw_line(&d.closest_a, &d.closest_b, &closest_color, false, tok);
+                render.temp().draw_line(&offset, &(offset + (d.closest_a - d.closest_b)), &closest_color, false, tok);
+            }
         });
     }
 
@@ -682,7 +1389,364 @@ impl SGJKDebug {
                     self.step_backward();
                     self.render_cur_step(ctxt);
                 }
+
+                match &self.distance {
+                    Some(EGJKDistanceResult::Separated(d)) => {
+                        imgui_ui.text(&im_str!("Distance: {} ({} iterations)", d.distance, d.iterations));
+                    },
+                    Some(EGJKDistanceResult::DidNotConverge(d)) => {
+                        imgui_ui.text(&im_str!("Distance did not converge, best: {}", d.distance));
+                    },
+                    Some(EGJKDistanceResult::Intersecting) => {
+                        imgui_ui.text(im_str!("Distance: intersecting"));
+                    },
+                    None => {},
+                }
+            }
+
+            let mut max_iterations = self.settings.max_iterations as i32;
+            if imgui_ui.input_int(im_str!("Max iterations"), &mut max_iterations).build() {
+                self.settings.max_iterations = std::cmp::max(max_iterations, 1) as usize;
             }
+            imgui_ui.input_float(im_str!("Tolerance"), &mut self.settings.tolerance).build();
         });
     }
 }
+
+#[allow(dead_code)]
+fn cube_pts(center: &Vec3, half_extent: f32) -> Vec<Vec3> {
+    let mut result = Vec::new();
+    for x in &[-1.0, 1.0] {
+        for y in &[-1.0, 1.0] {
+            for z in &[-1.0, 1.0] {
+                result.push(*center + half_extent * Vec3::new(*x, *y, *z));
+            }
+        }
+    }
+    result
+}
+
+#[test]
+fn test_gjk_distance_separated_cubes() {
+    let a = cube_pts(&Vec3::new(0.0, 0.0, 0.0), 0.5);
+    let b = cube_pts(&Vec3::new(3.0, 0.2, -0.1), 0.5);
+
+    match gjk_distance(a.as_slice(), b.as_slice(), &SGJKSettings::default()) {
+        EGJKDistanceResult::Separated(d) => {
+            assert!((d.distance - 2.0).abs() < 0.001);
+            assert!((d.closest_a.x - 0.5).abs() < 0.001);
+            assert!((d.closest_b.x - 2.5).abs() < 0.001);
+            assert!((d.witness_dir - Vec3::new(1.0, 0.0, 0.0)).mag() < 0.001);
+        },
+        _ => panic!("expected separated result"),
+    }
+}
+
+#[test]
+fn test_gjk_distance_diagonal_cubes() {
+    // -- closest features are corners
+    let a = cube_pts(&Vec3::new(0.0, 0.0, 0.0), 0.5);
+    let b = cube_pts(&Vec3::new(2.0, 2.0, 2.0), 0.5);
+
+    match gjk_distance(a.as_slice(), b.as_slice(), &SGJKSettings::default()) {
+        EGJKDistanceResult::Separated(d) => {
+            assert!((d.distance - (3.0 as f32).sqrt()).abs() < 0.001);
+            assert!((d.closest_a - Vec3::new(0.5, 0.5, 0.5)).mag() < 0.001);
+            assert!((d.closest_b - Vec3::new(1.5, 1.5, 1.5)).mag() < 0.001);
+        },
+        _ => panic!("expected separated result"),
+    }
+}
+
+#[test]
+fn test_gjk_distance_intersecting_cubes() {
+    let a = cube_pts(&Vec3::new(0.0, 0.0, 0.0), 0.5);
+    let b = cube_pts(&Vec3::new(0.5, 0.3, 0.0), 0.5);
+
+    match gjk_distance(a.as_slice(), b.as_slice(), &SGJKSettings::default()) {
+        EGJKDistanceResult::Intersecting => {},
+        _ => panic!("expected intersecting result"),
+    }
+    match gjk_intersection(a.as_slice(), b.as_slice(), &SGJKSettings::default()) {
+        EGJKResult::Intersection(_) => {},
+        _ => panic!("expected intersection"),
+    }
+}
+
+#[test]
+fn test_gjk_distance_non_convergence() {
+    let a = cube_pts(&Vec3::new(0.0, 0.0, 0.0), 0.5);
+    let b = cube_pts(&Vec3::new(3.0, 0.2, -0.1), 0.5);
+
+    let settings = SGJKSettings {
+        max_iterations: 1,
+        tolerance: 0.0,
+    };
+    match gjk_distance(a.as_slice(), b.as_slice(), &settings) {
+        EGJKDistanceResult::DidNotConverge(d) => assert!(d.distance >= 2.0 - 0.001),
+        _ => panic!("expected non-convergence with a single iteration"),
+    }
+}
+
+#[allow(dead_code)]
+fn sphere_pts(center: &Vec3, radius: f32) -> Vec<Vec3> {
+    let mut result = Vec::new();
+    let rings = 24;
+    let segments = 48;
+    result.push(*center + Vec3::new(0.0, radius, 0.0));
+    result.push(*center + Vec3::new(0.0, -radius, 0.0));
+    for ring in 1..rings {
+        let theta = std::f32::consts::PI * (ring as f32) / (rings as f32);
+        for segment in 0..segments {
+            let phi = 2.0 * std::f32::consts::PI * (segment as f32) / (segments as f32);
+            let dir = Vec3::new(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin());
+            result.push(*center + radius * dir);
+        }
+    }
+    result
+}
+
+#[allow(dead_code)]
+fn epa_test_penetration(pts_a: &[Vec3], pts_b: &[Vec3]) -> SPenetration {
+    let settings = SGJKSettings::default();
+    match gjk_intersection(pts_a, pts_b, &settings) {
+        EGJKResult::Intersection(terminating_simplex) => {
+            match epa(pts_a, pts_b, &terminating_simplex, &settings) {
+                EEPAResult::Penetration(p) => p,
+                EEPAResult::DidNotConverge(_) => panic!("EPA did not converge"),
+            }
+        },
+        _ => panic!("expected GJK to find an intersection"),
+    }
+}
+
+#[test]
+fn test_gjk_distance_nearly_flat_simplex() {
+    use crate::shapes::{SCapsule, STransformedShape};
+    use crate::utils::{STransform};
+
+    // -- a capsule next to the bottom edge of a long thin triangle, the new support point ends up
+    // -- almost in the plane of the simplex and the side tests used to call it an intersection
+    let feet = Vec3::new(0.85000014, 0.0050000064, 0.0);
+    let capsule = STransformedShape::new(
+        SCapsule{ half_height: 0.5 * 1.8 - 0.3, radius: 0.3 },
+        STransform::new_translation(&(feet + Vec3::new(0.0, 0.5 * 1.8, 0.0))),
+    );
+    let tri = [Vec3::new(1.0, 0.0, -10.0), Vec3::new(1.0, 0.0, 10.0), Vec3::new(4.0, 1.7320508, 10.0)];
+
+    match gjk_distance(&capsule, &tri[..], &SGJKSettings::default()) {
+        EGJKDistanceResult::Separated(d) => assert!((d.distance - 0.039).abs() < 0.005),
+        _ => panic!("expected the shapes to be separated"),
+    }
+}
+
+#[test]
+fn test_epa_boxes() {
+    let a = cube_pts(&Vec3::new(0.0, 0.0, 0.0), 0.5);
+    let b = cube_pts(&Vec3::new(0.8, 0.1, 0.05), 0.5);
+
+    let p = epa_test_penetration(a.as_slice(), b.as_slice());
+    assert!((p.depth - 0.2).abs() < 0.001);
+    assert!((p.normal - Vec3::new(1.0, 0.0, 0.0)).mag() < 0.001);
+    assert!((p.contact_a.x - 0.5).abs() < 0.001);
+    assert!((p.contact_b.x - 0.3).abs() < 0.001);
+    assert!(((p.contact_a - p.contact_b) - p.depth * p.normal).mag() < 0.001);
+}
+
+#[test]
+fn test_epa_spheres() {
+    // -- spheres are tessellated, so only roughly match the analytic answer
+    let a = sphere_pts(&Vec3::new(0.0, 0.0, 0.0), 1.0);
+    let b = sphere_pts(&Vec3::new(1.5, 0.0, 0.0), 1.0);
+
+    let p = epa_test_penetration(a.as_slice(), b.as_slice());
+    assert!((p.depth - 0.5).abs() < 0.05);
+    assert!(Vec3::dot(&p.normal, &Vec3::new(1.0, 0.0, 0.0)) > 0.98);
+}
+
+#[test]
+fn test_epa_hulls() {
+    // -- octahedron tip poking up into the bottom of a box
+    let a = vec![
+        Vec3::new(1.0, 0.0, 0.0),
+        Vec3::new(-1.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        Vec3::new(0.0, -1.0, 0.0),
+        Vec3::new(0.0, 0.0, 1.0),
+        Vec3::new(0.0, 0.0, -1.0),
+    ];
+    let b = cube_pts(&Vec3::new(0.0, 1.2, 0.0), 0.5);
+
+    let p = epa_test_penetration(a.as_slice(), b.as_slice());
+    assert!((p.depth - 0.3).abs() < 0.001);
+    assert!((p.normal - Vec3::new(0.0, 1.0, 0.0)).mag() < 0.001);
+    assert!((p.contact_a - Vec3::new(0.0, 1.0, 0.0)).mag() < 0.001);
+}
+
+#[test]
+fn test_epa_touching_boxes() {
+    let a = cube_pts(&Vec3::new(0.0, 0.0, 0.0), 0.5);
+    let b = cube_pts(&Vec3::new(1.0, 0.0, 0.0), 0.5);
+
+    // -- the faces touch, so the origin is on the boundary of the minkowski difference and counts as inside
+    let settings = SGJKSettings::default();
+    let terminating_simplex = match gjk_intersection(a.as_slice(), b.as_slice(), &settings) {
+        EGJKResult::Intersection(terminating_simplex) => terminating_simplex,
+        EGJKResult::NoIntersection => panic!("touching boxes should intersect"),
+        EGJKResult::DidNotConverge => panic!("GJK did not converge"),
+    };
+
+    match epa(a.as_slice(), b.as_slice(), &terminating_simplex, &settings) {
+        EEPAResult::Penetration(p) => assert!(p.depth < 0.001),
+        EEPAResult::DidNotConverge(_) => panic!("EPA did not converge"),
+    }
+}
+
+#[test]
+fn test_epa_coplanar_simplex() {
+    // -- flat tetrahedron around the origin, the shapes overlap inside a plane
+    let a = vec![
+        Vec3::new(-0.5, 0.0, -0.5),
+        Vec3::new(0.5, 0.0, -0.5),
+        Vec3::new(0.5, 0.0, 0.5),
+        Vec3::new(-0.5, 0.0, 0.5),
+    ];
+    let b = cube_pts(&Vec3::new(0.0, 0.0, 0.0), 0.25);
+
+    let witness = |pos: Vec3| SSupportPoint { pos, a: pos, b: Vec3::zero() };
+    let terminating_simplex = SGJKTerminatingSimplex {
+        simplex: ESimplex::Four(S4Simplex {
+            a: a[0], b: a[1], c: a[2], d: a[3],
+        }),
+        witnesses: [witness(a[0]), witness(a[1]), witness(a[2]), witness(a[3])],
+    };
+
+    // -- against a point the minkowski difference stays flat, so there is no depth
+    let origin = vec![Vec3::zero()];
+    match epa(a.as_slice(), origin.as_slice(), &terminating_simplex, &SGJKSettings::default()) {
+        EEPAResult::Penetration(p) => assert!(p.depth < 0.001),
+        EEPAResult::DidNotConverge(_) => panic!("EPA did not converge"),
+    }
+
+    // -- against a box the flat simplex gets lifted and expands to the real depth
+    match epa(a.as_slice(), b.as_slice(), &terminating_simplex, &SGJKSettings::default()) {
+        EEPAResult::Penetration(p) => {
+            assert!((p.depth - 0.25).abs() < 0.001);
+            assert!(Vec3::dot(&p.normal, &Vec3::new(0.0, 1.0, 0.0)).abs() > 0.999);
+        },
+        EEPAResult::DidNotConverge(_) => panic!("EPA did not converge"),
+    }
+}
+
+#[test]
+fn test_gjk_analytic_shapes() {
+    use crate::math::{Quat};
+    use crate::shapes::{SSphere, SCapsule, SBox, STransformedShape};
+    use crate::utils::{STransform};
+
+    let sphere = SSphere{ radius: 1.0 };
+    let capsule = STransformedShape::new(
+        SCapsule{ half_height: 1.0, radius: 0.5 },
+        STransform::new(&Vec3::new(3.0, 0.0, 0.0), &Quat::new_angle_axis(0.0, &Vec3::new(0.0, 1.0, 0.0)), 1.0),
+    );
+
+    match gjk_distance(&sphere, &capsule, &SGJKSettings::default()) {
+        EGJKDistanceResult::Separated(result) => {
+            // -- witness points on curved surfaces converge slower than the distance does
+            assert!((result.distance - 1.5).abs() < 0.001);
+            assert!((result.closest_a - Vec3::new(1.0, 0.0, 0.0)).mag() < 0.01);
+            assert!((result.closest_b - Vec3::new(2.5, 0.0, 0.0)).mag() < 0.01);
+        },
+        _ => panic!("expected separated shapes"),
+    }
+
+    // -- sphere sunk 0.25 into the top of a box
+    let b = SBox{ half_extents: Vec3::new(2.0, 1.0, 2.0) };
+    let sunk_sphere = STransformedShape::new(
+        SSphere{ radius: 0.5 },
+        STransform::new(&Vec3::new(0.0, 1.25, 0.0), &Quat::new_angle_axis(0.0, &Vec3::new(0.0, 1.0, 0.0)), 1.0),
+    );
+
+    let settings = SGJKSettings::default();
+    match gjk_intersection(&b, &sunk_sphere, &settings) {
+        EGJKResult::Intersection(terminating_simplex) => {
+            match epa(&b, &sunk_sphere, &terminating_simplex, &settings) {
+                EEPAResult::Penetration(p) => {
+                    assert!((p.depth - 0.25).abs() < 0.01);
+                    assert!(Vec3::dot(&p.normal, &Vec3::new(0.0, 1.0, 0.0)) > 0.99);
+                },
+                EEPAResult::DidNotConverge(_) => panic!("EPA did not converge"),
+            }
+        },
+        _ => panic!("expected intersecting shapes"),
+    }
+}
+
+#[test]
+fn test_shape_cast() {
+    use crate::math::{Quat};
+    use crate::shapes::{SSphere, SBox, STransformedShape};
+    use crate::utils::{STransform};
+
+    let settings = SShapeCastSettings::default();
+    let b = SBox{ half_extents: Vec3::new(1.0, 1.0, 1.0) };
+    let sphere_at = |p: Vec3| STransformedShape::new(SSphere{ radius: 0.5 }, STransform::new(&p, &Quat::new_identity(), 1.0));
+
+    // -- head on, touching after travelling 3.5 of the 10
+    match shape_cast(&sphere_at(Vec3::new(-5.0, 0.0, 0.0)), &Vec3::new(10.0, 0.0, 0.0), &b, &settings) {
+        EShapeCastResult::Hit(hit) => {
+            assert!((hit.toi - 0.35).abs() < 0.001);
+            assert!((hit.point - Vec3::new(-1.0, 0.0, 0.0)).mag() < 0.01);
+            assert!(Vec3::dot(&hit.normal, &Vec3::new(-1.0, 0.0, 0.0)) > 0.99);
+        },
+        _ => panic!("expected a hit"),
+    }
+
+    // -- glancing off the top edge, the contact is on the edge and the normal points between the faces
+    match shape_cast(&sphere_at(Vec3::new(-5.0, 1.3, 0.0)), &Vec3::new(10.0, 0.0, 0.0), &b, &settings) {
+        EShapeCastResult::Hit(hit) => {
+            assert!((hit.point - Vec3::new(-1.0, 1.0, 0.0)).mag() < 0.01);
+            assert!(hit.normal.x < 0.0 && hit.normal.y > 0.0);
+        },
+        _ => panic!("expected a hit"),
+    }
+
+    // -- passing over the top, stopping short, and moving away all miss
+    let misses = [
+        (Vec3::new(-5.0, 1.6, 0.0), Vec3::new(10.0, 0.0, 0.0)),
+        (Vec3::new(-5.0, 0.0, 0.0), Vec3::new(3.0, 0.0, 0.0)),
+        (Vec3::new(-5.0, 0.0, 0.0), Vec3::new(-10.0, 0.0, 0.0)),
+    ];
+    for (start, translation) in &misses {
+        match shape_cast(&sphere_at(*start), translation, &b, &settings) {
+            EShapeCastResult::Miss => {},
+            _ => panic!("expected a miss"),
+        }
+    }
+
+    match shape_cast(&sphere_at(Vec3::new(0.0, 1.2, 0.0)), &Vec3::new(1.0, 0.0, 0.0), &b, &settings) {
+        EShapeCastResult::InitiallyOverlapping => {},
+        _ => panic!("expected an initial overlap"),
+    }
+
+    // -- a box falling onto a tilted triangle
+    let tri = [Vec3::new(-5.0, 0.0, -5.0), Vec3::new(5.0, 2.0, -5.0), Vec3::new(0.0, 1.0, 5.0)];
+    let falling_box = STransformedShape::new(b, STransform::new(&Vec3::new(0.0, 10.0, 0.0), &Quat::new_identity(), 1.0));
+    match shape_cast(&falling_box, &Vec3::new(0.0, -20.0, 0.0), &tri[..], &settings) {
+        EShapeCastResult::Hit(hit) => {
+            // -- the plane is y = 0.2x + 1, so the box's lowest corner over it is at x = 1
+            let expected_travel = 10.0 - 1.0 - 1.2;
+            assert!((hit.toi * 20.0 - expected_travel).abs() < 0.01);
+            assert!(hit.normal.y > 0.9);
+        },
+        _ => panic!("expected a hit"),
+    }
+
+    // -- GJK without enough iterations to converge stops the cast where it was last known to be clear
+    let mut short_settings = SShapeCastSettings::default();
+    short_settings.gjk.max_iterations = 1;
+    match shape_cast(&falling_box, &Vec3::new(0.0, -20.0, 0.0), &tri[..], &short_settings) {
+        EShapeCastResult::DidNotConverge(hit) => assert!(hit.toi == 0.0),
+        _ => panic!("expected the cast not to converge"),
+    }
+}
@@ -54,6 +54,9 @@ pub struct SInput {
     pub escape_down: bool,
     pub key_pad_enter_down: bool,
     pub minus_down: bool,
+    pub shift_down: bool,
+    pub control_down: bool,
+    pub alt_down: bool,
 
     pub a_edge: EInputEdge,
     pub b_edge: EInputEdge,
@@ -100,6 +103,9 @@ pub struct SInput {
     pub escape_edge: EInputEdge,
     pub key_pad_enter_edge: EInputEdge,
     pub minus_edge: EInputEdge,
+    pub shift_edge: EInputEdge,
+    pub control_edge: EInputEdge,
+    pub alt_edge: EInputEdge,
 
     pub left_mouse_down: bool,
     pub middle_mouse_down: bool,
@@ -115,6 +121,7 @@ pub struct SInput {
     pub mouse_cursor_pos_window: [i32; 2],
     pub mouse_dx: i32,
     pub mouse_dy: i32,
+    pub mouse_wheel: i32, // in notches, reset each frame
 }
 
 pub struct SInputEventHandler<'a> {
@@ -210,6 +217,9 @@ impl SInput {
             escape_down: false,
             key_pad_enter_down: false,
             minus_down: false,
+            shift_down: false,
+            control_down: false,
+            alt_down: false,
 
             a_edge: EInputEdge::Unchanged,
             b_edge: EInputEdge::Unchanged,
@@ -256,6 +266,9 @@ impl SInput {
             escape_edge: EInputEdge::Unchanged,
             key_pad_enter_edge: EInputEdge::Unchanged,
             minus_edge: EInputEdge::Unchanged,
+            shift_edge: EInputEdge::Unchanged,
+            control_edge: EInputEdge::Unchanged,
+            alt_edge: EInputEdge::Unchanged,
 
             left_mouse_down: false,
             middle_mouse_down: false,
@@ -271,6 +284,7 @@ impl SInput {
             mouse_cursor_pos_window: [0, 2],
             mouse_dx: 0,
             mouse_dy: 0,
+            mouse_wheel: 0,
         }
     }
 
@@ -320,6 +334,9 @@ impl SInput {
         self.escape_edge = EInputEdge::Unchanged;
         self.key_pad_enter_edge = EInputEdge::Unchanged;
         self.minus_edge = EInputEdge::Unchanged;
+        self.shift_edge = EInputEdge::Unchanged;
+        self.control_edge = EInputEdge::Unchanged;
+        self.alt_edge = EInputEdge::Unchanged;
 
         self.left_mouse_edge = EInputEdge::Unchanged;
         self.middle_mouse_edge = EInputEdge::Unchanged;
@@ -328,6 +345,7 @@ impl SInput {
 
     pub fn frame<'a>(&'a mut self, imgui_io: &'a mut imgui::Io) -> SInputEventHandler {
         self.reset_edges();
+        self.mouse_wheel = 0;
         SInputEventHandler {
             input: self,
             imgui_io,
@@ -453,6 +471,18 @@ impl<'a> SInputEventHandler<'a> {
             EKey::KeyPadEnter => change!(key_pad_enter_down, key_pad_enter_edge, None, Some(EKey::KeyPadEnter as usize)),
             EKey::Minus => change!(minus_down, minus_edge, Some('-'), None),
             EKey::Tilde => change!(tilde_down, tilde_edge, Some('~'), None),
+            EKey::Shift => {
+                change!(shift_down, shift_edge, None, None);
+                self.imgui_io.key_shift = down;
+            },
+            EKey::Control => {
+                change!(control_down, control_edge, None, None);
+                self.imgui_io.key_ctrl = down;
+            },
+            EKey::Alt => {
+                change!(alt_down, alt_edge, None, None);
+                self.imgui_io.key_alt = down;
+            },
 
             _ => (),
         }
@@ -498,5 +528,12 @@ impl<'a> SInputEventHandler<'a> {
         self.input.mouse_dx = dx;
         self.input.mouse_dy = dy;
     }
+
+    pub fn handle_mouse_wheel(&mut self, delta: i16) {
+        // -- windows reports the wheel in multiples of WHEEL_DELTA (120) per notch
+        let notches = (delta as f32) / 120.0;
+        self.input.mouse_wheel += notches.round() as i32;
+        self.imgui_io.mouse_wheel += notches;
+    }
 }
 
@@ -0,0 +1,169 @@
+use std::ops::{Deref};
+
+use crate::math::{Vec3, Vec4, Quat};
+use crate::utils::{STransform};
+
+// -- reflection over init/component structs. A type lists its fields to a visitor, and the
+// -- visitor decides what to do with them, e.g. SImguiInspector builds an editor widget per field.
+
+pub trait TInspectVisitor {
+    // -- each visit returns true if the value was changed
+    fn visit_f32(&mut self, name: &str, value: &mut f32) -> bool;
+    fn visit_vec3(&mut self, name: &str, value: &mut Vec3) -> bool;
+    fn visit_quat(&mut self, name: &str, value: &mut Quat) -> bool;
+    fn visit_colour(&mut self, name: &str, value: &mut Vec4) -> bool;
+    fn visit_string(&mut self, name: &str, value: &mut String) -> bool;
+    fn visit_enum(&mut self, name: &str, value: &mut usize, variants: &[&str]) -> bool;
+    fn visit_asset_path(&mut self, name: &str, value: &mut String, extension: &str) -> bool;
+    fn visit_struct(&mut self, name: &str, value: &mut dyn TInspect) -> bool;
+}
+
+pub trait TInspect {
+    fn inspect(&mut self, visitor: &mut dyn TInspectVisitor) -> bool;
+}
+
+pub trait TInspectEnum: Sized {
+    fn variants() -> &'static [&'static str];
+    fn to_index(&self) -> usize;
+    fn from_index(index: usize) -> Self;
+}
+
+pub fn inspect_enum<T: TInspectEnum>(visitor: &mut dyn TInspectVisitor, name: &str, value: &mut T) -> bool {
+    let mut index = value.to_index();
+    if visitor.visit_enum(name, &mut index, T::variants()) {
+        *value = T::from_index(index);
+        return true;
+    }
+    false
+}
+
+pub fn inspect_optional_string(visitor: &mut dyn TInspectVisitor, name: &str, value: &mut Option<String>) -> bool {
+    let mut string = value.clone().unwrap_or_default();
+    if visitor.visit_string(name, &mut string) {
+        *value = if string.is_empty() { None } else { Some(string) };
+        return true;
+    }
+    false
+}
+
+pub fn inspect_optional_colour(visitor: &mut dyn TInspectVisitor, name: &str, value: &mut Option<Vec4>, default: &Vec4) -> bool {
+    let mut colour = value.unwrap_or(*default);
+    if visitor.visit_colour(name, &mut colour) {
+        *value = Some(colour);
+        return true;
+    }
+    false
+}
+
+impl TInspect for STransform {
+    fn inspect(&mut self, visitor: &mut dyn TInspectVisitor) -> bool {
+        let mut changed = visitor.visit_vec3("Position", &mut self.t);
+        changed |= visitor.visit_quat("Rotation", &mut self.r);
+        changed |= visitor.visit_f32("Scale", &mut self.s);
+        changed
+    }
+}
+
+pub struct SImguiInspector<'a, 'ui> {
+    ui: &'a imgui::Ui<'ui>,
+}
+
+impl<'a, 'ui> SImguiInspector<'a, 'ui> {
+    pub fn new(ui: &'a imgui::Ui<'ui>) -> Self {
+        Self {
+            ui,
+        }
+    }
+}
+
+impl<'a, 'ui> TInspectVisitor for SImguiInspector<'a, 'ui> {
+    fn visit_f32(&mut self, name: &str, value: &mut f32) -> bool {
+        imgui::DragFloat::new(self.ui, &imgui::im_str!("{}", name), value).speed(0.01).build()
+    }
+
+    fn visit_vec3(&mut self, name: &str, value: &mut Vec3) -> bool {
+        let mut vals = [value.x, value.y, value.z];
+        if imgui::DragFloat3::new(self.ui, &imgui::im_str!("{}", name), &mut vals).speed(0.1).build() {
+            *value = Vec3::new(vals[0], vals[1], vals[2]);
+            return true;
+        }
+        false
+    }
+
+    fn visit_quat(&mut self, name: &str, value: &mut Quat) -> bool {
+        // -- edited as euler angles in degrees
+        let euler = value.to_euler();
+        let mut vals = [euler.x.to_degrees(), euler.y.to_degrees(), euler.z.to_degrees()];
+        if imgui::DragFloat3::new(self.ui, &imgui::im_str!("{}", name), &mut vals).speed(1.0).build() {
+            *value = Quat::new_from_euler(&Vec3::new(vals[0].to_radians(), vals[1].to_radians(), vals[2].to_radians()));
+            return true;
+        }
+        false
+    }
+
+    fn visit_colour(&mut self, name: &str, value: &mut Vec4) -> bool {
+        let mut vals = [value.x, value.y, value.z, value.w];
+        if imgui::ColorEdit::new(&imgui::im_str!("{}", name), &mut vals).build(self.ui) {
+            *value = Vec4::new(vals[0], vals[1], vals[2], vals[3]);
+            return true;
+        }
+        false
+    }
+
+    fn visit_string(&mut self, name: &str, value: &mut String) -> bool {
+        let mut buf = imgui::ImString::with_capacity(128);
+        buf.push_str(value.as_str());
+        if self.ui.input_text(&imgui::im_str!("{}", name), &mut buf).build() {
+            *value = String::from(buf.to_str());
+            return true;
+        }
+        false
+    }
+
+    fn visit_enum(&mut self, name: &str, value: &mut usize, variants: &[&str]) -> bool {
+        let im_variants : Vec<imgui::ImString> = variants.iter().map(|v| imgui::ImString::new(*v)).collect();
+        let im_variant_refs : Vec<&imgui::ImStr> = im_variants.iter().map(|v| v.deref()).collect();
+        imgui::ComboBox::new(&imgui::im_str!("{}", name)).build_simple_string(self.ui, value, &im_variant_refs)
+    }
+
+    fn visit_asset_path(&mut self, name: &str, value: &mut String, extension: &str) -> bool {
+        let mut changed = self.visit_string(name, value);
+
+        // -- offer every matching file in the assets folder
+        let mut chosen = None;
+        imgui::ComboBox::new(&imgui::im_str!("##{}_assets", name))
+            .preview_value(&imgui::im_str!("{} files", extension))
+            .build(self.ui, || {
+                if let Ok(dir) = std::fs::read_dir("assets/") {
+                    for entry in dir.flatten() {
+                        let path = entry.path();
+                        if path.extension().map_or(false, |e| e == extension) {
+                            let path_str = String::from(path.to_string_lossy());
+                            if imgui::Selectable::new(&imgui::im_str!("{}", path_str)).selected(path_str == *value).build(self.ui) {
+                                chosen = Some(path_str);
+                            }
+                        }
+                    }
+                }
+            });
+
+        if let Some(path) = chosen {
+            *value = path;
+            changed = true;
+        }
+
+        changed
+    }
+
+    fn visit_struct(&mut self, name: &str, value: &mut dyn TInspect) -> bool {
+        let mut changed = false;
+        if self.ui.collapsing_header(&imgui::im_str!("{}", name)).default_open(true).build() {
+            let id = self.ui.push_id(name);
+            self.ui.indent();
+            changed = value.inspect(self);
+            self.ui.unindent();
+            id.pop(self.ui);
+        }
+        changed
+    }
+}
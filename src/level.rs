@@ -1,17 +1,36 @@
 use serde::{Serialize, Deserialize};
 
-use crate::allocate::{SAllocatorRef};
+use crate::allocate::{SAllocatorRef, STACK_ALLOCATOR};
+use crate::bvh;
 use crate::collections::{SVec};
 use crate::databucket::{SEntityBVH};
 use crate::entity::{SEntityHandle, SEntityBucket};
 use crate::entity_animation;
 use crate::entity_model;
+use crate::entity_rigid_body;
+use crate::entity_trigger;
 use crate::entitytypes::{EEntityInit};
 use crate::game_context::{SGameContext};
+use crate::utils::{SAABB};
+
+// -- editor-only state for an entity, shown and edited in the outliner
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct SEntityEditorData {
+    pub folder: Option<String>,
+    pub hidden: bool,
+    pub locked: bool,
+}
 
 #[derive(Serialize, Deserialize)]
 pub struct SInit {
     entity_inits: Vec<EEntityInit>, // $$$FRK(TODO): write what I need to make SVec serde compatible - difficulty is where does the allocator live?
+
+    // -- parallel to entity_inits, but may be shorter in level files saved before it existed
+    #[serde(default)]
+    entity_editor_data: Vec<SEntityEditorData>,
+    // -- kept separately so empty folders survive a save
+    #[serde(default)]
+    folders: Vec<String>,
 }
 
 pub struct SLevel {
@@ -22,6 +41,8 @@ impl SInit {
     pub fn new() -> Self {
         Self {
             entity_inits: Vec::new(),
+            entity_editor_data: Vec::new(),
+            folders: Vec::new(),
         }
     }
 
@@ -33,26 +54,206 @@ impl SInit {
 
         Self {
             entity_inits,
+            entity_editor_data: Vec::new(),
+            folders: Vec::new(),
+        }
+    }
+
+    // -- rebuild the entity inits from live entities, keeping the editor data
+    pub fn refresh_entity_inits(&mut self, game_context: &SGameContext, entities: &[SEntityHandle]) {
+        assert!(entities.len() == self.entity_inits.len());
+        for (i, entity) in entities.iter().enumerate() {
+            self.entity_inits[i] = EEntityInit::new_from_entity(game_context, entity.clone());
+        }
+    }
+
+    pub fn entity_inits(&self) -> &[EEntityInit] {
+        self.entity_inits.as_slice()
+    }
+
+    pub fn set_entity_init(&mut self, index: usize, e_init: EEntityInit) {
+        self.entity_inits[index] = e_init;
+    }
+
+    pub fn entity_editor_data(&self, index: usize) -> SEntityEditorData {
+        self.entity_editor_data.get(index).cloned().unwrap_or_default()
+    }
+
+    pub fn set_entity_editor_data(&mut self, index: usize, editor_data: SEntityEditorData) {
+        assert!(index < self.entity_inits.len());
+        if index >= self.entity_editor_data.len() {
+            self.entity_editor_data.resize(self.entity_inits.len(), SEntityEditorData::default());
+        }
+        self.entity_editor_data[index] = editor_data;
+    }
+
+    pub fn folders(&self) -> &[String] {
+        self.folders.as_slice()
+    }
+
+    pub fn add_folder(&mut self, name: &str) -> Result<(), &'static str> {
+        if name.is_empty() {
+            return Err("folder name can't be empty");
+        }
+        if self.folders.iter().any(|f| f == name) {
+            return Err("folder already exists");
+        }
+
+        self.folders.push(String::from(name));
+        Ok(())
+    }
+
+    // -- entities in the folder move back to the root
+    pub fn remove_folder(&mut self, name: &str) {
+        self.folders.retain(|f| f != name);
+        for editor_data in &mut self.entity_editor_data {
+            if editor_data.folder.as_ref().map_or(false, |f| f == name) {
+                editor_data.folder = None;
+            }
         }
     }
 }
 
 impl SLevel {
+    const MAX_ENTITIES: usize = 1024;
+
     pub fn new(allocator: &SAllocatorRef, game_context: &SGameContext, init: &SInit) -> Result<Self, &'static str> {
-        let mut owned_entities = SVec::<SEntityHandle>::new(allocator, init.entity_inits.len(), 0).expect("Failed to allocate memory for owned_entities table.");
-        for e_init in &init.entity_inits {
+        // -- leave room for entities added in the editor
+        let capacity = std::cmp::max(init.entity_inits.len(), Self::MAX_ENTITIES);
+        let mut owned_entities = SVec::<SEntityHandle>::new(allocator, capacity, 0).expect("Failed to allocate memory for owned_entities table.");
+        for (i, e_init) in init.entity_inits.iter().enumerate() {
             let e = e_init.init(game_context)?;
             owned_entities.push(e);
+
+            if init.entity_editor_data(i).hidden {
+                game_context.data_bucket.get::<SEntityBucket>().with_mut(|entities: &mut SEntityBucket| {
+                    entities.set_entity_hidden(e, true);
+                });
+            }
         }
 
+        Self::build_bvh_entries(game_context)?;
+
         Ok(Self{
             owned_entities,
         })
     }
 
+    // -- builds the BVH over every model that isn't in it yet in one batch, rather than letting
+    // -- each get inserted on its own the first time it's updated
+    fn build_bvh_entries(game_context: &SGameContext) -> Result<(), &'static str> {
+        use crate::render;
+
+        game_context.data_bucket.get::<SEntityBVH>()
+            .and::<entity_model::SBucket>()
+            .and::<SEntityBucket>()
+            .and::<render::SRender>()
+            .with_mmcc(|bvh, e_model, entities, render| {
+                let num_models = e_model.models.len();
+                if num_models == 0 {
+                    return Ok(());
+                }
+
+                STACK_ALLOCATOR.with(|sa| -> Result<(), &'static str> {
+                    let mut model_handles = SVec::<entity_model::SHandle>::new(&sa.as_ref(), num_models, 0)?;
+                    let mut batch = SVec::<(SEntityHandle, SAABB)>::new(&sa.as_ref(), num_models, 0)?;
+                    for model_handle in 0..num_models {
+                        if e_model.get_bvh_entry(model_handle).is_none() {
+                            model_handles.push(model_handle);
+                            batch.push((e_model.get_entity(model_handle), e_model.world_aabb(model_handle, entities, render)));
+                        }
+                    }
+
+                    let mut bvh_entries = SVec::<bvh::SNodeHandle>::new(&sa.as_ref(), num_models, 0)?;
+                    bvh.insert_batch(batch.as_slice(), &mut bvh_entries)?;
+
+                    for i in 0..model_handles.len() {
+                        e_model.set_bvh_entry(model_handles[i], bvh_entries[i]);
+                    }
+
+                    Ok(())
+                })
+            })
+    }
+
+    pub fn owned_entities(&self) -> &[SEntityHandle] {
+        self.owned_entities.as_ref()
+    }
+
+    // -- owned_entities and init.entity_inits are kept parallel, so entity i was created from init i
+    pub fn add_entity(&mut self, game_context: &SGameContext, init: &mut SInit, e_init: EEntityInit) -> Result<SEntityHandle, &'static str> {
+        let index = self.owned_entities.len();
+        self.insert_entity(game_context, init, index, e_init)
+    }
+
+    // -- same as add_entity, but the entity goes in at index instead of at the end
+    pub fn insert_entity(&mut self, game_context: &SGameContext, init: &mut SInit, index: usize, e_init: EEntityInit) -> Result<SEntityHandle, &'static str> {
+        let e = e_init.init(game_context)?;
+        self.owned_entities.insert(index, e);
+        init.entity_inits.insert(index, e_init);
+        if init.entity_editor_data.len() > 0 {
+            init.entity_editor_data.resize(init.entity_inits.len() - 1, SEntityEditorData::default());
+            init.entity_editor_data.insert(index, SEntityEditorData::default());
+        }
+
+        Ok(e)
+    }
+
+    pub fn remove_entities(&mut self, game_context: &SGameContext, init: &mut SInit, entities: &[SEntityHandle]) {
+        use crate::render;
+
+        let mut i = 0;
+        while i < self.owned_entities.len() {
+            if entities.contains(&self.owned_entities[i]) {
+                self.owned_entities.remove(i);
+                init.entity_inits.remove(i);
+                if i < init.entity_editor_data.len() {
+                    init.entity_editor_data.remove(i);
+                }
+            }
+            else {
+                i += 1;
+            }
+        }
+
+        game_context.data_bucket.get::<entity_rigid_body::SBucket>()
+            .with_mut(|e_rigid_body| {
+                e_rigid_body.purge_entities(entities);
+            });
+
+        game_context.data_bucket.get::<entity_trigger::SBucket>()
+            .with_mut(|e_trigger| {
+                e_trigger.purge_entities(entities);
+            });
+
+        game_context.data_bucket.get::<SEntityBVH>()
+            .and::<entity_model::SBucket>()
+            .and::<entity_animation::SBucket>()
+            .and::<render::SRender>()
+            .and::<SEntityBucket>()
+            .with_mmmmm(|bvh, e_model, e_anim, render, entities_bucket| {
+                render.flush().unwrap();
+
+                bvh.purge_owners(entities);
+                e_model.purge_entities(entities);
+                e_anim.purge_entities(entities);
+                entities_bucket.purge_entities(entities);
+            });
+    }
+
     pub fn destroy(&mut self, game_context: &SGameContext) {
         use crate::render;
 
+        game_context.data_bucket.get::<entity_rigid_body::SBucket>()
+            .with_mut(|e_rigid_body| {
+                e_rigid_body.purge_entities(self.owned_entities.as_ref());
+            });
+
+        game_context.data_bucket.get::<entity_trigger::SBucket>()
+            .with_mut(|e_trigger| {
+                e_trigger.purge_entities(self.owned_entities.as_ref());
+            });
+
         game_context.data_bucket.get::<SEntityBVH>()
             .and::<entity_model::SBucket>()
             .and::<entity_animation::SBucket>()
@@ -18,6 +18,7 @@ mod allocate;
 mod animation;
 mod bvh;
 mod collections;
+mod collision;
 mod databucket;
 mod debug_ui;
 mod directxgraphicssamples;
@@ -25,21 +26,30 @@ mod editmode;
 mod entity;
 mod entity_animation;
 mod entity_model;
+mod entity_rigid_body;
+mod entity_trigger;
 mod game_context;
 mod game_mode;
 mod gjk;
 mod input;
+mod inspect;
 mod level;
 mod math;
 mod niced3d12;
+mod physics;
 mod rustywindows;
 mod string_db;
 mod typeyd3d12;
 mod utils;
 mod enumflags;
 mod camera;
+mod character_controller;
 mod model;
+mod quickhull;
 mod render;
+mod scene_query;
+mod shapes;
+mod trimesh;
 mod entitytypes;
 mod win;
 
@@ -55,7 +65,7 @@ use niced3d12 as n12;
 use typeyd3d12 as t12;
 
 fn update_frame(game_context: &SGameContext, frame_context: &mut SFrameContext) -> Result<(), &'static str> {
-    game_mode::update_toggle_mode(game_context);
+    game_mode::update_toggle_mode(game_context, frame_context);
     camera::update_debug_camera(game_context, frame_context);
 
     let edit_mode_input = editmode::update_create_input_for_frame(game_context, frame_context);
@@ -63,13 +73,24 @@ fn update_frame(game_context: &SGameContext, frame_context: &mut SFrameContext)
     editmode::update_edit_mode_level_editor_ui(game_context, frame_context);
     editmode::update_edit_mode(game_context, frame_context);
 
-    entity_animation::update_animation(game_context, frame_context);
+    // -- gameplay only runs in play, the edited level is restored when returning to edit
+    if game_mode::is_playing(game_context) {
+        entity_animation::update_animation(game_context, frame_context);
+        entity_rigid_body::update_rigid_bodies(game_context, frame_context);
+        character_controller::update_character_controller(game_context, frame_context);
+    }
     update_entity_bvh_entries(game_context, frame_context);
+    update_entity_overlap_pairs(game_context, frame_context);
+    entity_trigger::update_triggers(game_context, frame_context);
 
     // -- debug updates
     debug_ui::update_debug_main_menu(game_context, frame_context);
     debug_ui::update_debug_entity_menu(game_context, frame_context);
+    debug_ui::update_debug_entity_properties(game_context, frame_context);
+    debug_ui::update_debug_outliner(game_context, frame_context);
     debug_ui::update_draw_entity_bvh(game_context, frame_context);
+    debug_ui::update_debug_draw_entity_colliding(game_context, frame_context);
+    entity_trigger::update_draw_triggers(game_context, frame_context);
 
     frame_context.finalize_ui();
 
@@ -79,8 +100,7 @@ fn update_frame(game_context: &SGameContext, frame_context: &mut SFrameContext)
 }
 
 pub fn update_entity_bvh_entries(game_context: &SGameContext, _frame_context: &SFrameContext) {
-    /*
-    game_context.data_bucket.get::<bvh::STree<entity::SEntityHandle>>()
+    game_context.data_bucket.get::<databucket::SEntityBVH>()
         .and::<entity_model::SBucket>()
         .and::<SEntityBucket>()
         .and::<render::SRender>()
@@ -89,19 +109,16 @@ pub fn update_entity_bvh_entries(game_context: &SGameContext, _frame_context: &S
                 let model_handle : entity_model::SHandle = i;
 
                 let entity_handle = entity_model.get_entity(model_handle);
-                if entities.get_location_update_frame(entity_handle) != game_context.cur_frame {
+                let bvh_entry = entity_model.get_bvh_entry(model_handle);
+                if bvh_entry.is_some() && entities.get_location_update_frame(entity_handle) != game_context.cur_frame {
                     continue;
                 }
 
-                let mesh = entity_model.get_model(model_handle).mesh;
-                let identity_aabb = render.mesh_loader().get_mesh_local_aabb(mesh);
-
-                let location = entities.get_entity_location(entity_handle);
-
-                let transformed_aabb = utils::SAABB::transform(&identity_aabb, &location);
+                let transformed_aabb = entity_model.world_aabb(model_handle, entities, render);
 
-                if let Some(bvh_entry) = entity_model.get_bvh_entry(model_handle) {
-                    bvh.update_entry(bvh_entry, &transformed_aabb);
+                if let Some(bvh_entry) = bvh_entry {
+                    // -- assume it keeps moving like it did this frame
+                    bvh.update_entry_with_displacement(bvh_entry, &transformed_aabb, &entities.get_frame_displacement(entity_handle));
                 }
                 else {
                     let new_bvh_handle = bvh.insert(entity_handle, &transformed_aabb, None)
@@ -110,7 +127,16 @@ pub fn update_entity_bvh_entries(game_context: &SGameContext, _frame_context: &S
                 }
             }
         });
-    */
+}
+
+pub fn update_entity_overlap_pairs(game_context: &SGameContext, _frame_context: &SFrameContext) {
+    game_context.data_bucket.get::<databucket::SEntityPairs>()
+        .and::<databucket::SEntityBVH>()
+        .with_mc(|pairs, bvh| {
+            if let Err(e) = pairs.update(bvh) {
+                println!("ERROR: entity overlap pairs: {}", e);
+            }
+        });
 }
 
 fn main_d3d12(d3d_debug: bool) -> Result<(), &'static str> {
@@ -146,8 +172,14 @@ fn main_d3d12(d3d_debug: bool) -> Result<(), &'static str> {
     game_context.data_bucket.add(render);
     game_context.data_bucket.add(entity_model::SBucket::new(&SYSTEM_ALLOCATOR(), 1024)?);
     game_context.data_bucket.add(entity_animation::SBucket::new(&SYSTEM_ALLOCATOR(), 1024)?);
-    game_context.data_bucket.add(bvh::STree::new());
+    game_context.data_bucket.add(entity_rigid_body::SBucket::new(&SYSTEM_ALLOCATOR(), 1024)?);
+    game_context.data_bucket.add(entity_trigger::SBucket::new(&SYSTEM_ALLOCATOR(), 1024)?);
+    let mut entity_bvh = databucket::SEntityBVH::new();
+    entity_bvh.set_fat_bounds_settings(bvh::SFatBoundsSettings{ margin: 0.1, displacement_multiplier: 2.0 });
+    game_context.data_bucket.add(entity_bvh);
+    game_context.data_bucket.add(databucket::SEntityPairs::new(&SYSTEM_ALLOCATOR(), 4096)?);
     game_context.data_bucket.add(camera::SDebugFPCamera::new(Vec3::new(0.0, 0.0, -10.0)));
+    game_context.data_bucket.add(character_controller::SCharacterController::new(Vec3::new(0.0, 0.0, -10.0)));
     game_context.data_bucket.add(input::SInput::new());
     game_context.data_bucket.add(gjk::SGJKDebug::new(&game_context.data_bucket));
 
@@ -227,6 +259,15 @@ fn main_d3d12(d3d_debug: bool) -> Result<(), &'static str> {
                             safewindows::EMsgType::MButtonUp{ .. } => {
                                 input_handler.handle_mmouse_down_up(false);
                             },
+                            safewindows::EMsgType::RButtonDown{ .. } => {
+                                input_handler.handle_rmouse_down_up(true);
+                            },
+                            safewindows::EMsgType::RButtonUp{ .. } => {
+                                input_handler.handle_rmouse_down_up(false);
+                            },
+                            safewindows::EMsgType::MouseWheel{ delta } => {
+                                input_handler.handle_mouse_wheel(delta);
+                            },
                             safewindows::EMsgType::Input{ raw_input } => {
                                 if let safewindows::rawinput::ERawInputData::Mouse{data} = raw_input.data {
                                     input_handler.handle_mouse_move(data.last_x, data.last_y);
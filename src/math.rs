@@ -12,7 +12,7 @@ pub struct Vec2{
     pub y: f32,
 }
 
-#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[derive(Copy, Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
 pub struct Vec3{
     pub x: f32,
     pub y: f32,
@@ -66,6 +66,11 @@ impl Vec3 {
         self.sqmag().sqrt()
     }
 
+    pub fn normalized(&self) -> Self {
+        let mag = self.mag();
+        Self::new(self.x / mag, self.y / mag, self.z / mag)
+    }
+
     pub fn dot(a: &Self, b: &Self) -> f32 {
         a.x * b.x + a.y * b.y + a.z * b.z
     }
@@ -377,6 +382,34 @@ impl Quat {
         }
     }
 
+    // -- euler angles in radians, as rotation around x, then y, then z
+    pub fn new_from_euler(euler: &Vec3) -> Self {
+        let (sx, cx) = (euler.x / 2.0).sin_cos();
+        let (sy, cy) = (euler.y / 2.0).sin_cos();
+        let (sz, cz) = (euler.z / 2.0).sin_cos();
+
+        Self::new(
+            sx * cy * cz - cx * sy * sz,
+            cx * sy * cz + sx * cy * sz,
+            cx * cy * sz - sx * sy * cz,
+            cx * cy * cz + sx * sy * sz,
+        )
+    }
+
+    pub fn to_euler(&self) -> Vec3 {
+        let x = (2.0 * (self.w * self.x + self.y * self.z))
+            .atan2(1.0 - 2.0 * (self.x * self.x + self.y * self.y));
+
+        // -- clamp to avoid NaN at the gimbal lock poles
+        let sin_y = utils::clamp(2.0 * (self.w * self.y - self.z * self.x), -1.0, 1.0);
+        let y = sin_y.asin();
+
+        let z = (2.0 * (self.w * self.z + self.x * self.y))
+            .atan2(1.0 - 2.0 * (self.y * self.y + self.z * self.z));
+
+        Vec3::new(x, y, z)
+    }
+
     pub fn inverse(&self) -> Self {
         let self_glm_t = self as *const Quat as *const glm::Quat;
         unsafe {
@@ -653,4 +686,20 @@ pub fn validate_glm_compatibility() {
             assert!(*my_mat4_as_glm_mat4 == glm_mat4);
         }
     }
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_quat_euler_round_trip() {
+    let euler = Vec3::new(0.3, -0.7, 1.2);
+    let q = Quat::new_from_euler(&euler);
+    let round_trip = q.to_euler();
+
+    assert!((round_trip - euler).mag() < 0.0001);
+
+    // -- should match composing the individual axis rotations
+    let composed = Quat::new_angle_axis(euler.z, &Vec3::new(0.0, 0.0, 1.0)) *
+                   Quat::new_angle_axis(euler.y, &Vec3::new(0.0, 1.0, 0.0)) *
+                   Quat::new_angle_axis(euler.x, &Vec3::new(1.0, 0.0, 0.0));
+    let v = Vec3::new(1.0, 2.0, 3.0);
+    assert!((Quat::rotate_vec3(&q, &v) - Quat::rotate_vec3(&composed, &v)).mag() < 0.0001);
+}
@@ -1,6 +1,6 @@
 use std::cell::RefCell;
 use std::ops::{Deref, DerefMut};
-use std::rc::Weak;
+use std::rc::{Rc, Weak};
 
 use crate::math::{Vec4, Vec3, Vec2, Mat4, Quat};
 use arrayvec::{ArrayString};
@@ -9,6 +9,7 @@ use gltf;
 use crate::t12;
 use crate::n12;
 use crate::n12::descriptorallocator::{descriptor_alloc};
+use crate::quickhull;
 use crate::allocate::{SYSTEM_ALLOCATOR, STACK_ALLOCATOR};
 use crate::collections;
 use crate::collections::{SStoragePool, SVec};
@@ -17,6 +18,7 @@ use crate::render::shaderbindings;
 use crate::render::shaderbindings::types;
 use crate::rustywindows;
 use crate::string_db::{hash_str, SHashedStr};
+use crate::trimesh;
 use crate::utils;
 use crate::utils::{STransform, gltf_accessor_slice};
 
@@ -46,6 +48,10 @@ pub struct SMesh {
     pub(super) indices: SVec<u16>,
 
     local_aabb: utils::SAABB,
+    // -- collision queries use this instead of every render vertex
+    local_hull: quickhull::SHull,
+    // -- for ray picking and collision against the actual triangles, None if it was over budget
+    triangle_mesh: Option<Rc<trimesh::STriangleMesh>>,
 
     verts_buf: n12::SBindlessBufferResourceSlice<Vec3>,
     normals_buf: n12::SBindlessBufferResourceSlice<Vec3>,
@@ -64,6 +70,17 @@ pub struct STexture {
     pub(super) diffuse_texture_srv: Option<n12::descriptorallocator::SDescriptorAllocatorAllocation>,
 }
 
+// -- closest triangle of a mesh hit by a ray
+#[derive(Clone, Copy, Debug)]
+pub struct SMeshRayHit {
+    pub t: f32,
+    pub triangle: usize,
+    // -- unit face normal in ray space
+    pub normal: Vec3,
+    // -- weights of the triangle's three verts at the hit point
+    pub barycentric: Vec3,
+}
+
 pub struct SMeshLoader {
     device: Weak<n12::SDevice>,
     copy_command_list_pool: n12::SCommandListPool,
@@ -71,6 +88,7 @@ pub struct SMeshLoader {
     cbv_srv_uav_heap: Weak<n12::descriptorallocator::SDescriptorAllocator>,
 
     mesh_pool: SStoragePool<SMesh, u16, u16>,
+    triangle_mesh_bytes: usize,
 
     // -- these are shared between all meshes, bound for all draws, and bindlessly indexed into
 
@@ -82,6 +100,13 @@ pub struct SMeshLoader {
 }
 pub type SMeshHandle = collections::SPoolHandle<u16, u16>;
 
+const MESH_HULL_SETTINGS : quickhull::SQuickHullSettings = quickhull::SQuickHullSettings {
+    max_vertices: Some(64),
+};
+
+// -- memory all meshes' triangle meshes can take up together, meshes past this don't get one
+const TRIANGLE_MESH_BUDGET_BYTES : usize = 32 * 1024 * 1024;
+
 pub struct STextureLoader {
     device: Weak<n12::SDevice>,
     copy_command_list_pool: n12::SCommandListPool,
@@ -173,6 +198,7 @@ impl SMeshLoader {
             direct_command_list_pool: n12::SCommandListPool::create(device.upgrade().expect("bad device").deref(), direct_command_queue, &winapi.rawwinapi(), 1, 2)?,
             cbv_srv_uav_heap,
             mesh_pool: SStoragePool::create(&SYSTEM_ALLOCATOR(), max_mesh_count),
+            triangle_mesh_bytes: 0,
 
             local_verts_resource,
             local_normals_resource,
@@ -183,6 +209,26 @@ impl SMeshLoader {
 
     pub fn shutdown(&mut self) {
         self.mesh_pool.clear();
+        self.triangle_mesh_bytes = 0;
+    }
+
+    fn build_triangle_mesh(&mut self, verts: &[Vec3], indices: &[u16]) -> Option<Rc<trimesh::STriangleMesh>> {
+        let bytes = trimesh::STriangleMesh::bytes_for(verts.len(), indices.len());
+        if self.triangle_mesh_bytes + bytes > TRIANGLE_MESH_BUDGET_BYTES {
+            println!("Triangle mesh budget exhausted, queries on this mesh will test every triangle");
+            return None;
+        }
+
+        match trimesh::STriangleMesh::new(verts, indices) {
+            Ok(triangle_mesh) => {
+                self.triangle_mesh_bytes += bytes;
+                Some(Rc::new(triangle_mesh))
+            },
+            Err(e) => {
+                println!("Failed to build triangle mesh: {}", e);
+                None
+            }
+        }
     }
 
     fn sync_upload_bindless_buffer_resource<T>(
@@ -212,7 +258,7 @@ impl SMeshLoader {
         Ok(())
     }
 
-    pub fn get_or_create_mesh_gltf(&mut self, asset_file_path: &'static str, gltf_data: &gltf::Gltf) -> Result<SMeshHandle, &'static str> {
+    pub fn get_or_create_mesh_gltf(&mut self, asset_file_path: &str, gltf_data: &gltf::Gltf) -> Result<SMeshHandle, &'static str> {
         let uid = hash_str(asset_file_path);
 
         // -- $$$FRK(TODO): replace with some accelerated lookup structure
@@ -311,6 +357,8 @@ impl SMeshLoader {
         );
 
         let local_aabb = utils::SAABB::new_from_points(local_verts.as_slice());
+        let local_hull = quickhull::quickhull(local_verts.as_slice(), &MESH_HULL_SETTINGS);
+        let triangle_mesh = self.build_triangle_mesh(local_verts.as_slice(), indices.as_slice());
         //println!("Asset name: {}\nAABB: {:?}", asset_name, local_aabb);
 
         // -- load skeleton data
@@ -438,6 +486,8 @@ impl SMeshLoader {
             indices,
 
             local_aabb,
+            local_hull,
+            triangle_mesh,
 
             skinning,
         };
@@ -445,7 +495,7 @@ impl SMeshLoader {
         return self.mesh_pool.insert_val(mesh)
     }
 
-    pub fn get_or_create_mesh_obj(&mut self, asset_name: &'static str, tobj_mesh: &tobj::Mesh) -> Result<SMeshHandle, &'static str> {
+    pub fn get_or_create_mesh_obj(&mut self, asset_name: &str, tobj_mesh: &tobj::Mesh) -> Result<SMeshHandle, &'static str> {
         let uid = hash_str(asset_name);
 
         // -- $$$FRK(TODO): replace with some accelerated lookup structure
@@ -509,6 +559,8 @@ impl SMeshLoader {
         let indices_ibv = indices_resource.raw.create_index_buffer_view(t12::EDXGIFormat::R16UINT)?;
 
         let local_aabb = utils::SAABB::new_from_points(local_verts.as_slice());
+        let local_hull = quickhull::quickhull(local_verts.as_slice(), &MESH_HULL_SETTINGS);
+        let triangle_mesh = self.build_triangle_mesh(local_verts.as_slice(), indices.as_slice());
 
         let mesh = SMesh{
             uid: uid,
@@ -519,6 +571,8 @@ impl SMeshLoader {
             indices,
 
             local_aabb,
+            local_hull,
+            triangle_mesh,
 
             local_verts_resource,
             local_normals_resource,
@@ -536,16 +590,63 @@ impl SMeshLoader {
         return self.mesh_pool.insert_val(mesh)
     }
 
+    // -- the path the mesh was loaded from, meshes are keyed on it
+    pub fn get_mesh_asset_path(&self, mesh: SMeshHandle) -> &str {
+        let mesh = self.mesh_pool.get(mesh).unwrap();
+        unsafe{ mesh.uid._debug_ptr.as_ref().unwrap() }
+    }
+
     pub fn get_mesh_local_aabb(&self, mesh: SMeshHandle) -> &utils::SAABB {
         let mesh = self.mesh_pool.get(mesh).unwrap();
         &mesh.local_aabb
     }
 
+    pub fn get_mesh_local_hull(&self, mesh: SMeshHandle) -> &quickhull::SHull {
+        let mesh = self.mesh_pool.get(mesh).unwrap();
+        &mesh.local_hull
+    }
+
+    pub fn get_mesh_triangle_mesh(&self, mesh: SMeshHandle) -> Option<&Rc<trimesh::STriangleMesh>> {
+        let mesh = self.mesh_pool.get(mesh).unwrap();
+        mesh.triangle_mesh.as_ref()
+    }
+
+    // -- every triangle of mesh overlapping aabb, with both in the space mesh_to_space maps to
+    pub fn gather_triangles_in_aabb(
+        &self,
+        mesh: SMeshHandle,
+        aabb: &utils::SAABB,
+        mesh_to_space: &STransform,
+        out: &mut Vec<[Vec3; 3]>,
+    ) {
+        let mesh = self.mesh_pool.get(mesh).unwrap();
+        if let Some(triangle_mesh) = &mesh.triangle_mesh {
+            triangle_mesh.triangles_in_aabb(aabb, mesh_to_space, out);
+            return;
+        }
+
+        for tri_indices in mesh.indices.as_slice().chunks(3) {
+            let tri = [
+                mesh_to_space.mul_point(&mesh.local_verts[tri_indices[0] as usize]),
+                mesh_to_space.mul_point(&mesh.local_verts[tri_indices[1] as usize]),
+                mesh_to_space.mul_point(&mesh.local_verts[tri_indices[2] as usize]),
+            ];
+            if utils::SAABB::overlaps(aabb, &utils::SAABB::new_from_points(&tri)) {
+                out.push(tri);
+            }
+        }
+    }
+
     pub fn get_mesh_local_vertices(&self, mesh: SMeshHandle) -> &SVec<Vec3> {
         let mesh = self.mesh_pool.get(mesh).unwrap();
         &mesh.local_verts
     }
 
+    pub fn get_mesh_local_indices(&self, mesh: SMeshHandle) -> &SVec<u16> {
+        let mesh = self.mesh_pool.get(mesh).unwrap();
+        &mesh.indices
+    }
+
     pub fn get_mesh_local_normals(&self, mesh: SMeshHandle) -> &SVec<Vec3> {
         let mesh = self.mesh_pool.get(mesh).unwrap();
         &mesh.local_normals
@@ -574,12 +675,33 @@ impl SMeshLoader {
         ray_dir: &Vec3,
         model_to_ray_space: &STransform,
     ) -> Option<f32> {
+        let ray = utils::SRay{ origin: *ray_origin, dir: *ray_dir };
+        self.cast_ray(mesh, &ray, model_to_ray_space).map(|hit| hit.t)
+    }
+
+    // -- closest triangle hit by ray, which is in the space model_to_ray_space maps to
+    pub fn cast_ray(
+        &self,
+        mesh: SMeshHandle,
+        ray: &utils::SRay,
+        model_to_ray_space: &STransform,
+    ) -> Option<SMeshRayHit> {
         let mesh = self.mesh_pool.get(mesh).unwrap();
 
+        if let Some(triangle_mesh) = &mesh.triangle_mesh {
+            return triangle_mesh.cast_ray(ray, model_to_ray_space).map(|hit| SMeshRayHit {
+                t: hit.t,
+                triangle: hit.triangle as usize,
+                normal: hit.normal,
+                barycentric: hit.barycentric,
+            });
+        }
+
+        // -- no triangle mesh, so test every triangle
         break_assert!(mesh.indices.len() % 3 == 0);
         let num_tris = mesh.indices.len() / 3;
 
-        let mut min_t = None;
+        let mut result : Option<SMeshRayHit> = None;
 
         for ti in 0..num_tris {
             let ti_vi_0 = mesh.indices[ti * 3 + 0];
@@ -594,26 +716,29 @@ impl SMeshLoader {
             let v1_ray_space_pos = model_to_ray_space.mul_point(&v1_pos);
             let v2_ray_space_pos = model_to_ray_space.mul_point(&v2_pos);
 
-            if let Some(t) = utils::ray_intersects_triangle(
-                &ray_origin,
-                &ray_dir,
+            if let Some((t, barycentric)) = utils::ray_intersects_triangle_barycentric(
+                &ray.origin,
+                &ray.dir,
                 &v0_ray_space_pos,
                 &v1_ray_space_pos,
                 &v2_ray_space_pos,
             ) {
-
-                if let Some(cur_min_t) = min_t {
-                    if t < cur_min_t {
-                        min_t = Some(t);
-                    }
-                }
-                else {
-                    min_t = Some(t);
+                if t < result.map_or(std::f32::MAX, |r| r.t) {
+                    let normal = Vec3::cross(
+                        &(v1_ray_space_pos - v0_ray_space_pos),
+                        &(v2_ray_space_pos - v0_ray_space_pos),
+                    ).normalized();
+                    result = Some(SMeshRayHit {
+                        t,
+                        triangle: ti,
+                        normal,
+                        barycentric,
+                    });
                 }
             }
         }
 
-        return min_t;
+        result
     }
 
     pub fn vertex_count(&self, mesh_handle: SMeshHandle) -> usize {
@@ -923,7 +1048,7 @@ impl SMeshInstanceLoader {
 
     pub fn new_from_obj(
         &mut self,
-        obj_file: &'static str,
+        obj_file: &str,
         mesh_loader: &mut SMeshLoader,
         texture_loader: &mut STextureLoader,
         diffuse_weight: f32,
@@ -970,7 +1095,7 @@ impl SMeshInstanceLoader {
 
     pub fn new_from_gltf(
         &mut self,
-        gltf_path: &'static str,
+        gltf_path: &str,
         mesh_loader: &mut SMeshLoader,
         _texture_loader: &mut STextureLoader,
         diffuse_weight: f32,
@@ -1067,6 +1192,14 @@ impl SMeshInstanceLoader {
         })
     }
 
+    pub fn get_mesh(&self, handle: SMeshInstanceHandle) -> SMeshHandle {
+        self.instance_pool.get(handle).expect("querying invalid mesh instance").mesh
+    }
+
+    pub fn is_pickable(&self, handle: SMeshInstanceHandle) -> bool {
+        self.instance_pool.get(handle).expect("querying invalid mesh instance").pickable
+    }
+
     pub fn set_diffuse_colour(&mut self, handle: SMeshInstanceHandle, colour: &Vec4) {
         match self.instance_pool.get_mut(handle) {
             Ok(mesh_instance) => {
@@ -0,0 +1,1105 @@
+use std::rc::Rc;
+
+use arrayvec::{ArrayVec};
+
+use crate::allocate::{SYSTEM_ALLOCATOR};
+use crate::bvh;
+use crate::collections::{SPoolHandle, SStoragePool};
+use crate::collision::{SCollisionFilter};
+use crate::gjk::{SGJKSettings, EGJKResult, EEPAResult, SPenetration, gjk_intersection, epa};
+use crate::math::{Vec3, Quat};
+use crate::quickhull;
+use crate::safewindows;
+use crate::shapes::{TConvexShape, SSphere, SBox, SCapsule, STransformedShape, shape_aabb};
+use crate::trimesh::{STriangleMesh};
+use crate::utils::{SAABB, STransform};
+
+// -- rigid body dynamics. Semi-implicit euler on a fixed step, with a sequential impulse solver
+// -- running over persistent contact manifolds that are fed one GJK/EPA contact per step.
+// -- solver follows Erin Catto - "Iterative Dynamics with Temporal Coherence" and Box2D Lite,
+// -- manifold upkeep follows Bullet's btPersistentManifold
+
+pub type SBodyHandle = SPoolHandle<u16, u16>;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum EBodyType {
+    Static,
+    Dynamic,
+}
+
+// -- in the body's local space, centered on its centre of mass
+#[derive(Clone, Debug)]
+pub enum ECollisionShape {
+    Sphere(SSphere),
+    Box(SBox),
+    Capsule(SCapsule),
+    Hull(Vec<Vec3>),
+    // -- static bodies only, tested against a triangle at a time. Shared with the mesh it came from,
+    // -- with the scale applied at query time
+    TriangleMesh{ mesh: Rc<STriangleMesh>, scale: f32 },
+}
+
+#[derive(Clone, Debug)]
+pub struct SRigidBodyDesc {
+    pub body_type: EBodyType,
+    pub shape: ECollisionShape,
+    pub mass: f32,
+    pub friction: f32,
+    pub restitution: f32,
+    pub filter: SCollisionFilter,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct SPhysicsSettings {
+    pub gravity: Vec3,
+    pub fixed_dt: f32,
+    // -- time past this many steps in one update is dropped, so a long frame can't snowball
+    pub max_steps_per_update: usize,
+    pub solver_iterations: usize,
+    // -- fraction of the penetration past penetration_slop that is pushed out each step
+    pub baumgarte: f32,
+    pub penetration_slop: f32,
+    // -- contacts approaching slower than this don't bounce, so resting bodies settle
+    pub restitution_threshold: f32,
+    pub linear_damping: f32,
+    pub angular_damping: f32,
+    // -- an island goes to sleep once all its bodies have been under these speeds for time_to_sleep
+    pub sleep_linear_speed: f32,
+    pub sleep_angular_speed: f32,
+    pub time_to_sleep: f32,
+}
+
+pub struct SRigidBody {
+    body_type: EBodyType,
+    shape: ECollisionShape,
+
+    position: Vec3,
+    rotation: Quat,
+    linear_velocity: Vec3,
+    angular_velocity: Vec3,
+
+    inv_mass: f32,
+    // -- diagonal of the inverse inertia tensor, exact for the primitives and approximate for hulls
+    inv_inertia_local: Vec3,
+
+    friction: f32,
+    restitution: f32,
+    filter: SCollisionFilter,
+
+    sleep_time: f32,
+    asleep: bool,
+
+    bvh_entry: bvh::SNodeHandle,
+}
+
+#[derive(Copy, Clone)]
+struct SContactPoint {
+    local_a: Vec3,
+    local_b: Vec3,
+    depth: f32,
+
+    // -- accumulated over the step and kept for warm starting the next one
+    normal_impulse: f32,
+    tangent_impulse: [f32; 2],
+
+    // -- computed at the start of each solve
+    r_a: Vec3,
+    r_b: Vec3,
+    normal_mass: f32,
+    tangent_mass: [f32; 2],
+    velocity_bias: f32,
+}
+
+const MAX_MANIFOLD_POINTS : usize = 4;
+// -- points that separate or slide apart by more than this are dropped from the manifold
+const CONTACT_BREAKING_DIST : f32 = 0.02;
+
+struct SContactManifold {
+    body_a: SBodyHandle,
+    body_b: SBodyHandle,
+    // -- from A towards B
+    normal: Vec3,
+    tangents: [Vec3; 2],
+    points: ArrayVec<[SContactPoint; MAX_MANIFOLD_POINTS]>,
+
+    friction: f32,
+    restitution: f32,
+}
+
+// -- copy of what the solver needs from a body, so both sides of a contact can be written at once
+#[derive(Copy, Clone)]
+struct SSolverBody {
+    position: Vec3,
+    rotation: Quat,
+    linear_velocity: Vec3,
+    angular_velocity: Vec3,
+    inv_mass: f32,
+    inv_inertia_local: Vec3,
+}
+
+pub struct SPhysicsWorld {
+    pub settings: SPhysicsSettings,
+
+    bodies: SStoragePool<SRigidBody, u16, u16>,
+    bvh: bvh::STree<SBodyHandle>,
+    pairs: bvh::SPairTracker<SBodyHandle>,
+    manifolds: Vec<SContactManifold>,
+
+    accumulator: f32,
+}
+
+impl Default for SPhysicsSettings {
+    fn default() -> Self {
+        Self {
+            gravity: Vec3::new(0.0, -9.8, 0.0),
+            fixed_dt: 1.0 / 60.0,
+            max_steps_per_update: 4,
+            solver_iterations: 10,
+            baumgarte: 0.2,
+            penetration_slop: 0.005,
+            restitution_threshold: 1.0,
+            linear_damping: 0.05,
+            angular_damping: 0.1,
+            sleep_linear_speed: 0.05,
+            sleep_angular_speed: 0.05,
+            time_to_sleep: 0.5,
+        }
+    }
+}
+
+impl TConvexShape for ECollisionShape {
+    fn support(&self, dir: &Vec3) -> Vec3 {
+        match self {
+            Self::Sphere(sphere) => sphere.support(dir),
+            Self::Box(b) => b.support(dir),
+            Self::Capsule(capsule) => capsule.support(dir),
+            Self::Hull(verts) => verts.as_slice().support(dir),
+            // -- support of the whole mesh's hull, only good for bounds
+            Self::TriangleMesh{ mesh, scale } => *scale * mesh.verts().support(dir),
+        }
+    }
+}
+
+impl ECollisionShape {
+    // -- diagonal of the inertia tensor for a solid shape of uniform density
+    pub fn inertia(&self, mass: f32) -> Vec3 {
+        let box_inertia = |half_extents: &Vec3| {
+            let (x2, y2, z2) = (half_extents.x * half_extents.x, half_extents.y * half_extents.y, half_extents.z * half_extents.z);
+            Vec3::new(mass * (y2 + z2) / 3.0, mass * (x2 + z2) / 3.0, mass * (x2 + y2) / 3.0)
+        };
+
+        match self {
+            Self::Sphere(sphere) => {
+                let i = 0.4 * mass * sphere.radius * sphere.radius;
+                Vec3::new(i, i, i)
+            },
+            Self::Box(b) => box_inertia(&b.half_extents),
+            Self::Capsule(capsule) => {
+                // -- split the mass between the cylinder and the two hemispheres by volume
+                let r = capsule.radius;
+                let h = capsule.half_height;
+                let cylinder_volume = std::f32::consts::PI * r * r * 2.0 * h;
+                let sphere_volume = 4.0 / 3.0 * std::f32::consts::PI * r * r * r;
+                let cylinder_mass = mass * cylinder_volume / (cylinder_volume + sphere_volume);
+                let sphere_mass = mass - cylinder_mass;
+
+                let axial = cylinder_mass * r * r / 2.0 + sphere_mass * 0.4 * r * r;
+                let transverse = cylinder_mass * (h * h / 3.0 + r * r / 4.0)
+                    + sphere_mass * (0.4 * r * r + h * h + 0.75 * h * r);
+                Vec3::new(transverse, axial, transverse)
+            },
+            Self::Hull(verts) => {
+                match hull_inertia(verts.as_slice(), mass) {
+                    Some(inertia) => inertia,
+                    None => {
+                        // -- a flat hull has no volume to integrate, fall back to its bounding box
+                        let aabb = SAABB::new_from_points(verts.as_slice());
+                        box_inertia(&(0.5 * (aabb.max - aabb.min)))
+                    },
+                }
+            },
+            Self::TriangleMesh{..} => {
+                break_assert!(false); // -- triangle meshes can't be dynamic
+                Vec3::zero()
+            },
+        }
+    }
+}
+
+// -- sums the tetrahedra from the origin to each hull face, then shifts the second moments to the
+// -- centroid. Products of inertia are dropped, the body only keeps the diagonal.
+// -- follows Jonathan Blow & Atman Binstock - "How to find the inertia tensor (or other mass properties) of a 3D solid body represented by a triangle mesh"
+fn hull_inertia(verts: &[Vec3], mass: f32) -> Option<Vec3> {
+    let hull = quickhull::quickhull(verts, &quickhull::SQuickHullSettings::default());
+
+    let mut volume = 0.0;
+    let mut weighted_centroid = Vec3::zero();
+    // -- integral of x_i * x_j over the solid
+    let mut covariance = [[0.0f32; 3]; 3];
+    for face in &hull.faces {
+        let a = hull.verts[face.verts[0]];
+        let b = hull.verts[face.verts[1]];
+        let c = hull.verts[face.verts[2]];
+
+        let det = Vec3::dot(&a, &Vec3::cross(&b, &c));
+        let sum = a + b + c;
+
+        volume += det / 6.0;
+        weighted_centroid = weighted_centroid + (det / 24.0) * sum;
+        for i in 0..3 {
+            for j in 0..3 {
+                covariance[i][j] += det / 120.0 * (a[i] * a[j] + b[i] * b[j] + c[i] * c[j] + sum[i] * sum[j]);
+            }
+        }
+    }
+
+    // -- flat hulls are two sided, so their faces cancel out
+    let aabb = SAABB::new_from_points(verts);
+    let extents = aabb.max - aabb.min;
+    if volume <= 0.0001 * extents.x * extents.y * extents.z {
+        return None;
+    }
+
+    let centroid = (1.0 / volume) * weighted_centroid;
+    for i in 0..3 {
+        covariance[i][i] -= volume * centroid[i] * centroid[i];
+    }
+
+    let density = mass / volume;
+    Some(Vec3::new(
+        density * (covariance[1][1] + covariance[2][2]),
+        density * (covariance[0][0] + covariance[2][2]),
+        density * (covariance[0][0] + covariance[1][1]),
+    ))
+}
+
+fn normalize_quat(q: &Quat) -> Quat {
+    let len = (q.x * q.x + q.y * q.y + q.z * q.z + q.w * q.w).sqrt();
+    Quat::new(q.x / len, q.y / len, q.z / len, q.w / len)
+}
+
+fn component_mul(a: &Vec3, b: &Vec3) -> Vec3 {
+    Vec3::new(a.x * b.x, a.y * b.y, a.z * b.z)
+}
+
+// -- two unit vectors perpendicular to n and each other
+fn tangent_basis(n: &Vec3) -> [Vec3; 2] {
+    let axis = if n.x.abs() < 0.57 { Vec3::new(1.0, 0.0, 0.0) } else { Vec3::new(0.0, 1.0, 0.0) };
+    let t0 = Vec3::cross(n, &axis).normalized();
+    let t1 = Vec3::cross(n, &t0);
+    [t0, t1]
+}
+
+impl SRigidBody {
+    pub fn body_type(&self) -> EBodyType {
+        self.body_type
+    }
+
+    pub fn is_dynamic(&self) -> bool {
+        self.body_type == EBodyType::Dynamic
+    }
+
+    pub fn position(&self) -> Vec3 {
+        self.position
+    }
+
+    pub fn rotation(&self) -> Quat {
+        self.rotation
+    }
+
+    pub fn linear_velocity(&self) -> Vec3 {
+        self.linear_velocity
+    }
+
+    pub fn angular_velocity(&self) -> Vec3 {
+        self.angular_velocity
+    }
+
+    pub fn is_asleep(&self) -> bool {
+        self.asleep
+    }
+
+    fn local_to_world(&self, p: &Vec3) -> Vec3 {
+        self.position + Quat::rotate_vec3(&self.rotation, p)
+    }
+
+    fn world_to_local(&self, p: &Vec3) -> Vec3 {
+        Quat::rotate_vec3(&self.rotation.inverse(), &(p - self.position))
+    }
+
+    pub fn world_shape(&self) -> STransformedShape<&ECollisionShape> {
+        STransformedShape::new(&self.shape, STransform::new(&self.position, &self.rotation, 1.0))
+    }
+
+    pub fn world_aabb(&self) -> SAABB {
+        shape_aabb(&self.world_shape())
+    }
+
+    // -- asleep bodies are treated as static until their island wakes
+    fn solver_body(&self) -> SSolverBody {
+        let movable = self.is_dynamic() && !self.asleep;
+        SSolverBody {
+            position: self.position,
+            rotation: self.rotation,
+            linear_velocity: self.linear_velocity,
+            angular_velocity: self.angular_velocity,
+            inv_mass: if movable { self.inv_mass } else { 0.0 },
+            inv_inertia_local: if movable { self.inv_inertia_local } else { Vec3::zero() },
+        }
+    }
+
+    fn wake(&mut self) {
+        self.asleep = false;
+        self.sleep_time = 0.0;
+    }
+}
+
+impl SSolverBody {
+    fn inv_inertia_mul(&self, v: &Vec3) -> Vec3 {
+        let local = Quat::rotate_vec3(&self.rotation.inverse(), v);
+        Quat::rotate_vec3(&self.rotation, &component_mul(&self.inv_inertia_local, &local))
+    }
+
+    fn velocity_at(&self, r: &Vec3) -> Vec3 {
+        self.linear_velocity + Vec3::cross(&self.angular_velocity, r)
+    }
+
+    fn apply_impulse(&mut self, impulse: &Vec3, r: &Vec3) {
+        self.linear_velocity = self.linear_velocity + self.inv_mass * impulse;
+        self.angular_velocity = self.angular_velocity + self.inv_inertia_mul(&Vec3::cross(r, impulse));
+    }
+
+    // -- inverse of the effective mass seen by an impulse along dir at r
+    fn inv_effective_mass(&self, r: &Vec3, dir: &Vec3) -> f32 {
+        let angular = Vec3::cross(&self.inv_inertia_mul(&Vec3::cross(r, dir)), r);
+        self.inv_mass + Vec3::dot(&angular, dir)
+    }
+}
+
+impl SContactPoint {
+    fn new(local_a: Vec3, local_b: Vec3, depth: f32) -> Self {
+        Self {
+            local_a,
+            local_b,
+            depth,
+            normal_impulse: 0.0,
+            tangent_impulse: [0.0; 2],
+            r_a: Vec3::zero(),
+            r_b: Vec3::zero(),
+            normal_mass: 0.0,
+            tangent_mass: [0.0; 2],
+            velocity_bias: 0.0,
+        }
+    }
+}
+
+// -- rough area of the quad through four points, the largest of its diagonal cross products
+fn quad_area(p: [&Vec3; 4]) -> f32 {
+    let a = Vec3::cross(&(p[0] - p[1]), &(p[2] - p[3])).sqmag();
+    let b = Vec3::cross(&(p[0] - p[2]), &(p[1] - p[3])).sqmag();
+    let c = Vec3::cross(&(p[0] - p[3]), &(p[1] - p[2])).sqmag();
+    a.max(b).max(c)
+}
+
+impl SContactManifold {
+    fn new(body_a: SBodyHandle, body_b: SBodyHandle, a: &SRigidBody, b: &SRigidBody) -> Self {
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+        Self {
+            body_a,
+            body_b,
+            normal,
+            tangents: tangent_basis(&normal),
+            points: ArrayVec::new(),
+            friction: (a.friction * b.friction).sqrt(),
+            restitution: a.restitution.max(b.restitution),
+        }
+    }
+
+    // -- re-measure the existing points now that the bodies have moved, and drop any that have
+    // -- separated or slid too far to still be the same contact
+    fn refresh(&mut self, a: &SRigidBody, b: &SRigidBody) {
+        let normal = self.normal;
+        self.points.retain(|point| {
+            let diff = a.local_to_world(&point.local_a) - b.local_to_world(&point.local_b);
+            point.depth = Vec3::dot(&diff, &normal);
+            let tangential = diff - point.depth * normal;
+            point.depth > -CONTACT_BREAKING_DIST && tangential.sqmag() < CONTACT_BREAKING_DIST * CONTACT_BREAKING_DIST
+        });
+    }
+
+    fn add_point(&mut self, a: &SRigidBody, b: &SRigidBody, contact_a: &Vec3, contact_b: &Vec3, normal: &Vec3, depth: f32) {
+        // -- a big change in normal means the old points describe a different contact
+        if Vec3::dot(normal, &self.normal) < 0.95 {
+            self.points.clear();
+        }
+        self.normal = *normal;
+        self.tangents = tangent_basis(normal);
+
+        let new_point = SContactPoint::new(a.world_to_local(contact_a), b.world_to_local(contact_b), depth);
+
+        // -- replacing a nearby point keeps its impulses for warm starting
+        for point in self.points.iter_mut() {
+            if (a.local_to_world(&point.local_a) - contact_a).sqmag() < CONTACT_BREAKING_DIST * CONTACT_BREAKING_DIST {
+                point.local_a = new_point.local_a;
+                point.local_b = new_point.local_b;
+                point.depth = depth;
+                return;
+            }
+        }
+
+        if !self.points.is_full() {
+            self.points.push(new_point);
+            return;
+        }
+
+        // -- full, so keep the deepest point and replace whichever other point leaves the most area
+        let mut deepest = None;
+        let mut deepest_depth = depth;
+        for (i, point) in self.points.iter().enumerate() {
+            if point.depth > deepest_depth {
+                deepest_depth = point.depth;
+                deepest = Some(i);
+            }
+        }
+
+        let world_points : ArrayVec<[Vec3; MAX_MANIFOLD_POINTS]> = self.points.iter().map(|p| a.local_to_world(&p.local_a)).collect();
+        let mut best_replace = 0;
+        let mut best_area = -1.0;
+        for replace in 0..MAX_MANIFOLD_POINTS {
+            if Some(replace) == deepest {
+                continue;
+            }
+
+            let mut quad = [&world_points[0], &world_points[1], &world_points[2], &world_points[3]];
+            quad[replace] = contact_a;
+            let area = quad_area(quad);
+            if area > best_area {
+                best_area = area;
+                best_replace = replace;
+            }
+        }
+
+        self.points[best_replace] = new_point;
+    }
+}
+
+// -- triangle meshes aren't convex, so they go through the mesh's BVH a triangle at a time
+fn body_penetration(a: &SRigidBody, b: &SRigidBody, settings: &SGJKSettings) -> Option<SPenetration> {
+    match (&a.shape, &b.shape) {
+        (ECollisionShape::TriangleMesh{..}, ECollisionShape::TriangleMesh{..}) => None,
+        (_, ECollisionShape::TriangleMesh{ mesh, scale }) => {
+            let mesh_to_world = STransform::new(&b.position, &b.rotation, *scale);
+            mesh.convex_penetration(&a.world_shape(), &mesh_to_world, settings)
+        },
+        (ECollisionShape::TriangleMesh{ mesh, scale }, _) => {
+            // -- the mesh is B in the query, so flip the result back round
+            let mesh_to_world = STransform::new(&a.position, &a.rotation, *scale);
+            mesh.convex_penetration(&b.world_shape(), &mesh_to_world, settings).map(|p| SPenetration {
+                normal: -p.normal,
+                contact_a: p.contact_b,
+                contact_b: p.contact_a,
+                ..p
+            })
+        },
+        _ => {
+            let shape_a = a.world_shape();
+            let shape_b = b.world_shape();
+            match gjk_intersection(&shape_a, &shape_b, settings) {
+                EGJKResult::Intersection(simplex) => match epa(&shape_a, &shape_b, &simplex, settings) {
+                    EEPAResult::Penetration(p) | EEPAResult::DidNotConverge(p) => Some(p),
+                },
+                _ => None,
+            }
+        },
+    }
+}
+
+impl SPhysicsWorld {
+    pub fn new(max_bodies: u16, max_pairs: usize) -> Result<Self, &'static str> {
+        Ok(Self {
+            settings: SPhysicsSettings::default(),
+            bodies: SStoragePool::create(&SYSTEM_ALLOCATOR(), max_bodies),
+            bvh: {
+                let mut bvh = bvh::STree::new();
+                bvh.set_fat_bounds_settings(bvh::SFatBoundsSettings{ margin: 0.05, displacement_multiplier: 4.0 });
+                bvh
+            },
+            pairs: bvh::SPairTracker::new(&SYSTEM_ALLOCATOR(), max_pairs)?,
+            manifolds: Vec::new(),
+            accumulator: 0.0,
+        })
+    }
+
+    pub fn add_body(&mut self, desc: &SRigidBodyDesc, location: &STransform) -> Result<SBodyHandle, &'static str> {
+        let (inv_mass, inv_inertia_local) = match desc.body_type {
+            EBodyType::Static => (0.0, Vec3::zero()),
+            EBodyType::Dynamic => {
+                if desc.mass <= 0.0 {
+                    return Err("dynamic bodies need a positive mass");
+                }
+                if let ECollisionShape::TriangleMesh{..} = desc.shape {
+                    return Err("triangle mesh bodies must be static");
+                }
+                let inertia = desc.shape.inertia(desc.mass);
+                (1.0 / desc.mass, Vec3::new(1.0 / inertia.x, 1.0 / inertia.y, 1.0 / inertia.z))
+            },
+        };
+
+        let body = SRigidBody {
+            body_type: desc.body_type,
+            shape: desc.shape.clone(),
+            position: location.t,
+            rotation: location.r,
+            linear_velocity: Vec3::zero(),
+            angular_velocity: Vec3::zero(),
+            inv_mass,
+            inv_inertia_local,
+            friction: desc.friction,
+            restitution: desc.restitution,
+            filter: desc.filter,
+            sleep_time: 0.0,
+            asleep: false,
+            bvh_entry: bvh::SNodeHandle::default(),
+        };
+        let aabb = body.world_aabb();
+
+        let handle = self.bodies.insert_val(body)?;
+        let bvh_entry = self.bvh.insert(handle, &aabb, None)?;
+        self.bodies.get_mut(handle).unwrap().bvh_entry = bvh_entry;
+
+        Ok(handle)
+    }
+
+    pub fn remove_body(&mut self, handle: SBodyHandle) {
+        let bvh_entry = self.bodies.get(handle).expect("invalid body").bvh_entry;
+        self.bvh.remove(bvh_entry, true);
+        self.manifolds.retain(|m| m.body_a != handle && m.body_b != handle);
+        self.bodies.free(handle);
+    }
+
+    pub fn body(&self, handle: SBodyHandle) -> &SRigidBody {
+        self.bodies.get(handle).expect("invalid body")
+    }
+
+    // -- teleports the body, dropping its velocity
+    pub fn set_body_location(&mut self, handle: SBodyHandle, location: &STransform) {
+        let body = self.bodies.get_mut(handle).expect("invalid body");
+        body.position = location.t;
+        body.rotation = location.r;
+        body.linear_velocity = Vec3::zero();
+        body.angular_velocity = Vec3::zero();
+        body.wake();
+
+        // -- a teleport says nothing about where the body goes next
+        let aabb = body.world_aabb();
+        let bvh_entry = body.bvh_entry;
+        self.bvh.update_entry_with_displacement(bvh_entry, &aabb, &Vec3::zero());
+    }
+
+    pub fn set_linear_velocity(&mut self, handle: SBodyHandle, velocity: &Vec3) {
+        let body = self.bodies.get_mut(handle).expect("invalid body");
+        body.linear_velocity = *velocity;
+        body.wake();
+    }
+
+    pub fn set_angular_velocity(&mut self, handle: SBodyHandle, velocity: &Vec3) {
+        let body = self.bodies.get_mut(handle).expect("invalid body");
+        body.angular_velocity = *velocity;
+        body.wake();
+    }
+
+    // -- advances by as many fixed steps as fit in dt, returns how many were taken
+    pub fn update(&mut self, dt: f32) -> usize {
+        self.accumulator += dt;
+
+        let mut steps = 0;
+        while self.accumulator >= self.settings.fixed_dt {
+            if steps == self.settings.max_steps_per_update {
+                self.accumulator = 0.0;
+                break;
+            }
+
+            self.step(self.settings.fixed_dt);
+            self.accumulator -= self.settings.fixed_dt;
+            steps += 1;
+        }
+
+        steps
+    }
+
+    fn for_each_body_mut<F: FnMut(SBodyHandle, &mut SRigidBody)>(&mut self, mut f: F) {
+        for i in 0..self.bodies.max() {
+            let handle = self.bodies.handle_for_index(i).expect("bounded loop");
+            if let Some(body) = self.bodies.get_by_index_mut(i).expect("bounded loop") {
+                f(handle, body);
+            }
+        }
+    }
+
+    pub fn step(&mut self, dt: f32) {
+        let settings = self.settings;
+
+        // -- integrate forces
+        self.for_each_body_mut(|_, body| {
+            if body.is_dynamic() && !body.asleep {
+                body.linear_velocity = body.linear_velocity + dt * settings.gravity;
+                body.linear_velocity = (1.0 / (1.0 + dt * settings.linear_damping)) * body.linear_velocity;
+                body.angular_velocity = (1.0 / (1.0 + dt * settings.angular_damping)) * body.angular_velocity;
+            }
+        });
+
+        self.update_broadphase(dt);
+        self.update_narrowphase();
+        self.update_islands();
+        self.solve(dt);
+
+        // -- integrate velocities
+        self.for_each_body_mut(|_, body| {
+            if !body.is_dynamic() || body.asleep {
+                return;
+            }
+
+            body.position = body.position + dt * body.linear_velocity;
+
+            let angular_speed = body.angular_velocity.mag();
+            if angular_speed > std::f32::EPSILON {
+                let axis = (1.0 / angular_speed) * body.angular_velocity;
+                let delta = Quat::new_angle_axis(angular_speed * dt, &axis);
+                body.rotation = normalize_quat(&(delta * body.rotation));
+            }
+
+            let resting = body.linear_velocity.mag() < settings.sleep_linear_speed
+                && angular_speed < settings.sleep_angular_speed;
+            body.sleep_time = if resting { body.sleep_time + dt } else { 0.0 };
+        });
+    }
+
+    fn update_broadphase(&mut self, dt: f32) {
+        let bvh = &mut self.bvh;
+        for i in 0..self.bodies.max() {
+            if let Some(body) = self.bodies.get_by_index(i).expect("bounded loop") {
+                if body.is_dynamic() && !body.asleep {
+                    bvh.update_entry_with_displacement(body.bvh_entry, &body.world_aabb(), &(dt * body.linear_velocity));
+                }
+            }
+        }
+
+        // -- on overflow the tracker keeps last step's pairs, so existing contacts carry on
+        if let Err(e) = self.pairs.update(&self.bvh) {
+            println!("ERROR: physics broadphase: {}", e);
+        }
+
+        for (a, b) in self.pairs.ended() {
+            self.manifolds.retain(|m| !(m.body_a == *a && m.body_b == *b));
+        }
+
+        for (a, b) in self.pairs.began() {
+            let body_a = self.bodies.get(*a).expect("pairs only hold live bodies");
+            let body_b = self.bodies.get(*b).expect("pairs only hold live bodies");
+            // -- filtered pairs stay in the tracker, they just never get a manifold
+            if (body_a.is_dynamic() || body_b.is_dynamic()) && body_a.filter.collides_with(&body_b.filter) {
+                self.manifolds.push(SContactManifold::new(*a, *b, body_a, body_b));
+            }
+        }
+    }
+
+    fn update_narrowphase(&mut self) {
+        let gjk_settings = SGJKSettings {
+            max_iterations: 32,
+            tolerance: 0.001,
+        };
+
+        let bodies = &self.bodies;
+        for manifold in self.manifolds.iter_mut() {
+            let a = bodies.get(manifold.body_a).expect("manifolds only hold live bodies");
+            let b = bodies.get(manifold.body_b).expect("manifolds only hold live bodies");
+
+            let a_moving = a.is_dynamic() && !a.asleep;
+            let b_moving = b.is_dynamic() && !b.asleep;
+            if !a_moving && !b_moving {
+                continue;
+            }
+
+            manifold.refresh(a, b);
+
+            if let Some(p) = body_penetration(a, b, &gjk_settings) {
+                manifold.add_point(a, b, &p.contact_a, &p.contact_b, &p.normal, p.depth);
+            }
+        }
+    }
+
+    // -- bodies touching through dynamic bodies form an island, which sleeps and wakes as one
+    fn update_islands(&mut self) {
+        let max = self.bodies.max() as usize;
+        let mut island_parent : Vec<usize> = (0..max).collect();
+
+        fn find(parents: &mut Vec<usize>, mut i: usize) -> usize {
+            while parents[i] != i {
+                parents[i] = parents[parents[i]];
+                i = parents[i];
+            }
+            i
+        }
+
+        for manifold in &self.manifolds {
+            if manifold.points.is_empty() {
+                continue;
+            }
+
+            let a = self.bodies.get(manifold.body_a).unwrap();
+            let b = self.bodies.get(manifold.body_b).unwrap();
+            if a.is_dynamic() && b.is_dynamic() {
+                let root_a = find(&mut island_parent, manifold.body_a.index() as usize);
+                let root_b = find(&mut island_parent, manifold.body_b.index() as usize);
+                island_parent[root_a] = root_b;
+            }
+        }
+
+        // -- an island can sleep only if every body in it is ready to
+        let mut island_ready = vec![true; max];
+        for i in 0..max {
+            if let Some(body) = self.bodies.get_by_index(i as u16).unwrap() {
+                if body.is_dynamic() && !body.asleep && body.sleep_time < self.settings.time_to_sleep {
+                    let root = find(&mut island_parent, i);
+                    island_ready[root] = false;
+                }
+            }
+        }
+
+        for i in 0..max {
+            let root = find(&mut island_parent, i);
+            if let Some(body) = self.bodies.get_by_index_mut(i as u16).unwrap() {
+                if !body.is_dynamic() {
+                    continue;
+                }
+
+                if island_ready[root] {
+                    body.asleep = true;
+                    body.linear_velocity = Vec3::zero();
+                    body.angular_velocity = Vec3::zero();
+                }
+                else if body.asleep {
+                    body.wake();
+                }
+            }
+        }
+    }
+
+    fn solve(&mut self, dt: f32) {
+        let settings = self.settings;
+
+        let max = self.bodies.max() as usize;
+        let mut solver_bodies : Vec<Option<SSolverBody>> = Vec::with_capacity(max);
+        for i in 0..max {
+            solver_bodies.push(self.bodies.get_by_index(i as u16).unwrap().map(|body| body.solver_body()));
+        }
+
+        // -- both bodies of a manifold are live, so their solver bodies are always there
+        fn pair_mut(solver_bodies: &mut Vec<Option<SSolverBody>>, a: usize, b: usize) -> (&mut SSolverBody, &mut SSolverBody) {
+            assert!(a != b);
+            if a < b {
+                let (low, high) = solver_bodies.split_at_mut(b);
+                (low[a].as_mut().unwrap(), high[0].as_mut().unwrap())
+            }
+            else {
+                let (low, high) = solver_bodies.split_at_mut(a);
+                (high[0].as_mut().unwrap(), low[b].as_mut().unwrap())
+            }
+        }
+
+        // -- prepare contacts and warm start with last step's impulses
+        for manifold in self.manifolds.iter_mut() {
+            let (a, b) = pair_mut(&mut solver_bodies, manifold.body_a.index() as usize, manifold.body_b.index() as usize);
+            if a.inv_mass == 0.0 && b.inv_mass == 0.0 {
+                continue;
+            }
+
+            let normal = manifold.normal;
+            let tangents = manifold.tangents;
+            for point in manifold.points.iter_mut() {
+                let world_a = a.position + Quat::rotate_vec3(&a.rotation, &point.local_a);
+                let world_b = b.position + Quat::rotate_vec3(&b.rotation, &point.local_b);
+                let contact = 0.5 * (world_a + world_b);
+                point.r_a = contact - a.position;
+                point.r_b = contact - b.position;
+
+                point.normal_mass = 1.0 / (a.inv_effective_mass(&point.r_a, &normal) + b.inv_effective_mass(&point.r_b, &normal));
+                for t in 0..2 {
+                    point.tangent_mass[t] = 1.0 / (a.inv_effective_mass(&point.r_a, &tangents[t]) + b.inv_effective_mass(&point.r_b, &tangents[t]));
+                }
+
+                // -- push out penetration past the slop, and let separated points close their gap
+                point.velocity_bias = if point.depth > settings.penetration_slop {
+                    settings.baumgarte / dt * (point.depth - settings.penetration_slop)
+                }
+                else if point.depth < 0.0 {
+                    point.depth / dt
+                }
+                else {
+                    0.0
+                };
+
+                let approach_speed = -Vec3::dot(&(b.velocity_at(&point.r_b) - a.velocity_at(&point.r_a)), &normal);
+                if approach_speed > settings.restitution_threshold {
+                    point.velocity_bias = point.velocity_bias.max(manifold.restitution * approach_speed);
+                }
+
+                let impulse = point.normal_impulse * normal
+                    + point.tangent_impulse[0] * tangents[0]
+                    + point.tangent_impulse[1] * tangents[1];
+                a.apply_impulse(&-impulse, &point.r_a);
+                b.apply_impulse(&impulse, &point.r_b);
+            }
+        }
+
+        for _ in 0..settings.solver_iterations {
+            for manifold in self.manifolds.iter_mut() {
+                let (a, b) = pair_mut(&mut solver_bodies, manifold.body_a.index() as usize, manifold.body_b.index() as usize);
+                if a.inv_mass == 0.0 && b.inv_mass == 0.0 {
+                    continue;
+                }
+
+                let normal = manifold.normal;
+                let tangents = manifold.tangents;
+                let friction = manifold.friction;
+                for point in manifold.points.iter_mut() {
+                    // -- friction, bounded by the current normal impulse
+                    let max_friction = friction * point.normal_impulse;
+                    for t in 0..2 {
+                        let relative_velocity = b.velocity_at(&point.r_b) - a.velocity_at(&point.r_a);
+                        let lambda = -point.tangent_mass[t] * Vec3::dot(&relative_velocity, &tangents[t]);
+                        let old_impulse = point.tangent_impulse[t];
+                        point.tangent_impulse[t] = (old_impulse + lambda).max(-max_friction).min(max_friction);
+                        let impulse = (point.tangent_impulse[t] - old_impulse) * tangents[t];
+                        a.apply_impulse(&-impulse, &point.r_a);
+                        b.apply_impulse(&impulse, &point.r_b);
+                    }
+
+                    // -- non-penetration, the accumulated impulse can only push
+                    let relative_velocity = b.velocity_at(&point.r_b) - a.velocity_at(&point.r_a);
+                    let normal_speed = Vec3::dot(&relative_velocity, &normal);
+                    let lambda = point.normal_mass * (point.velocity_bias - normal_speed);
+                    let old_impulse = point.normal_impulse;
+                    point.normal_impulse = (old_impulse + lambda).max(0.0);
+                    let impulse = (point.normal_impulse - old_impulse) * normal;
+                    a.apply_impulse(&-impulse, &point.r_a);
+                    b.apply_impulse(&impulse, &point.r_b);
+                }
+            }
+        }
+
+        for i in 0..max {
+            if let (Some(body), Some(solver_body)) = (self.bodies.get_by_index_mut(i as u16).unwrap(), &solver_bodies[i]) {
+                if body.is_dynamic() && !body.asleep {
+                    body.linear_velocity = solver_body.linear_velocity;
+                    body.angular_velocity = solver_body.angular_velocity;
+                }
+            }
+        }
+    }
+}
+
+#[allow(dead_code)]
+fn test_world_with_floor() -> SPhysicsWorld {
+    let mut world = SPhysicsWorld::new(64, 256).unwrap();
+    let floor = SRigidBodyDesc {
+        body_type: EBodyType::Static,
+        shape: ECollisionShape::Box(SBox{ half_extents: Vec3::new(20.0, 1.0, 20.0) }),
+        mass: 0.0,
+        friction: 0.5,
+        restitution: 0.0,
+        filter: SCollisionFilter::default(),
+    };
+    world.add_body(&floor, &STransform::new_translation(&Vec3::new(0.0, -1.0, 0.0))).unwrap();
+    world
+}
+
+#[allow(dead_code)]
+fn test_dynamic_desc(shape: ECollisionShape, friction: f32, restitution: f32) -> SRigidBodyDesc {
+    SRigidBodyDesc {
+        body_type: EBodyType::Dynamic,
+        shape,
+        mass: 1.0,
+        friction,
+        restitution,
+        filter: SCollisionFilter::default(),
+    }
+}
+
+#[test]
+fn test_physics_box_lands_and_sleeps() {
+    let mut world = test_world_with_floor();
+    let cube = test_dynamic_desc(ECollisionShape::Box(SBox{ half_extents: Vec3::new(0.5, 0.5, 0.5) }), 0.5, 0.0);
+    let body = world.add_body(&cube, &STransform::new_translation(&Vec3::new(0.0, 3.0, 0.0))).unwrap();
+
+    for _ in 0..180 {
+        world.update(1.0 / 60.0);
+    }
+
+    let b = world.body(body);
+    assert!((b.position().y - 0.5).abs() < 0.05);
+    assert!(b.position().x.abs() < 0.05 && b.position().z.abs() < 0.05);
+    assert!(b.is_asleep());
+
+    // -- waking it up keeps it resting in place
+    world.set_linear_velocity(body, &Vec3::zero());
+    assert!(!world.body(body).is_asleep());
+    for _ in 0..60 {
+        world.update(1.0 / 60.0);
+    }
+    assert!((world.body(body).position().y - 0.5).abs() < 0.05);
+}
+
+#[test]
+fn test_physics_triangle_mesh_floor() {
+    // -- a floor made of a grid of triangles, scaled up by its body
+    let mut verts = Vec::new();
+    let mut indices = Vec::new();
+    for z in 0..=4 {
+        for x in 0..=4 {
+            verts.push(Vec3::new(x as f32 - 2.0, 0.0, z as f32 - 2.0));
+        }
+    }
+    for z in 0..4u16 {
+        for x in 0..4u16 {
+            let i = z * 5 + x;
+            indices.extend_from_slice(&[i, i + 5, i + 1, i + 1, i + 5, i + 6]);
+        }
+    }
+    let mesh = Rc::new(STriangleMesh::new(&verts, &indices).unwrap());
+
+    let mut world = SPhysicsWorld::new(64, 256).unwrap();
+    let mut floor = SRigidBodyDesc {
+        body_type: EBodyType::Static,
+        shape: ECollisionShape::TriangleMesh{ mesh: mesh.clone(), scale: 4.0 },
+        mass: 0.0,
+        friction: 0.5,
+        restitution: 0.0,
+        filter: SCollisionFilter::default(),
+    };
+    world.add_body(&floor, &STransform::default()).unwrap();
+
+    let cube = test_dynamic_desc(ECollisionShape::Box(SBox{ half_extents: Vec3::new(0.5, 0.5, 0.5) }), 0.5, 0.0);
+    let body = world.add_body(&cube, &STransform::new_translation(&Vec3::new(1.3, 3.0, -0.7))).unwrap();
+
+    for _ in 0..180 {
+        world.update(1.0 / 60.0);
+    }
+
+    let b = world.body(body);
+    assert!((b.position().y - 0.5).abs() < 0.05);
+    // -- it lands on one corner first, so it shuffles a little before settling
+    assert!((b.position().x - 1.3).abs() < 0.25 && (b.position().z + 0.7).abs() < 0.25);
+    assert!(b.is_asleep());
+
+    floor.body_type = EBodyType::Dynamic;
+    floor.mass = 1.0;
+    assert!(world.add_body(&floor, &STransform::default()).is_err());
+}
+
+#[test]
+fn test_physics_restitution_and_friction() {
+    // -- a bouncy ball comes back up most of the way
+    let mut world = test_world_with_floor();
+    let ball = test_dynamic_desc(ECollisionShape::Sphere(SSphere{ radius: 0.5 }), 0.5, 0.8);
+    let body = world.add_body(&ball, &STransform::new_translation(&Vec3::new(0.0, 3.0, 0.0))).unwrap();
+
+    let mut max_upward_speed : f32 = 0.0;
+    for _ in 0..120 {
+        world.update(1.0 / 60.0);
+        max_upward_speed = max_upward_speed.max(world.body(body).linear_velocity().y);
+    }
+    let impact_speed = (2.0 * 9.8 * 2.5 as f32).sqrt();
+    assert!(max_upward_speed > 0.6 * impact_speed);
+
+    // -- a sliding box stops after about v^2 / (2 * mu * g)
+    let mut world = test_world_with_floor();
+    let cube = test_dynamic_desc(ECollisionShape::Box(SBox{ half_extents: Vec3::new(0.5, 0.5, 0.5) }), 0.5, 0.0);
+    let body = world.add_body(&cube, &STransform::new_translation(&Vec3::new(0.0, 0.5, 0.0))).unwrap();
+    world.set_linear_velocity(body, &Vec3::new(3.0, 0.0, 0.0));
+
+    for _ in 0..120 {
+        world.update(1.0 / 60.0);
+    }
+
+    let b = world.body(body);
+    let expected_dist = 9.0 / (2.0 * 0.5 * 9.8);
+    assert!((b.position().x - expected_dist).abs() < 0.3);
+    assert!(b.linear_velocity().mag() < 0.1);
+    assert!((b.position().y - 0.5).abs() < 0.05);
+}
+
+#[test]
+fn test_physics_stack_island() {
+    let mut world = test_world_with_floor();
+    let cube = test_dynamic_desc(ECollisionShape::Box(SBox{ half_extents: Vec3::new(0.5, 0.5, 0.5) }), 0.6, 0.0);
+    let bottom = world.add_body(&cube, &STransform::new_translation(&Vec3::new(0.0, 0.5, 0.0))).unwrap();
+    let top = world.add_body(&cube, &STransform::new_translation(&Vec3::new(0.0, 1.5, 0.0))).unwrap();
+
+    for _ in 0..240 {
+        world.update(1.0 / 60.0);
+    }
+
+    assert!((world.body(top).position().y - 1.5).abs() < 0.1);
+    assert!(world.body(bottom).is_asleep() && world.body(top).is_asleep());
+
+    // -- knocking the top box wakes the whole stack
+    world.set_linear_velocity(top, &Vec3::new(0.5, 0.0, 0.0));
+    world.update(1.0 / 60.0);
+    assert!(!world.body(bottom).is_asleep());
+}
+
+#[test]
+fn test_physics_collision_filter() {
+    use crate::collision::{ECollisionLayer, COLLISION_MASK_ALL};
+
+    let mut world = test_world_with_floor();
+    let cube = test_dynamic_desc(ECollisionShape::Box(SBox{ half_extents: Vec3::new(0.5, 0.5, 0.5) }), 0.5, 0.0);
+    let mut ghost = cube.clone();
+    ghost.filter = SCollisionFilter::new(ECollisionLayer::Dynamic, COLLISION_MASK_ALL & !ECollisionLayer::Default.bit());
+
+    let solid_body = world.add_body(&cube, &STransform::new_translation(&Vec3::new(-2.0, 3.0, 0.0))).unwrap();
+    let ghost_body = world.add_body(&ghost, &STransform::new_translation(&Vec3::new(2.0, 3.0, 0.0))).unwrap();
+
+    for _ in 0..120 {
+        world.update(1.0 / 60.0);
+    }
+
+    // -- the floor is on the default layer, which the ghost's mask leaves out
+    assert!((world.body(solid_body).position().y - 0.5).abs() < 0.05);
+    assert!(world.body(ghost_body).position().y < -5.0);
+}
+
+#[test]
+fn test_physics_inertia() {
+    let sphere = ECollisionShape::Sphere(SSphere{ radius: 2.0 });
+    assert!((sphere.inertia(5.0).x - 8.0).abs() < 0.0001);
+
+    let b = ECollisionShape::Box(SBox{ half_extents: Vec3::new(1.0, 2.0, 3.0) });
+    let i = b.inertia(3.0);
+    assert!((i.x - 13.0).abs() < 0.0001 && (i.y - 10.0).abs() < 0.0001 && (i.z - 5.0).abs() < 0.0001);
+
+    // -- a capsule with no cylinder is a sphere
+    let capsule = ECollisionShape::Capsule(SCapsule{ half_height: 0.0, radius: 2.0 });
+    assert!((capsule.inertia(5.0).y - 8.0).abs() < 0.0001);
+
+    // -- a box hull away from the origin matches the box about its own centre
+    let mut box_verts = Vec::new();
+    for i in 0..8 {
+        let sign = |bit: usize| if i & bit != 0 { 1.0 } else { -1.0 };
+        box_verts.push(Vec3::new(5.0 + sign(1), -2.0 + 2.0 * sign(2), 1.0 + 3.0 * sign(4)));
+    }
+    let hull = ECollisionShape::Hull(box_verts);
+    let i = hull.inertia(3.0);
+    assert!((i.x - 13.0).abs() < 0.001 && (i.y - 10.0).abs() < 0.001 && (i.z - 5.0).abs() < 0.001);
+
+    // -- a tetrahedron is narrower at the top, so it is lighter to spin about y than its bounding box
+    let tetra = ECollisionShape::Hull(vec![
+        Vec3::new(-1.0, 0.0, -1.0),
+        Vec3::new(1.0, 0.0, -1.0),
+        Vec3::new(0.0, 0.0, 1.0),
+        Vec3::new(0.0, 2.0, 0.0),
+    ]);
+    let tetra_box = ECollisionShape::Box(SBox{ half_extents: Vec3::new(1.0, 1.0, 1.0) });
+    assert!(tetra.inertia(1.0).y < tetra_box.inertia(1.0).y);
+}
@@ -0,0 +1,615 @@
+use std::collections::HashMap;
+
+use crate::math::{Vec3};
+use crate::shapes::{TConvexShape};
+
+// -- 3D quickhull. Builds the convex hull of a point cloud as a closed triangle mesh, with face
+// -- and edge adjacency. Points within epsilon of the hull (duplicates, or points coplanar with a
+// -- face) are treated as inside, so they never produce slivers or degenerate faces.
+// -- see Barber, Dobkin, Huhdanpaa - "The Quickhull Algorithm for Convex Hulls"
+
+#[derive(Copy, Clone, Debug)]
+pub struct SQuickHullSettings {
+    // -- stop expanding once the hull has this many vertices. Since the furthest point is always
+    // -- added next, this gives a simplified hull that sits inside the full one. Flat input is cut
+    // -- down to at least 3 vertices, anything else to at least 4
+    pub max_vertices: Option<usize>,
+}
+
+impl Default for SQuickHullSettings {
+    fn default() -> Self {
+        Self {
+            max_vertices: None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct SHullFace {
+    // -- counter-clockwise seen from outside
+    pub verts: [usize; 3],
+    // -- face across the edge from verts[i] to verts[(i + 1) % 3]
+    pub neighbours: [usize; 3],
+    pub normal: Vec3,
+    pub plane_dist: f32,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct SHullEdge {
+    pub verts: [usize; 2],
+    pub faces: [usize; 2],
+}
+
+// -- flat input produces a two sided hull, with every face on the top mirrored on the bottom.
+// -- collinear input produces just the two end points, and a single point produces one vertex.
+pub struct SHull {
+    pub verts: Vec<Vec3>,
+    pub faces: Vec<SHullFace>,
+    pub edges: Vec<SHullEdge>,
+    // -- for each vertex, the vertices it shares an edge with
+    pub vert_neighbours: Vec<Vec<usize>>,
+}
+
+struct SBuildFace {
+    verts: [usize; 3],
+    normal: Vec3,
+    plane_dist: f32,
+    outside: Vec<usize>,
+    alive: bool,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum EVisibility {
+    Unknown,
+    Visible,
+    NotVisible,
+}
+
+struct SQuickHullBuilder<'a> {
+    points: &'a [Vec3],
+    epsilon: f32,
+    faces: Vec<SBuildFace>,
+    // -- directed edge to the face that owns it, the neighbour across (a, b) owns (b, a)
+    edge_to_face: HashMap<(usize, usize), usize>,
+}
+
+impl SHullFace {
+    pub fn dist_to(&self, p: &Vec3) -> f32 {
+        Vec3::dot(&self.normal, p) - self.plane_dist
+    }
+}
+
+impl SHull {
+    pub fn new_empty() -> Self {
+        Self {
+            verts: Vec::new(),
+            faces: Vec::new(),
+            edges: Vec::new(),
+            vert_neighbours: Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.verts.is_empty()
+    }
+}
+
+impl TConvexShape for SHull {
+    fn support(&self, dir: &Vec3) -> Vec3 {
+        self.verts.as_slice().support(dir)
+    }
+}
+
+impl<'a> SQuickHullBuilder<'a> {
+    fn dist_to_face(&self, face_idx: usize, point_idx: usize) -> f32 {
+        let face = &self.faces[face_idx];
+        Vec3::dot(&face.normal, &self.points[point_idx]) - face.plane_dist
+    }
+
+    fn add_face(&mut self, i0: usize, i1: usize, i2: usize) -> usize {
+        let a = &self.points[i0];
+        let b = &self.points[i1];
+        let c = &self.points[i2];
+
+        // -- a sliver gets a zero normal, so nothing is ever outside it
+        let n = Vec3::cross(&(b - a), &(c - a));
+        let mag = n.mag();
+        let normal = if mag > std::f32::EPSILON { (1.0 / mag) * n } else { Vec3::zero() };
+
+        let face_idx = self.faces.len();
+        self.faces.push(SBuildFace {
+            verts: [i0, i1, i2],
+            normal,
+            plane_dist: Vec3::dot(&normal, a),
+            outside: Vec::new(),
+            alive: true,
+        });
+
+        self.edge_to_face.insert((i0, i1), face_idx);
+        self.edge_to_face.insert((i1, i2), face_idx);
+        self.edge_to_face.insert((i2, i0), face_idx);
+
+        face_idx
+    }
+
+    fn remove_face(&mut self, face_idx: usize) {
+        let verts = self.faces[face_idx].verts;
+        for i in 0..3 {
+            let edge = (verts[i], verts[(i + 1) % 3]);
+            if self.edge_to_face.get(&edge) == Some(&face_idx) {
+                self.edge_to_face.remove(&edge);
+            }
+        }
+
+        self.faces[face_idx].alive = false;
+    }
+
+    // -- gives the point to the face it is furthest outside of, or drops it if it's inside all of them
+    fn assign_to_outside_set(&mut self, point_idx: usize, candidate_faces: &[usize]) {
+        let mut best_face = None;
+        let mut best_dist = self.epsilon;
+
+        for &face_idx in candidate_faces {
+            let dist = self.dist_to_face(face_idx, point_idx);
+            if dist > best_dist {
+                best_dist = dist;
+                best_face = Some(face_idx);
+            }
+        }
+
+        if let Some(face_idx) = best_face {
+            self.faces[face_idx].outside.push(point_idx);
+        }
+    }
+
+    fn add_point(&mut self, start_face: usize, point_idx: usize) {
+        // -- flood out from the face the point belongs to, collecting every face it can see and
+        // -- the horizon edges between those and the faces it can't
+        let mut visibility = vec![EVisibility::Unknown; self.faces.len()];
+        let mut visible = Vec::new();
+        let mut horizon = Vec::new();
+        let mut stack = vec![start_face];
+        visibility[start_face] = EVisibility::Visible;
+
+        while let Some(face_idx) = stack.pop() {
+            visible.push(face_idx);
+
+            let verts = self.faces[face_idx].verts;
+            for i in 0..3 {
+                let a = verts[i];
+                let b = verts[(i + 1) % 3];
+                let neighbour = self.edge_to_face[&(b, a)];
+
+                if visibility[neighbour] == EVisibility::Unknown {
+                    visibility[neighbour] = if self.dist_to_face(neighbour, point_idx) > self.epsilon {
+                        EVisibility::Visible
+                    }
+                    else {
+                        EVisibility::NotVisible
+                    };
+
+                    if visibility[neighbour] == EVisibility::Visible {
+                        stack.push(neighbour);
+                    }
+                }
+
+                if visibility[neighbour] == EVisibility::NotVisible {
+                    horizon.push((a, b));
+                }
+            }
+        }
+
+        let mut orphans = Vec::new();
+        for &face_idx in &visible {
+            orphans.extend(self.faces[face_idx].outside.drain(..).filter(|p| *p != point_idx));
+            self.remove_face(face_idx);
+        }
+
+        // -- each horizon edge keeps its winding, so the new faces face outwards
+        let mut new_faces = Vec::with_capacity(horizon.len());
+        for (a, b) in horizon {
+            new_faces.push(self.add_face(a, b, point_idx));
+        }
+
+        for orphan in orphans {
+            self.assign_to_outside_set(orphan, new_faces.as_slice());
+        }
+    }
+
+    fn next_point(&self) -> Option<(usize, usize)> {
+        for (face_idx, face) in self.faces.iter().enumerate() {
+            if !face.alive || face.outside.is_empty() {
+                continue;
+            }
+
+            let mut furthest = face.outside[0];
+            let mut furthest_dist = self.dist_to_face(face_idx, furthest);
+            for &point_idx in &face.outside[1..] {
+                let dist = self.dist_to_face(face_idx, point_idx);
+                if dist > furthest_dist {
+                    furthest_dist = dist;
+                    furthest = point_idx;
+                }
+            }
+
+            return Some((face_idx, furthest));
+        }
+
+        None
+    }
+
+    // -- all points are within epsilon of the plane through i0, i1, i2, so wrap them in 2D and
+    // -- close the polygon with a mirrored copy
+    fn build_flat(&mut self, i0: usize, i1: usize, i2: usize, max_vertices: Option<usize>) {
+        let origin = self.points[i0];
+        let u = (self.points[i1] - origin).normalized();
+        let n = Vec3::cross(&(self.points[i1] - origin), &(self.points[i2] - origin)).normalized();
+        let v = Vec3::cross(&n, &u);
+
+        let mut projected : Vec<(f32, f32, usize)> = self.points.iter().enumerate().map(|(i, p)| {
+            let d = p - origin;
+            (Vec3::dot(&d, &u), Vec3::dot(&d, &v), i)
+        }).collect();
+        projected.sort_by(|a, b| {
+            a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal)
+                .then(a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+        });
+
+        // -- monotone chain, dropping collinear and duplicate points
+        let turns_left = |o: &(f32, f32, usize), a: &(f32, f32, usize), b: &(f32, f32, usize)| -> bool {
+            (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0) > 0.0
+        };
+
+        let mut polygon : Vec<(f32, f32, usize)> = Vec::with_capacity(projected.len() + 1);
+        for p in projected.iter() {
+            while polygon.len() >= 2 && !turns_left(&polygon[polygon.len() - 2], &polygon[polygon.len() - 1], p) {
+                polygon.pop();
+            }
+            polygon.push(*p);
+        }
+
+        let lower_len = polygon.len() + 1;
+        for p in projected.iter().rev().skip(1) {
+            while polygon.len() >= lower_len && !turns_left(&polygon[polygon.len() - 2], &polygon[polygon.len() - 1], p) {
+                polygon.pop();
+            }
+            polygon.push(*p);
+        }
+
+        // -- the chain ends back at the first point
+        polygon.pop();
+
+        // -- drop whichever vertex loses the least area until the polygon is small enough, so the
+        // -- simplified polygon still sits inside the full one
+        if let Some(max_vertices) = max_vertices {
+            while polygon.len() > std::cmp::max(max_vertices, 3) {
+                let n = polygon.len();
+                let area_lost = |i: usize| -> f32 {
+                    let o = &polygon[(i + n - 1) % n];
+                    let a = &polygon[i];
+                    let b = &polygon[(i + 1) % n];
+                    ((a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)).abs()
+                };
+                let cheapest = (0..n)
+                    .min_by(|x, y| area_lost(*x).partial_cmp(&area_lost(*y)).unwrap_or(std::cmp::Ordering::Equal))
+                    .expect("polygon has more than 3 vertices");
+                polygon.remove(cheapest);
+            }
+        }
+
+        // -- fan the top from the first vertex and the bottom from the second, so no diagonal is
+        // -- shared by four faces
+        let n = polygon.len();
+        for i in 1..(n - 1) {
+            self.add_face(polygon[0].2, polygon[i].2, polygon[i + 1].2);
+        }
+        for i in 2..n {
+            self.add_face(polygon[1].2, polygon[(i + 1) % n].2, polygon[i].2);
+        }
+    }
+
+    fn finish(&self) -> SHull {
+        let mut hull = SHull::new_empty();
+
+        let mut face_remap = vec![std::usize::MAX; self.faces.len()];
+        let mut vert_remap = vec![std::usize::MAX; self.points.len()];
+
+        for (face_idx, face) in self.faces.iter().enumerate() {
+            if !face.alive {
+                continue;
+            }
+            face_remap[face_idx] = hull.faces.len();
+
+            let mut verts = [0; 3];
+            for i in 0..3 {
+                let point_idx = face.verts[i];
+                if vert_remap[point_idx] == std::usize::MAX {
+                    vert_remap[point_idx] = hull.verts.len();
+                    hull.verts.push(self.points[point_idx]);
+                }
+                verts[i] = vert_remap[point_idx];
+            }
+
+            hull.faces.push(SHullFace {
+                verts,
+                neighbours: [0; 3],
+                normal: face.normal,
+                plane_dist: face.plane_dist,
+            });
+        }
+
+        hull.vert_neighbours = vec![Vec::new(); hull.verts.len()];
+
+        for (face_idx, face) in self.faces.iter().enumerate() {
+            if !face.alive {
+                continue;
+            }
+            let hull_face_idx = face_remap[face_idx];
+
+            for i in 0..3 {
+                let a = face.verts[i];
+                let b = face.verts[(i + 1) % 3];
+                let neighbour = face_remap[self.edge_to_face[&(b, a)]];
+                hull.faces[hull_face_idx].neighbours[i] = neighbour;
+
+                // -- every edge is seen from both sides, only record it once
+                if a < b {
+                    let (ha, hb) = (vert_remap[a], vert_remap[b]);
+                    hull.edges.push(SHullEdge {
+                        verts: [ha, hb],
+                        faces: [hull_face_idx, neighbour],
+                    });
+                    hull.vert_neighbours[ha].push(hb);
+                    hull.vert_neighbours[hb].push(ha);
+                }
+            }
+        }
+
+        hull
+    }
+}
+
+pub fn quickhull(points: &[Vec3], settings: &SQuickHullSettings) -> SHull {
+    if points.is_empty() {
+        return SHull::new_empty();
+    }
+
+    // -- tolerance scaled to the magnitude of the input, as in qhull
+    let mut max_abs = Vec3::zero();
+    for p in points {
+        max_abs = Vec3::new(max_abs.x.max(p.x.abs()), max_abs.y.max(p.y.abs()), max_abs.z.max(p.z.abs()));
+    }
+    let epsilon = 3.0 * std::f32::EPSILON * (max_abs.x + max_abs.y + max_abs.z);
+
+    // -- initial simplex: the most distant pair of axis extremes, then the point furthest from
+    // -- their line, then the point furthest from that plane
+    let mut extremes = [0; 6];
+    for (i, p) in points.iter().enumerate() {
+        if p.x < points[extremes[0]].x { extremes[0] = i; }
+        if p.x > points[extremes[1]].x { extremes[1] = i; }
+        if p.y < points[extremes[2]].y { extremes[2] = i; }
+        if p.y > points[extremes[3]].y { extremes[3] = i; }
+        if p.z < points[extremes[4]].z { extremes[4] = i; }
+        if p.z > points[extremes[5]].z { extremes[5] = i; }
+    }
+
+    let mut i0 = 0;
+    let mut i1 = 0;
+    let mut max_sqdist = 0.0;
+    for a in 0..6 {
+        for b in (a + 1)..6 {
+            let sqdist = (points[extremes[a]] - points[extremes[b]]).sqmag();
+            if sqdist > max_sqdist {
+                max_sqdist = sqdist;
+                i0 = extremes[a];
+                i1 = extremes[b];
+            }
+        }
+    }
+
+    if max_sqdist.sqrt() <= epsilon {
+        let mut hull = SHull::new_empty();
+        hull.verts.push(points[i0]);
+        hull.vert_neighbours.push(Vec::new());
+        return hull;
+    }
+
+    let line_dir = (points[i1] - points[i0]).normalized();
+    let mut i2 = i0;
+    let mut max_line_dist = 0.0;
+    for (i, p) in points.iter().enumerate() {
+        let dist = Vec3::cross(&(p - points[i0]), &line_dir).mag();
+        if dist > max_line_dist {
+            max_line_dist = dist;
+            i2 = i;
+        }
+    }
+
+    if max_line_dist <= epsilon {
+        let mut hull = SHull::new_empty();
+        hull.verts.push(points[i0]);
+        hull.verts.push(points[i1]);
+        hull.edges.push(SHullEdge {
+            verts: [0, 1],
+            faces: [std::usize::MAX, std::usize::MAX],
+        });
+        hull.vert_neighbours.push(vec![1]);
+        hull.vert_neighbours.push(vec![0]);
+        return hull;
+    }
+
+    let plane_normal = Vec3::cross(&(points[i1] - points[i0]), &(points[i2] - points[i0])).normalized();
+    let mut i3 = i0;
+    let mut max_plane_dist = 0.0;
+    for (i, p) in points.iter().enumerate() {
+        let dist = Vec3::dot(&(p - points[i0]), &plane_normal);
+        if dist.abs() > max_plane_dist {
+            max_plane_dist = dist.abs();
+            i3 = i;
+        }
+    }
+
+    let mut builder = SQuickHullBuilder {
+        points,
+        epsilon,
+        faces: Vec::new(),
+        edge_to_face: HashMap::new(),
+    };
+
+    if max_plane_dist <= epsilon {
+        builder.build_flat(i0, i1, i2, settings.max_vertices);
+        return builder.finish();
+    }
+
+    // -- wind the base so the fourth point is behind it
+    if Vec3::dot(&(points[i3] - points[i0]), &plane_normal) > 0.0 {
+        std::mem::swap(&mut i1, &mut i2);
+    }
+
+    let initial_faces = [
+        builder.add_face(i0, i1, i2),
+        builder.add_face(i0, i3, i1),
+        builder.add_face(i1, i3, i2),
+        builder.add_face(i2, i3, i0),
+    ];
+
+    for i in 0..points.len() {
+        if i != i0 && i != i1 && i != i2 && i != i3 {
+            builder.assign_to_outside_set(i, &initial_faces);
+        }
+    }
+
+    let mut vert_count = 4;
+    while let Some((face_idx, point_idx)) = builder.next_point() {
+        if let Some(max_vertices) = settings.max_vertices {
+            if vert_count >= max_vertices {
+                break;
+            }
+        }
+
+        builder.add_point(face_idx, point_idx);
+        vert_count += 1;
+    }
+
+    builder.finish()
+}
+
+#[allow(dead_code)]
+fn check_hull_is_closed_and_convex(hull: &SHull, points: &[Vec3]) {
+    // -- euler characteristic of a closed triangle mesh
+    assert_eq!(hull.verts.len() + hull.faces.len(), hull.edges.len() + 2);
+    assert_eq!(hull.faces.len() * 3, hull.edges.len() * 2);
+
+    for (face_idx, face) in hull.faces.iter().enumerate() {
+        for i in 0..3 {
+            let neighbour = &hull.faces[face.neighbours[i]];
+            assert!(neighbour.neighbours.contains(&face_idx));
+        }
+
+        for p in points {
+            assert!(face.dist_to(p) < 0.0001);
+        }
+    }
+
+    for (v, neighbours) in hull.vert_neighbours.iter().enumerate() {
+        for n in neighbours {
+            assert!(hull.vert_neighbours[*n].contains(&v));
+        }
+    }
+}
+
+#[test]
+fn test_quickhull_cube_with_interior_coplanar_and_duplicate_points() {
+    let mut points = Vec::new();
+    for x in &[-1.0, 1.0] {
+        for y in &[-1.0, 1.0] {
+            for z in &[-1.0, 1.0] {
+                points.push(Vec3::new(*x, *y, *z));
+                points.push(Vec3::new(*x, *y, *z));
+            }
+        }
+    }
+
+    // -- a grid over the cube, so the faces are covered in coplanar points
+    for i in 0..5 {
+        for j in 0..5 {
+            for k in 0..5 {
+                points.push(Vec3::new(-1.0 + 0.5 * i as f32, -1.0 + 0.5 * j as f32, -1.0 + 0.5 * k as f32));
+            }
+        }
+    }
+
+    let hull = quickhull(points.as_slice(), &SQuickHullSettings::default());
+    assert_eq!(hull.verts.len(), 8);
+    assert_eq!(hull.faces.len(), 12);
+    assert_eq!(hull.edges.len(), 18);
+    for v in &hull.verts {
+        assert!(v.x.abs() == 1.0 && v.y.abs() == 1.0 && v.z.abs() == 1.0);
+    }
+    check_hull_is_closed_and_convex(&hull, points.as_slice());
+}
+
+#[test]
+fn test_quickhull_sphere_and_simplification() {
+    // -- fibonacci sphere, every point is on the hull
+    let count = 200;
+    let mut points = Vec::new();
+    for i in 0..count {
+        let y = 1.0 - 2.0 * (i as f32 + 0.5) / count as f32;
+        let r = (1.0 - y * y).sqrt();
+        let theta = i as f32 * 2.399963;
+        points.push(Vec3::new(r * theta.cos(), y, r * theta.sin()));
+    }
+
+    let hull = quickhull(points.as_slice(), &SQuickHullSettings::default());
+    assert_eq!(hull.verts.len(), count);
+    check_hull_is_closed_and_convex(&hull, points.as_slice());
+
+    let simplified = quickhull(points.as_slice(), &SQuickHullSettings{ max_vertices: Some(20) });
+    assert_eq!(simplified.verts.len(), 20);
+    assert_eq!(simplified.verts.len() + simplified.faces.len(), simplified.edges.len() + 2);
+    check_hull_is_closed_and_convex(&simplified, simplified.verts.as_slice());
+}
+
+#[test]
+fn test_quickhull_degenerate_input() {
+    let settings = SQuickHullSettings::default();
+
+    assert!(quickhull(&[], &settings).is_empty());
+
+    let point = quickhull(&[Vec3::new(1.0, 2.0, 3.0), Vec3::new(1.0, 2.0, 3.0)], &settings);
+    assert_eq!(point.verts.len(), 1);
+
+    let line = quickhull(&[Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 1.0), Vec3::new(2.0, 2.0, 2.0)], &settings);
+    assert_eq!(line.verts.len(), 2);
+    assert_eq!(line.edges.len(), 1);
+
+    // -- a flat grid gives a two sided quad
+    let mut grid = Vec::new();
+    for i in 0..4 {
+        for j in 0..4 {
+            grid.push(Vec3::new(i as f32, 2.0, j as f32));
+        }
+    }
+    let flat = quickhull(grid.as_slice(), &settings);
+    assert_eq!(flat.verts.len(), 4);
+    assert_eq!(flat.faces.len(), 4);
+    check_hull_is_closed_and_convex(&flat, grid.as_slice());
+
+    // -- flat input is simplified too, down to a polygon inside the full one
+    let circle : Vec<Vec3> = (0..32).map(|i| {
+        let theta = i as f32 * 2.0 * std::f32::consts::PI / 32.0;
+        Vec3::new(theta.cos(), 0.5, theta.sin())
+    }).collect();
+    let full_circle = quickhull(circle.as_slice(), &settings);
+    assert_eq!(full_circle.verts.len(), 32);
+    let simplified_circle = quickhull(circle.as_slice(), &SQuickHullSettings{ max_vertices: Some(8) });
+    assert_eq!(simplified_circle.verts.len(), 8);
+    assert_eq!(simplified_circle.faces.len(), 12);
+    check_hull_is_closed_and_convex(&simplified_circle, simplified_circle.verts.as_slice());
+    for v in &simplified_circle.verts {
+        assert!(circle.contains(v));
+    }
+    let triangle = quickhull(circle.as_slice(), &SQuickHullSettings{ max_vertices: Some(1) });
+    assert_eq!(triangle.verts.len(), 3);
+}
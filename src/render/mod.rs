@@ -14,8 +14,7 @@ use crate::niced3d12 as n12;
 use crate::typeyd3d12 as t12;
 use crate::allocate::{STACK_ALLOCATOR};
 use crate::collections::{SVec};
-use crate::databucket::{SDataBucket};
-use crate::entity::{SEntityBucket, SEntityHandle};
+use crate::entity::{SEntityBucket};
 use crate::entity_animation;
 use crate::entity_model;
 use crate::game_context::{SGameContext, SFrameContext};
@@ -23,7 +22,7 @@ use crate::model::{SMeshInstanceLoader, SMeshInstanceHandle, SMeshLoader, STextu
 use crate::safewindows;
 use crate::rustywindows;
 use crate::utils;
-use crate::utils::{STransform, SRay};
+use crate::utils::{STransform};
 
 mod compute_skinning_pipeline;
 mod shadowmapping;
@@ -403,11 +402,11 @@ impl SRender {
         Ok(())
     }
 
-    pub fn new_mesh_instance_from_obj(&mut self, obj_file_path: &'static str, diffuse_weight: f32, is_lit: bool) -> Result<SMeshInstanceHandle, &'static str> {
+    pub fn new_mesh_instance_from_obj(&mut self, obj_file_path: &str, diffuse_weight: f32, is_lit: bool) -> Result<SMeshInstanceHandle, &'static str> {
         self.mesh_instance_loader.new_from_obj(obj_file_path, &mut self.mesh_loader, &mut self.texture_loader, diffuse_weight, is_lit)
     }
 
-    pub fn new_mesh_instance_from_gltf(&mut self, gltf_file_path: &'static str, diffuse_weight: f32, is_lit: bool) -> Result<SMeshInstanceHandle, &'static str> {
+    pub fn new_mesh_instance_from_gltf(&mut self, gltf_file_path: &str, diffuse_weight: f32, is_lit: bool) -> Result<SMeshInstanceHandle, &'static str> {
         self.mesh_instance_loader.new_from_gtlf.new_from_gltf(gltf_file_path, &mut self.mesh_loader, &mut self.texture_loader, diffuse_weight, is_lit)
     }
 
@@ -469,6 +468,7 @@ impl SRender {
         &mut self,
         window: &n12::SD3D12Window,
         view_matrix: &Mat4,
+        projection_matrix: &Mat4,
         entities: &mut SEntityBucket,
         entity_animation: &mut entity_animation::SBucket,
         entity_model: &entity_model::SBucket,
@@ -513,13 +513,7 @@ impl SRender {
         }
 
         // -- reminder: D3D clip space is (-1, 1) x, (-1, 1) y, (0, 1) znear-zfar
-        let perspective_matrix: Mat4 = {
-            let aspect = (window.width() as f32) / (window.height() as f32);
-            let zfar = 100.0;
-
-            //SMat44::new_perspective(aspect, fovy, znear, zfar)
-            Mat4::new_perspective(aspect, self.fovy(), self.znear(), zfar)
-        };
+        let perspective_matrix: Mat4 = projection_matrix.clone();
 
         let context = SRenderContext{
             current_back_buffer_index: window.currentbackbufferindex(),
@@ -661,6 +655,9 @@ impl SRender {
 
                 for model_handle in 0..entity_model.models.len() {
                     let entity_handle = entity_model.get_entity(model_handle);
+                    if entities.is_entity_hidden(entity_handle) {
+                        continue;
+                    }
                     let model = entity_model.get_model(model_handle);
 
                     /*
@@ -744,21 +741,6 @@ impl Drop for SRender {
     }
 }
 
-pub fn cast_ray_against_entity_model(data_bucket: &SDataBucket, ray: &SRay, entity: SEntityHandle) -> Option<f32> {
-    let mut result = None;
-
-    data_bucket.get::<SEntityBucket>()
-        .and::<SRender>()
-        .and::<entity_model::SBucket>()
-        .with_ccc(|entities, render, em| {
-            let entity_to_world = entities.get_entity_location(entity);
-            let model_handle = em.handle_for_entity(entity).unwrap();
-            result = render.ray_intersects(em.get_model(model_handle), &ray.origin, &ray.dir, &entity_to_world);
-        });
-
-    result
-}
-
 pub fn update_render_frame(game_context: &SGameContext, frame_context: &SFrameContext) {
     game_context.data_bucket.get::<SRender>()
         .and::<SEntityBucket>()
@@ -767,8 +749,12 @@ pub fn update_render_frame(game_context: &SGameContext, frame_context: &SFrameCo
         .and::<camera::SDebugFPCamera>()
         .with_mmmcc(|render, entities, entity_animation, entity_model, camera| {
             let view_matrix = camera.world_to_view_matrix();
+            let projection_matrix = {
+                let aspect = (game_context.window.width() as f32) / (game_context.window.height() as f32);
+                camera.projection_matrix(aspect, render.fovy(), render.znear())
+            };
 
-            let render_result = render.render_frame(&game_context.window, &view_matrix, entities, entity_animation, entity_model, frame_context.imgui_draw_data);
+            let render_result = render.render_frame(&game_context.window, &view_matrix, &projection_matrix, entities, entity_animation, entity_model, frame_context.imgui_draw_data);
             match render_result {
                 Ok(_) => {},
                 Err(e) => {
@@ -208,6 +208,9 @@ impl SShadowMappingPipeline {
 
             for model_handle in 0..entity_model.models.len() {
                 let entity_handle = entity_model.get_entity(model_handle);
+                if entities.is_entity_hidden(entity_handle) {
+                    continue;
+                }
                 let model = entity_model.get_model(model_handle);
                 let location = entities.get_entity_location(entity_handle);
 
@@ -455,6 +455,9 @@ pub enum EKey {
     Insert,
     Delete,
     Minus,
+    Shift,
+    Control,
+    Alt,
 }
 
 pub fn translatewmkey(key: win::WPARAM) -> EKey {
@@ -512,6 +515,9 @@ pub fn translatewmkey(key: win::WPARAM) -> EKey {
         win::VK_RETURN => EKey::Enter,
         win::VK_ESCAPE => EKey::Escape,
         win::VK_OEM_MINUS => EKey::Minus,
+        win::VK_SHIFT => EKey::Shift,
+        win::VK_CONTROL => EKey::Control,
+        win::VK_MENU => EKey::Alt,
         _ => EKey::Invalid,
     }
 }
@@ -525,6 +531,9 @@ pub enum EMsgType {
     LButtonUp { x_pos: i32, y_pos: i32 },
     MButtonDown { x_pos: i32, y_pos: i32 },
     MButtonUp { x_pos: i32, y_pos: i32 },
+    RButtonDown { x_pos: i32, y_pos: i32 },
+    RButtonUp { x_pos: i32, y_pos: i32 },
+    MouseWheel { delta: i16 },
     Paint,
     Size,
     Input { raw_input: rawinput::SRawInput },
@@ -538,6 +547,13 @@ pub fn msgtype(msg: u32, wparam: win::WPARAM, lparam: win::LPARAM) -> EMsgType {
         win::WM_KEYUP => EMsgType::KeyUp {
             key: translatewmkey(wparam),
         },
+        // -- keys pressed while alt is held arrive as system keys
+        win::WM_SYSKEYDOWN => EMsgType::KeyDown {
+            key: translatewmkey(wparam),
+        },
+        win::WM_SYSKEYUP => EMsgType::KeyUp {
+            key: translatewmkey(wparam),
+        },
         win::WM_LBUTTONDOWN => EMsgType::LButtonDown {
             x_pos: win::GET_X_LPARAM(lparam),
             y_pos: win::GET_Y_LPARAM(lparam),
@@ -554,6 +570,17 @@ pub fn msgtype(msg: u32, wparam: win::WPARAM, lparam: win::LPARAM) -> EMsgType {
             x_pos: win::GET_X_LPARAM(lparam),
             y_pos: win::GET_Y_LPARAM(lparam),
         },
+        win::WM_RBUTTONDOWN => EMsgType::RButtonDown {
+            x_pos: win::GET_X_LPARAM(lparam),
+            y_pos: win::GET_Y_LPARAM(lparam),
+        },
+        win::WM_RBUTTONUP => EMsgType::RButtonUp {
+            x_pos: win::GET_X_LPARAM(lparam),
+            y_pos: win::GET_Y_LPARAM(lparam),
+        },
+        win::WM_MOUSEWHEEL => EMsgType::MouseWheel {
+            delta: win::GET_WHEEL_DELTA_WPARAM(wparam),
+        },
         win::WM_PAINT => EMsgType::Paint,
         win::WM_SIZE => EMsgType::Size,
         win::WM_INPUT => {
@@ -0,0 +1,268 @@
+use crate::collections::{SVec};
+use crate::collision::{SQueryFilter};
+use crate::databucket::{SDataBucket, SEntityBVH};
+use crate::entity::{SEntityBucket, SEntityHandle};
+use crate::entity_model;
+use crate::gjk;
+use crate::math::{Vec3};
+use crate::render;
+use crate::shapes::{TConvexShape, shape_aabb};
+use crate::utils::{SAABB, SRay};
+
+// -- queries against everything in the level, going through the entity BVH to find candidates
+
+#[derive(Clone, Copy, Debug)]
+pub struct SRaycastSettings {
+    // -- in world units along the ray, hits further away are ignored
+    pub max_distance: f32,
+    // -- skip model instances that aren't pickable, for the editor
+    pub pickable_only: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SSceneRaycastHit {
+    pub entity: SEntityHandle,
+    // -- in world units from the ray origin, whatever the length of the ray's dir
+    pub distance: f32,
+    pub position: Vec3,
+    // -- unit face normal of the hit triangle
+    pub normal: Vec3,
+    // -- index of the triangle in the entity's mesh
+    pub triangle: usize,
+    // -- weights of the triangle's three verts at position
+    pub barycentric: Vec3,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SSceneShapeCastHit {
+    pub entity: SEntityHandle,
+    // -- fraction of the translation travelled before touching
+    pub toi: f32,
+    pub point: Vec3,
+    // -- surface normal of what was hit, facing back towards the shape
+    pub normal: Vec3,
+}
+
+impl Default for SRaycastSettings {
+    fn default() -> Self {
+        Self {
+            max_distance: std::f32::MAX,
+            pickable_only: false,
+        }
+    }
+}
+
+// -- calls on_hit with the closest hit on each entity the ray touches, roughly front to back.
+// -- on_hit returns the new max distance, so a search for the closest hit can cut off early
+fn raycast_entities<F: FnMut(SSceneRaycastHit) -> f32>(
+    data_bucket: &SDataBucket,
+    ray: &SRay,
+    settings: &SRaycastSettings,
+    filter: &SQueryFilter,
+    mut on_hit: F,
+) {
+    let dir_length = ray.dir.mag();
+    if dir_length <= 0.0 {
+        return;
+    }
+
+    data_bucket.get::<SEntityBVH>()
+        .and::<SEntityBucket>()
+        .and::<entity_model::SBucket>()
+        .and::<render::SRender>()
+        .with_cccc(|bvh, entities, em, render| {
+            let mut max_distance = settings.max_distance;
+
+            // -- the bvh hands out entities in the order the ray enters their bounds, and stops once
+            // -- that's past max_distance since nothing further can be closer
+            bvh.cast_ray_front_to_back(ray, max_distance / dir_length, |_box_t, entity| {
+                let keep_searching = max_distance / dir_length;
+
+                if !filter.accepts(*entity, &entities.get_collision_filter(*entity)) {
+                    return keep_searching;
+                }
+
+                let model = match em.handle_for_entity(*entity) {
+                    Some(handle) => em.get_model(handle),
+                    None => return keep_searching,
+                };
+                if settings.pickable_only && !render.mesh_instance_loader().is_pickable(model) {
+                    return keep_searching;
+                }
+
+                let location = entities.get_entity_location(*entity);
+                let mesh = render.mesh_instance_loader().get_mesh(model);
+                if let Some(hit) = render.mesh_loader().cast_ray(mesh, ray, &location) {
+                    let distance = hit.t * dir_length;
+                    if distance <= max_distance {
+                        max_distance = on_hit(SSceneRaycastHit{
+                            entity: *entity,
+                            distance,
+                            position: ray.origin + hit.t * ray.dir,
+                            normal: hit.normal,
+                            triangle: hit.triangle,
+                            barycentric: hit.barycentric,
+                        });
+                    }
+                }
+
+                max_distance / dir_length
+            });
+        });
+}
+
+// -- closest model triangle along ray, which is in world space
+pub fn raycast(
+    data_bucket: &SDataBucket,
+    ray: &SRay,
+    settings: &SRaycastSettings,
+    filter: &SQueryFilter,
+) -> Option<SSceneRaycastHit> {
+    let mut result : Option<SSceneRaycastHit> = None;
+
+    raycast_entities(data_bucket, ray, settings, filter, |hit| {
+        // -- only hits closer than the last one get through
+        result = Some(hit);
+        hit.distance
+    });
+
+    result
+}
+
+// -- the closest hit on every entity along ray, appended to out nearest first.
+// -- returns false if out filled up, what it holds is then still the nearest hits that fit
+pub fn raycast_all(
+    data_bucket: &SDataBucket,
+    ray: &SRay,
+    settings: &SRaycastSettings,
+    filter: &SQueryFilter,
+    out: &mut SVec<SSceneRaycastHit>,
+) -> bool {
+    let start = out.len();
+    let mut complete = true;
+
+    raycast_entities(data_bucket, ray, settings, filter, |hit| {
+        if out.remaining_capacity() > 0 {
+            out.push(hit);
+            return settings.max_distance;
+        }
+
+        // -- full, so swap out the furthest hit if this one is nearer and only look closer from now on
+        complete = false;
+        let hits = &mut out.as_mut_slice()[start..];
+        let furthest = (0..hits.len()).fold(0, |best, i| if hits[i].distance > hits[best].distance { i } else { best });
+        if hits.len() > 0 && hit.distance < hits[furthest].distance {
+            hits[furthest] = hit;
+        }
+        hits.iter().fold(0.0, |max, h| if h.distance > max { h.distance } else { max })
+    });
+
+    out.as_mut_slice()[start..].sort_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap());
+    complete
+}
+
+// -- sweeps shape (in world space) along translation and returns the first model triangle it touches.
+// -- shapes that start out overlapping something hit it with a toi of 0, pointing back along translation.
+// -- A triangle the cast can't resolve counts as hit where the cast gave up, so nothing is tunnelled through
+pub fn shape_cast<S: TConvexShape + ?Sized>(
+    data_bucket: &SDataBucket,
+    shape: &S,
+    translation: &Vec3,
+    filter: &SQueryFilter,
+) -> Option<SSceneShapeCastHit> {
+    let start_aabb = shape_aabb(shape);
+    let swept_aabb = SAABB::new_from_points(&[
+        start_aabb.min,
+        start_aabb.max,
+        start_aabb.min + *translation,
+        start_aabb.max + *translation,
+    ]);
+
+    // -- a shape that isn't moving has no direction to report for an overlap
+    let back_dir = if translation.sqmag() > 0.0 { -translation.normalized() } else { Vec3::zero() };
+
+    let settings = gjk::SShapeCastSettings::default();
+    let mut result : Option<SSceneShapeCastHit> = None;
+
+    data_bucket.get::<SEntityBVH>()
+        .and::<SEntityBucket>()
+        .and::<entity_model::SBucket>()
+        .and::<render::SRender>()
+        .with_cccc(|bvh, entities, em, render| {
+            let mut triangles = Vec::new();
+
+            // -- visiting candidates straight from the tree means none can be lost to a full buffer
+            bvh.query_with(&swept_aabb, |entity| {
+                if !filter.accepts(*entity, &entities.get_collision_filter(*entity)) {
+                    return true;
+                }
+
+                let model_handle = match em.handle_for_entity(*entity) {
+                    Some(handle) => handle,
+                    None => return true,
+                };
+                let location = entities.get_entity_location(*entity);
+                let mesh = render.mesh_instance_loader().get_mesh(em.get_model(model_handle));
+
+                triangles.clear();
+                render.mesh_loader().gather_triangles_in_aabb(mesh, &swept_aabb, &location, &mut triangles);
+
+                for tri in &triangles {
+                    let hit = match gjk::shape_cast(shape, translation, &tri[..], &settings) {
+                        gjk::EShapeCastResult::Hit(hit) => hit,
+                        gjk::EShapeCastResult::InitiallyOverlapping => gjk::SShapeCastHit {
+                            toi: 0.0,
+                            point: tri[0],
+                            normal: back_dir,
+                            iterations: 0,
+                        },
+                        gjk::EShapeCastResult::Miss => continue,
+                        // -- the cast stopped short of the triangle without proving a miss, so stop there.
+                        // -- That's as far as the shape can safely go
+                        gjk::EShapeCastResult::DidNotConverge(hit) => hit,
+                    };
+
+                    if hit.toi < result.map_or(std::f32::MAX, |r| r.toi) {
+                        result = Some(SSceneShapeCastHit{
+                            entity: *entity,
+                            toi: hit.toi,
+                            point: hit.point,
+                            normal: hit.normal,
+                        });
+                    }
+                }
+
+                true
+            });
+        });
+
+    result
+}
+
+// -- every entity whose bounds overlap aabb, without any narrow phase test.
+// -- returns false if out filled up before every entity was found
+pub fn overlap_aabb(
+    data_bucket: &SDataBucket,
+    aabb: &SAABB,
+    filter: &SQueryFilter,
+    out: &mut SVec<SEntityHandle>,
+) -> bool {
+    data_bucket.get::<SEntityBVH>()
+        .and::<SEntityBucket>()
+        .with_cc(|bvh, entities| {
+            let mut complete = true;
+            bvh.query_with(aabb, |entity| {
+                if !filter.accepts(*entity, &entities.get_collision_filter(*entity)) {
+                    return true;
+                }
+
+                if out.remaining_capacity() == 0 {
+                    complete = false;
+                    return false;
+                }
+                out.push(*entity);
+                true
+            });
+            complete
+        })
+}
@@ -0,0 +1,236 @@
+use crate::math::{Vec3, Quat};
+use crate::utils::{SAABB, STransform};
+
+// -- convex shapes described by their support function, which is all GJK and EPA need
+// -- shapes are defined in their local space, centered on the origin with Y as the long axis,
+// -- and are placed in the world with STransformedShape
+
+pub trait TConvexShape {
+    // -- the point on the shape furthest along dir, dir doesn't need to be normalized
+    fn support(&self, dir: &Vec3) -> Vec3;
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct SSphere {
+    pub radius: f32,
+}
+
+// -- segment from -half_height to half_height on Y, swept by radius
+#[derive(Copy, Clone, Debug)]
+pub struct SCapsule {
+    pub half_height: f32,
+    pub radius: f32,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct SBox {
+    pub half_extents: Vec3,
+}
+
+// -- caps at -half_height and half_height on Y
+#[derive(Copy, Clone, Debug)]
+pub struct SCylinder {
+    pub half_height: f32,
+    pub radius: f32,
+}
+
+// -- base circle at -half_height, apex at half_height on Y
+#[derive(Copy, Clone, Debug)]
+pub struct SCone {
+    pub half_height: f32,
+    pub radius: f32,
+}
+
+// -- convex hull of a point cloud, support is O(n) in the number of points
+pub struct SConvexHull<'a> {
+    pub verts: &'a [Vec3],
+}
+
+pub struct STransformedShape<S: TConvexShape> {
+    pub shape: S,
+    pub transform: STransform,
+}
+
+pub struct SMinkowskiSum<A: TConvexShape, B: TConvexShape> {
+    pub a: A,
+    pub b: B,
+}
+
+fn sign(v: f32) -> f32 {
+    if v >= 0.0 { 1.0 } else { -1.0 }
+}
+
+// -- direction in the XZ plane, or zero if dir is parallel to Y
+fn radial_dir(dir: &Vec3) -> Vec3 {
+    let radial = Vec3::new(dir.x, 0.0, dir.z);
+    let mag = radial.mag();
+    if mag > std::f32::EPSILON {
+        (1.0 / mag) * radial
+    }
+    else {
+        Vec3::zero()
+    }
+}
+
+// -- tight bounds of any convex shape, from its support along each axis
+pub fn shape_aabb<S: TConvexShape + ?Sized>(shape: &S) -> SAABB {
+    let mut aabb = SAABB::zero();
+    for axis in 0..3 {
+        let mut dir = Vec3::zero();
+        dir[axis] = 1.0;
+        aabb.max[axis] = shape.support(&dir)[axis];
+        aabb.min[axis] = shape.support(&-dir)[axis];
+    }
+    aabb
+}
+
+impl TConvexShape for SSphere {
+    fn support(&self, dir: &Vec3) -> Vec3 {
+        let mag = dir.mag();
+        if mag > std::f32::EPSILON {
+            (self.radius / mag) * dir
+        }
+        else {
+            Vec3::new(self.radius, 0.0, 0.0)
+        }
+    }
+}
+
+impl TConvexShape for SCapsule {
+    fn support(&self, dir: &Vec3) -> Vec3 {
+        let cap_center = Vec3::new(0.0, sign(dir.y) * self.half_height, 0.0);
+        cap_center + SSphere{ radius: self.radius }.support(dir)
+    }
+}
+
+impl TConvexShape for SBox {
+    fn support(&self, dir: &Vec3) -> Vec3 {
+        Vec3::new(
+            sign(dir.x) * self.half_extents.x,
+            sign(dir.y) * self.half_extents.y,
+            sign(dir.z) * self.half_extents.z,
+        )
+    }
+}
+
+impl TConvexShape for SCylinder {
+    fn support(&self, dir: &Vec3) -> Vec3 {
+        self.radius * radial_dir(dir) + Vec3::new(0.0, sign(dir.y) * self.half_height, 0.0)
+    }
+}
+
+impl TConvexShape for SCone {
+    fn support(&self, dir: &Vec3) -> Vec3 {
+        // -- the apex wins whenever dir is within the cone's half angle of +Y
+        let height = 2.0 * self.half_height;
+        let sin_half_angle = self.radius / (self.radius * self.radius + height * height).sqrt();
+
+        let mag = dir.mag();
+        if mag <= std::f32::EPSILON || dir.y / mag > sin_half_angle {
+            return Vec3::new(0.0, self.half_height, 0.0);
+        }
+
+        self.radius * radial_dir(dir) + Vec3::new(0.0, -self.half_height, 0.0)
+    }
+}
+
+impl<'a> TConvexShape for SConvexHull<'a> {
+    fn support(&self, dir: &Vec3) -> Vec3 {
+        self.verts.support(dir)
+    }
+}
+
+// -- raw point clouds are treated as their convex hull
+impl TConvexShape for [Vec3] {
+    fn support(&self, dir: &Vec3) -> Vec3 {
+        let mut max_dist = std::f32::NEG_INFINITY;
+        let mut result = Vec3::zero();
+
+        for p in self {
+            let dist = Vec3::dot(p, dir);
+            if dist > max_dist {
+                max_dist = dist;
+                result = *p;
+            }
+        }
+
+        result
+    }
+}
+
+impl<S: TConvexShape> TConvexShape for STransformedShape<S> {
+    fn support(&self, dir: &Vec3) -> Vec3 {
+        // -- uniform scale doesn't change which point is furthest, only rotation does
+        let local_dir = Quat::rotate_vec3(&self.transform.r.inverse(), dir);
+        self.transform.mul_point(&self.shape.support(&local_dir))
+    }
+}
+
+impl<A: TConvexShape, B: TConvexShape> TConvexShape for SMinkowskiSum<A, B> {
+    fn support(&self, dir: &Vec3) -> Vec3 {
+        self.a.support(dir) + self.b.support(dir)
+    }
+}
+
+impl<S: TConvexShape + ?Sized> TConvexShape for &S {
+    fn support(&self, dir: &Vec3) -> Vec3 {
+        (**self).support(dir)
+    }
+}
+
+impl<S: TConvexShape> STransformedShape<S> {
+    pub fn new(shape: S, transform: STransform) -> Self {
+        Self {
+            shape,
+            transform,
+        }
+    }
+}
+
+impl<A: TConvexShape, B: TConvexShape> SMinkowskiSum<A, B> {
+    pub fn new(a: A, b: B) -> Self {
+        Self {
+            a,
+            b,
+        }
+    }
+}
+
+#[test]
+fn test_support_functions() {
+    let d = Vec3::new(1.0, 1.0, 0.0);
+
+    let sphere = SSphere{ radius: 2.0 };
+    assert!((sphere.support(&d) - (2.0 / (2.0 as f32).sqrt()) * d).mag() < 0.0001);
+
+    let b = SBox{ half_extents: Vec3::new(1.0, 2.0, 3.0) };
+    assert_eq!(b.support(&Vec3::new(-1.0, 0.5, -0.1)), Vec3::new(-1.0, 2.0, -3.0));
+
+    let capsule = SCapsule{ half_height: 1.0, radius: 0.5 };
+    assert!((capsule.support(&Vec3::new(0.0, -1.0, 0.0)) - Vec3::new(0.0, -1.5, 0.0)).mag() < 0.0001);
+
+    let cylinder = SCylinder{ half_height: 1.0, radius: 0.5 };
+    assert!((cylinder.support(&Vec3::new(0.0, 1.0, 1.0)) - Vec3::new(0.0, 1.0, 0.5)).mag() < 0.0001);
+
+    let cone = SCone{ half_height: 1.0, radius: 1.0 };
+    assert!((cone.support(&Vec3::new(0.0, 1.0, 0.1)) - Vec3::new(0.0, 1.0, 0.0)).mag() < 0.0001);
+    assert!((cone.support(&Vec3::new(1.0, 0.1, 0.0)) - Vec3::new(1.0, -1.0, 0.0)).mag() < 0.0001);
+
+    let verts = [Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 2.0, 0.0), Vec3::new(-1.0, 1.0, 0.0)];
+    let hull = SConvexHull{ verts: &verts };
+    assert_eq!(hull.support(&d), Vec3::new(1.0, 2.0, 0.0));
+}
+
+#[test]
+fn test_transformed_and_minkowski_support() {
+    // -- a box rotated 90 degrees around Y swaps its X and Z extents
+    let b = SBox{ half_extents: Vec3::new(1.0, 1.0, 3.0) };
+    let rotation = Quat::new_angle_axis(std::f32::consts::PI / 2.0, &Vec3::new(0.0, 1.0, 0.0));
+    let transformed = STransformedShape::new(b, STransform::new(&Vec3::new(10.0, 0.0, 0.0), &rotation, 1.0));
+    let s = transformed.support(&Vec3::new(1.0, 0.0, 0.0));
+    assert!((s.x - 13.0).abs() < 0.0001);
+
+    // -- a sphere swept by a box is a rounded box
+    let rounded = SMinkowskiSum::new(SBox{ half_extents: Vec3::new(1.0, 1.0, 1.0) }, SSphere{ radius: 0.5 });
+    assert!((rounded.support(&Vec3::new(1.0, 0.0, 0.0)) - Vec3::new(1.5, 1.0, 1.0)).mag() < 0.0001);
+}
@@ -0,0 +1,319 @@
+use crate::allocate::{STACK_ALLOCATOR};
+use crate::bvh;
+use crate::collections::{SVec};
+use crate::gjk::{SGJKSettings, EGJKResult, EEPAResult, SPenetration, gjk_intersection, epa};
+use crate::math::{Vec3};
+use crate::shapes::{TConvexShape, shape_aabb};
+use crate::utils::{SAABB, SRay, STransform, ray_intersects_triangle_barycentric};
+
+// -- static triangle soup with a BVH over its triangles, so rays and convex shapes only test the
+// -- triangles near them. Built once when a mesh loads and never changes after that
+
+// -- the BVH addresses its nodes with u16s, and building over n triangles needs 2n free nodes
+pub const MAX_TRIANGLES : usize = (std::u16::MAX as usize) / 2;
+
+pub struct STriangleMesh {
+    verts: Vec<Vec3>,
+    indices: Vec<u16>,
+    // -- leaves are owned by the index of their triangle
+    bvh: bvh::STree<u16>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct STriangleRayHit {
+    pub t: f32,
+    pub triangle: u16,
+    // -- unit face normal from the triangle's winding, in ray space
+    pub normal: Vec3,
+    // -- weights of the triangle's three verts at the hit point
+    pub barycentric: Vec3,
+}
+
+impl std::fmt::Debug for STriangleMesh {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "STriangleMesh {{ triangles: {} }}", self.triangle_count())
+    }
+}
+
+impl STriangleMesh {
+    pub fn new(verts: &[Vec3], indices: &[u16]) -> Result<Self, &'static str> {
+        if indices.len() % 3 != 0 {
+            return Err("triangle mesh indices must come in threes");
+        }
+        let num_tris = indices.len() / 3;
+        if num_tris > MAX_TRIANGLES {
+            return Err("too many triangles for a triangle mesh");
+        }
+
+        let mut result = Self {
+            verts: verts.to_vec(),
+            indices: indices.to_vec(),
+            bvh: bvh::STree::new_with_capacity(Self::node_capacity(num_tris)),
+        };
+
+        STACK_ALLOCATOR.with(|sa| -> Result<(), &'static str> {
+            let mut batch = SVec::<(u16, SAABB)>::new(&sa.as_ref(), num_tris, 0)?;
+            for ti in 0..num_tris {
+                batch.push((ti as u16, SAABB::new_from_points(&result.local_triangle(ti as u16))));
+            }
+
+            let mut leaves = SVec::<bvh::SNodeHandle>::new(&sa.as_ref(), num_tris, 0)?;
+            result.bvh.insert_batch(batch.as_slice(), &mut leaves)
+        })?;
+
+        Ok(result)
+    }
+
+    fn node_capacity(num_tris: usize) -> u16 {
+        std::cmp::max(2 * num_tris, 2) as u16
+    }
+
+    // -- what new() will allocate for a mesh this size, so loaders can check a budget before building
+    pub fn bytes_for(num_verts: usize, num_indices: usize) -> usize {
+        num_verts * std::mem::size_of::<Vec3>()
+            + num_indices * std::mem::size_of::<u16>()
+            + bvh::STree::<u16>::bytes_for_capacity(Self::node_capacity(num_indices / 3))
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    pub fn verts(&self) -> &[Vec3] {
+        &self.verts
+    }
+
+    pub fn local_triangle(&self, triangle: u16) -> [Vec3; 3] {
+        let ti = triangle as usize * 3;
+        [
+            self.verts[self.indices[ti + 0] as usize],
+            self.verts[self.indices[ti + 1] as usize],
+            self.verts[self.indices[ti + 2] as usize],
+        ]
+    }
+
+    pub fn triangle(&self, triangle: u16, mesh_to_space: &STransform) -> [Vec3; 3] {
+        let local = self.local_triangle(triangle);
+        [
+            mesh_to_space.mul_point(&local[0]),
+            mesh_to_space.mul_point(&local[1]),
+            mesh_to_space.mul_point(&local[2]),
+        ]
+    }
+
+    // -- indices of every triangle whose bounds overlap aabb, which is in the mesh's local space.
+    // -- Returns false if out filled up before every triangle was found
+    pub fn query_local_aabb(&self, aabb: &SAABB, out: &mut SVec<u16>) -> bool {
+        self.bvh.query_aabb(aabb, out)
+    }
+
+    // -- every triangle overlapping aabb, with both the aabb and the output triangles in the space mesh_to_space maps to
+    pub fn triangles_in_aabb(&self, aabb: &SAABB, mesh_to_space: &STransform, out: &mut Vec<[Vec3; 3]>) {
+        if self.triangle_count() == 0 {
+            return;
+        }
+
+        let local_aabb = SAABB::transform(aabb, &mesh_to_space.inverse());
+        STACK_ALLOCATOR.with(|sa| {
+            let mut candidates = SVec::<u16>::new(&sa.as_ref(), self.triangle_count(), 0).unwrap();
+            self.bvh.query_aabb(&local_aabb, &mut candidates);
+
+            for ti in candidates.as_ref() {
+                let tri = self.triangle(*ti, mesh_to_space);
+                if SAABB::overlaps(aabb, &SAABB::new_from_points(&tri)) {
+                    out.push(tri);
+                }
+            }
+        });
+    }
+
+    // -- closest triangle hit by ray, which is in the space mesh_to_ray_space maps to
+    pub fn cast_ray(&self, ray: &SRay, mesh_to_ray_space: &STransform) -> Option<STriangleRayHit> {
+        if self.triangle_count() == 0 {
+            return None;
+        }
+
+        // -- the transform is affine, so t along the local ray matches t along the original
+        let ray_to_mesh = mesh_to_ray_space.inverse();
+        let local_ray = SRay {
+            origin: ray_to_mesh.mul_point(&ray.origin),
+            dir: ray_to_mesh.mul_vec(&ray.dir),
+        };
+
+        let mut result : Option<STriangleRayHit> = None;
+
+        STACK_ALLOCATOR.with(|sa| {
+            let mut candidates = SVec::<(f32, u16)>::new(&sa.as_ref(), self.triangle_count(), 0).unwrap();
+            self.bvh.cast_ray(&local_ray, &mut candidates);
+            candidates.as_mut_slice().sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+            for (box_t, ti) in candidates.as_ref() {
+                // -- candidates are sorted by where they enter their bounds, nothing past here can be closer
+                if *box_t >= result.map_or(std::f32::MAX, |r| r.t) {
+                    break;
+                }
+
+                let tri = self.local_triangle(*ti);
+                if let Some((t, barycentric)) = ray_intersects_triangle_barycentric(&local_ray.origin, &local_ray.dir, &tri[0], &tri[1], &tri[2]) {
+                    if t < result.map_or(std::f32::MAX, |r| r.t) {
+                        let local_normal = Vec3::cross(&(tri[1] - tri[0]), &(tri[2] - tri[0]));
+                        result = Some(STriangleRayHit {
+                            t,
+                            triangle: *ti,
+                            normal: mesh_to_ray_space.mul_vec(&local_normal).normalized(),
+                            barycentric,
+                        });
+                    }
+                }
+            }
+        });
+
+        result
+    }
+
+    // -- deepest penetration of shape into any one triangle, with shape as A and the triangle as B.
+    // -- shape is in the space mesh_to_shape_space maps to, and so is the result
+    pub fn convex_penetration<S: TConvexShape + ?Sized>(
+        &self,
+        shape: &S,
+        mesh_to_shape_space: &STransform,
+        settings: &SGJKSettings,
+    ) -> Option<SPenetration> {
+        if self.triangle_count() == 0 {
+            return None;
+        }
+
+        let local_aabb = SAABB::transform(&shape_aabb(shape), &mesh_to_shape_space.inverse());
+        let mut deepest : Option<SPenetration> = None;
+
+        STACK_ALLOCATOR.with(|sa| {
+            let mut candidates = SVec::<u16>::new(&sa.as_ref(), self.triangle_count(), 0).unwrap();
+            self.bvh.query_aabb(&local_aabb, &mut candidates);
+
+            for ti in candidates.as_ref() {
+                let tri = self.triangle(*ti, mesh_to_shape_space);
+                if let EGJKResult::Intersection(simplex) = gjk_intersection(shape, &tri[..], settings) {
+                    let penetration = match epa(shape, &tri[..], &simplex, settings) {
+                        EEPAResult::Penetration(p) | EEPAResult::DidNotConverge(p) => p,
+                    };
+                    // -- no edge smoothing, shapes sliding over a shared edge can pick up the edge's normal here
+                    if penetration.depth > deepest.map_or(std::f32::MIN, |d| d.depth) {
+                        deepest = Some(penetration);
+                    }
+                }
+            }
+        });
+
+        deepest
+    }
+}
+
+// -- a bumpy n x n grid of quads over [0, n] in x and z
+#[allow(dead_code)]
+fn test_grid_mesh(n: usize) -> STriangleMesh {
+    let mut verts = Vec::new();
+    for z in 0..=n {
+        for x in 0..=n {
+            let height = 0.25 * ((x * 7 + z * 3) % 5) as f32;
+            verts.push(Vec3::new(x as f32, height, z as f32));
+        }
+    }
+
+    let mut indices = Vec::new();
+    for z in 0..n {
+        for x in 0..n {
+            let i = (z * (n + 1) + x) as u16;
+            let row = (n + 1) as u16;
+            indices.extend_from_slice(&[i, i + row, i + 1, i + 1, i + row, i + row + 1]);
+        }
+    }
+
+    STriangleMesh::new(&verts, &indices).unwrap()
+}
+
+#[test]
+fn test_triangle_mesh_max_size() {
+    // -- the largest grid under MAX_TRIANGLES still fits its BVH in the node budget
+    let mesh = test_grid_mesh(127);
+    assert_eq!(mesh.triangle_count(), 2 * 127 * 127);
+    assert!(mesh.triangle_count() <= MAX_TRIANGLES);
+}
+
+#[test]
+fn test_triangle_mesh_cast_ray() {
+    use crate::math::{Quat};
+    use crate::utils::{ray_intersects_triangle};
+
+    let mesh = test_grid_mesh(16);
+    assert_eq!(mesh.triangle_count(), 512);
+
+    let mesh_to_world = STransform::new(
+        &Vec3::new(-3.0, 1.0, 2.0),
+        &Quat::new_angle_axis(0.4, &Vec3::new(0.0, 1.0, 0.0)),
+        2.0,
+    );
+
+    // -- every ray should find the same closest triangle the brute force loop does
+    for i in 0..64 {
+        let origin = Vec3::new(-8.0 + 0.5 * i as f32, 10.0, 4.0 + 0.3 * i as f32);
+        let ray = SRay{ origin, dir: Vec3::new(0.1 * (i % 5) as f32, -1.0, 0.05 * (i % 3) as f32) };
+
+        let mut brute_force : Option<(f32, u16)> = None;
+        for ti in 0..mesh.triangle_count() as u16 {
+            let tri = mesh.triangle(ti, &mesh_to_world);
+            if let Some(t) = ray_intersects_triangle(&ray.origin, &ray.dir, &tri[0], &tri[1], &tri[2]) {
+                if t < brute_force.map_or(std::f32::MAX, |b| b.0) {
+                    brute_force = Some((t, ti));
+                }
+            }
+        }
+
+        let hit = mesh.cast_ray(&ray, &mesh_to_world);
+        match (hit, brute_force) {
+            (Some(hit), Some((t, _))) => {
+                assert!((hit.t - t).abs() < 0.0001);
+                assert!(hit.normal.y > 0.0);
+
+                // -- the barycentrics put us back on the ray
+                let tri = mesh.triangle(hit.triangle, &mesh_to_world);
+                let point = hit.barycentric.x * tri[0] + hit.barycentric.y * tri[1] + hit.barycentric.z * tri[2];
+                assert!((point - (ray.origin + hit.t * ray.dir)).mag() < 0.001);
+            },
+            (None, None) => {},
+            _ => panic!("BVH ray cast disagrees with brute force"),
+        }
+    }
+
+    let miss = SRay{ origin: Vec3::new(0.0, 10.0, 0.0), dir: Vec3::new(0.0, 1.0, 0.0) };
+    assert!(mesh.cast_ray(&miss, &mesh_to_world).is_none());
+}
+
+#[test]
+fn test_triangle_mesh_convex_queries() {
+    use crate::shapes::{SBox, STransformedShape};
+
+    let mesh = test_grid_mesh(8);
+    let flat = STriangleMesh::new(
+        &[Vec3::new(-5.0, 0.0, -5.0), Vec3::new(5.0, 0.0, -5.0), Vec3::new(-5.0, 0.0, 5.0), Vec3::new(5.0, 0.0, 5.0)],
+        &[0, 2, 1, 1, 2, 3],
+    ).unwrap();
+    let identity = STransform::default();
+
+    let mut triangles = Vec::new();
+    let aabb = SAABB{ min: Vec3::new(2.1, -1.0, 2.1), max: Vec3::new(2.9, 5.0, 2.9) };
+    mesh.triangles_in_aabb(&aabb, &identity, &mut triangles);
+    assert_eq!(triangles.len(), 2);
+
+    // -- a box sunk 0.1 into the floor gets pushed straight back up
+    let settings = SGJKSettings::default();
+    let sunk_box = STransformedShape::new(SBox{ half_extents: Vec3::new(0.5, 0.5, 0.5) }, STransform::new_translation(&Vec3::new(0.3, 0.4, -0.2)));
+    let penetration = flat.convex_penetration(&sunk_box, &identity, &settings).unwrap();
+    assert!((penetration.depth - 0.1).abs() < 0.001);
+    assert!(penetration.normal.y < -0.999);
+
+    let floating_box = STransformedShape::new(SBox{ half_extents: Vec3::new(0.5, 0.5, 0.5) }, STransform::new_translation(&Vec3::new(0.3, 0.6, -0.2)));
+    assert!(flat.convex_penetration(&floating_box, &identity, &settings).is_none());
+
+    assert!(STriangleMesh::new(&[Vec3::zero(); 3], &[0, 1]).is_err());
+    assert!(STriangleMesh::bytes_for(4, 6) > 0);
+}
@@ -9,7 +9,7 @@ use gltf;
 
 pub static PI : f32 = 3.14159265358979;
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct STransform {
     pub t: Vec3,
     pub r: Quat,
@@ -34,6 +34,26 @@ pub struct SAABB {
     pub max: Vec3,
 }
 
+// -- world space volumes for overlap queries, unlike shapes they carry their own placement
+#[derive(Clone, Copy, Debug)]
+pub struct SBoundingSphere {
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SOBB {
+    pub center: Vec3,
+    pub half_extents: Vec3,
+    pub rotation: Quat,
+}
+
+// -- planes' normals point into the frustum
+#[derive(Clone, Copy)]
+pub struct SFrustum {
+    pub planes: [SPlane; 6],
+}
+
 impl SAABB {
     pub fn new(p: &Vec3) -> Self {
         Self {
@@ -84,6 +104,23 @@ impl SAABB {
         result
     }
 
+    pub fn overlaps(a: &Self, b: &Self) -> bool {
+        a.min.x <= b.max.x && a.max.x >= b.min.x &&
+        a.min.y <= b.max.y && a.max.y >= b.min.y &&
+        a.min.z <= b.max.z && a.max.z >= b.min.z
+    }
+
+    // -- whether inner is entirely inside outer
+    pub fn contains(outer: &Self, inner: &Self) -> bool {
+        outer.min.x <= inner.min.x && outer.max.x >= inner.max.x &&
+        outer.min.y <= inner.min.y && outer.max.y >= inner.max.y &&
+        outer.min.z <= inner.min.z && outer.max.z >= inner.max.z
+    }
+
+    pub fn center(&self) -> Vec3 {
+        0.5 * (self.min + self.max)
+    }
+
     pub fn surface_area(&self) -> f32 {
         let d = self.max - self.min;
         return 2.0 * (d.x * d.y + d.y * d.z + d.z * d.x);
@@ -93,6 +130,131 @@ impl SAABB {
         self.min = Vec3::min(&self.min, p);
         self.max = Vec3::max(&self.max, p);
     }
+
+    pub fn closest_point(&self, p: &Vec3) -> Vec3 {
+        Vec3::min(&Vec3::max(p, &self.min), &self.max)
+    }
+
+    // -- zero when p is inside
+    pub fn sqdistance_to_point(&self, p: &Vec3) -> f32 {
+        (self.closest_point(p) - p).sqmag()
+    }
+}
+
+impl SBoundingSphere {
+    pub fn overlaps_aabb(&self, aabb: &SAABB) -> bool {
+        aabb.sqdistance_to_point(&self.center) <= self.radius * self.radius
+    }
+}
+
+impl SOBB {
+    pub fn axis(&self, i: usize) -> Vec3 {
+        let mut local_axis = Vec3::zero();
+        local_axis[i] = 1.0;
+        Quat::rotate_vec3(&self.rotation, &local_axis)
+    }
+
+    // -- separating axis test over the 3 world axes, the 3 box axes and the 9 crosses between them
+    pub fn overlaps_aabb(&self, aabb: &SAABB) -> bool {
+        let aabb_center = aabb.center();
+        let aabb_half_extents = 0.5 * (aabb.max - aabb.min);
+        let obb_axes = [self.axis(0), self.axis(1), self.axis(2)];
+        let offset = self.center - aabb_center;
+
+        let separated_on = |axis: &Vec3| -> bool {
+            // -- crosses of parallel axes are degenerate and can't separate anything
+            if axis.sqmag() < 0.000_001 {
+                return false;
+            }
+
+            let aabb_radius = axis.x.abs() * aabb_half_extents.x
+                + axis.y.abs() * aabb_half_extents.y
+                + axis.z.abs() * aabb_half_extents.z;
+            let obb_radius = Vec3::dot(axis, &obb_axes[0]).abs() * self.half_extents.x
+                + Vec3::dot(axis, &obb_axes[1]).abs() * self.half_extents.y
+                + Vec3::dot(axis, &obb_axes[2]).abs() * self.half_extents.z;
+
+            Vec3::dot(axis, &offset).abs() > aabb_radius + obb_radius
+        };
+
+        for i in 0..3 {
+            let mut world_axis = Vec3::zero();
+            world_axis[i] = 1.0;
+            if separated_on(&world_axis) || separated_on(&obb_axes[i]) {
+                return false;
+            }
+
+            for obb_axis in &obb_axes {
+                if separated_on(&Vec3::cross(&world_axis, obb_axis)) {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+
+    pub fn aabb(&self) -> SAABB {
+        let mut extent = Vec3::zero();
+        for i in 0..3 {
+            let axis = self.axis(i);
+            extent = extent + self.half_extents[i] * Vec3::new(axis.x.abs(), axis.y.abs(), axis.z.abs());
+        }
+        SAABB {
+            min: self.center - extent,
+            max: self.center + extent,
+        }
+    }
+}
+
+impl SFrustum {
+    pub fn new_perspective(origin: &Vec3, forward: &Vec3, up: &Vec3, fovy: f32, aspect_wh: f32, znear: f32, zfar: f32) -> Self {
+        let forward = forward.normalized();
+        let right = Vec3::cross(&forward, up).normalized();
+        let up = Vec3::cross(&right, &forward).normalized();
+
+        let half_height = (0.5 * fovy).tan();
+        let half_width = half_height * aspect_wh;
+
+        // -- the side planes go through the origin, and the view direction is always inside them
+        let side_plane = |edge_dir: Vec3, along_edge: &Vec3| -> SPlane {
+            let mut normal = Vec3::cross(&edge_dir, along_edge).normalized();
+            if Vec3::dot(&normal, &forward) < 0.0 {
+                normal = -normal;
+            }
+            SPlane::new(origin, &normal)
+        };
+
+        Self {
+            planes: [
+                SPlane::new(&(origin + znear * forward), &forward),
+                SPlane::new(&(origin + zfar * forward), &-forward),
+                side_plane(forward + half_width * right, &up),
+                side_plane(forward - half_width * right, &up),
+                side_plane(forward + half_height * up, &right),
+                side_plane(forward - half_height * up, &right),
+            ],
+        }
+    }
+
+    // -- conservative, boxes just outside a corner where two planes meet can still pass
+    pub fn overlaps_aabb(&self, aabb: &SAABB) -> bool {
+        for plane in &self.planes {
+            // -- the corner furthest along the normal, if that's outside the whole box is
+            let mut furthest = aabb.min;
+            for i in 0..3 {
+                if plane.normal[i] >= 0.0 {
+                    furthest[i] = aabb.max[i];
+                }
+            }
+
+            if plane.signed_distance(&furthest) < 0.0 {
+                return false;
+            }
+        }
+
+        true
+    }
 }
 
 pub fn align_up(size: usize, align: usize) -> usize {
@@ -125,6 +287,14 @@ where T: std::ops::Sub<Output = T> + std::ops::Add<Output = T> + std::ops::Mul<f
     start + (end - start) * t
 }
 
+pub fn snap_to_increment(val: f32, increment: f32) -> f32 {
+    if increment <= 0.0 {
+        return val;
+    }
+
+    (val / increment).round() * increment
+}
+
 pub fn unlerp_f32(start: f32, end: f32, cur: f32) -> f32 {
     assert!(start < end);
     (cur - start) / (end - start)
@@ -133,6 +303,9 @@ pub fn unlerp_f32(start: f32, end: f32, cur: f32) -> f32 {
 pub fn closest_point_on_line(line_p0: &Vec3, line_p1: &Vec3, p: &Vec3) -> (Vec3, f32) {
     let line_dir = line_p1 - line_p0;
     let line_len = (line_p1 - line_p0).mag();
+    if line_len < 0.0001 {
+        return (*line_p0, 0.0);
+    }
     let line_dir_norm = (1.0 / line_len) * line_dir;
 
     let dist_along : f32 = Vec3::dot(&(p - line_p0), &line_dir_norm);
@@ -221,6 +394,16 @@ pub fn ray_intersects_triangle(
     t0p: &Vec3,
     t1p: &Vec3,
     t2p: &Vec3) -> Option<f32> {
+    ray_intersects_triangle_barycentric(ray_origin, ray_dir, t0p, t1p, t2p).map(|(t, _)| t)
+}
+
+// -- also returns the barycentric weights of t0p, t1p and t2p at the hit
+pub fn ray_intersects_triangle_barycentric(
+    ray_origin: &Vec3,
+    ray_dir: &Vec3,
+    t0p: &Vec3,
+    t1p: &Vec3,
+    t2p: &Vec3) -> Option<(f32, Vec3)> {
 
     //let ray_dir_norm = ray_dir.normalized();
 
@@ -253,7 +436,7 @@ pub fn ray_intersects_triangle(
 
     let t = f * Vec3::dot(&edge2, &q);
     if t > 0.0 {
-        return Some(t); // t may be >1.0
+        return Some((t, Vec3::new(1.0 - u - v, u, v))); // t may be >1.0
     }
 
     return None;
@@ -266,6 +449,11 @@ impl SPlane {
             normal: plane_normal.clone(),
         }
     }
+
+    // -- positive on the side the normal points to
+    pub fn signed_distance(&self, point: &Vec3) -> f32 {
+        Vec3::dot(&(point - self.p), &self.normal)
+    }
 }
 
 pub fn ray_plane_intersection(ray: &SRay, plane: &SPlane) -> Option<(Vec3, f32)> {
@@ -331,13 +519,14 @@ impl STransform {
     }
 
     pub fn inverse(&self) -> Self {
-        break_assert!(self.s == 1.0); // didn't figure this out for non-1.0 scales yet
+        // -- scale is uniform, so it commutes with the rotation
         let r_inverse = self.r.inverse();
+        let s_inverse = 1.0 / self.s;
 
         Self {
-            t: Quat::rotate_vec3(&r_inverse, &(-self.t)),
+            t: s_inverse * Quat::rotate_vec3(&r_inverse, &(-self.t)),
             r: r_inverse,
-            s: 1.0,
+            s: s_inverse,
         }
     }
 
@@ -61,4 +61,8 @@ pub fn GET_Y_LPARAM(lparam: LPARAM) -> i32 {
     (lparam.0 >> 32) as i32
 }
 
+pub fn GET_WHEEL_DELTA_WPARAM(wparam: WPARAM) -> i16 {
+    ((wparam.0 >> 16) & 0xffff) as i16
+}
+
 